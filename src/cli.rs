@@ -11,11 +11,11 @@ use crate::{
     operations::safe_net::connect,
     shell,
     subcommands::{
-        auth::auth_commander, cat::cat_commander, config::config_commander, dog::dog_commander,
-        files::files_commander, keys::key_commander, networks::networks_commander,
-        node::node_commander, nrs::nrs_commander, seq::seq_commander, setup::setup_commander,
-        update::update_commander, wallet::wallet_commander, xorurl::xorurl_commander, OutputFmt,
-        SubCommands,
+        auth::auth_commander, cache::cache_commander, cat::cat_commander, config::config_commander,
+        dog::dog_commander, files::files_commander, keys::key_commander,
+        networks::networks_commander, node::node_commander, nrs::nrs_commander, seq::seq_commander,
+        setup::setup_commander, update::update_commander, wallet::wallet_commander,
+        xorurl::xorurl_commander, OutputFmt, SubCommands,
     },
 };
 use anyhow::{anyhow, Result};
@@ -45,6 +45,9 @@ pub struct CmdArgs {
     /// Base encoding to be used for XOR-URLs generated. Currently supported: base32z (default), base32 and base64
     #[structopt(long = "xorurl", global(true))]
     xorurl_base: Option<XorUrlBase>,
+    /// Don't read or store fetched files in the local cache
+    #[structopt(long = "no-cache", global(true))]
+    no_cache: bool,
     /// Endpoint of the Authenticator daemon where to send requests to. If not provided, https://localhost:33000 is assumed.
     #[structopt(long = "endpoint", global(true))]
     pub endpoint: Option<String>,
@@ -100,6 +103,7 @@ pub async fn run_with(cmd_args: Option<&[&str]>, safe: &mut Safe) -> Result<()>
         }) => xorurl_commander(cmd, location, recursive, follow_links, output_fmt, safe).await,
         Some(SubCommands::Node { cmd }) => node_commander(cmd).await,
         Some(SubCommands::Auth { cmd }) => auth_commander(cmd, args.endpoint, safe).await,
+        Some(SubCommands::Cache { cmd }) => cache_commander(cmd, output_fmt, safe).await,
         Some(other) => {
             // We treat these commands separatelly since we use the credentials if they are
             // available to connect to the network with them (unless dry-run was set),
@@ -111,10 +115,12 @@ pub async fn run_with(cmd_args: Option<&[&str]>, safe: &mut Safe) -> Result<()>
 
             match other {
                 SubCommands::Keys(cmd) => key_commander(cmd, output_fmt, safe).await,
-                SubCommands::Cat(cmd) => cat_commander(cmd, output_fmt, safe).await,
+                SubCommands::Cat(cmd) => cat_commander(cmd, output_fmt, !args.no_cache, safe).await,
                 SubCommands::Dog(cmd) => dog_commander(cmd, output_fmt, safe).await,
                 SubCommands::Wallet(cmd) => wallet_commander(cmd, output_fmt, safe).await,
-                SubCommands::Files(cmd) => files_commander(cmd, output_fmt, args.dry, safe).await,
+                SubCommands::Files(cmd) => {
+                    files_commander(cmd, output_fmt, args.dry, !args.no_cache, safe).await
+                }
                 SubCommands::Nrs(cmd) => nrs_commander(cmd, output_fmt, args.dry, safe).await,
                 SubCommands::Seq(cmd) => seq_commander(cmd, output_fmt, safe).await,
                 _ => Err(anyhow!("Unknown safe subcommand")),
@@ -0,0 +1,503 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::config::get_cli_config_path;
+use anyhow::{bail, Context, Result};
+use log::{debug, trace, warn};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use sn_api::{fetch::Range, SafeUrl};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{ErrorKind, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+const CACHE_DIRNAME: &str = "cache";
+const CACHE_INDEX_FILENAME: &str = "index.json";
+const CACHE_LOCK_FILENAME: &str = "index.lock";
+
+// How long to wait for the lock on the index held by another process, and
+// how old a lock must be to be taken over, e.g. if its process was killed.
+const CACHE_LOCK_TIMEOUT: Duration = Duration::from_secs(15);
+const CACHE_LOCK_STALE: Duration = Duration::from_secs(10);
+
+// Max total size of the blobs kept in the cache (1 GiB), the least
+// recently used blobs are evicted when it's exceeded.
+pub const CACHE_SIZE_CAP: u64 = 1024 * 1024 * 1024;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct CacheEntry {
+    size: u64,
+    // value of the index's clock the last time the blob was used.
+    last_used: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+struct CacheIndex {
+    // incremented every time a blob is used, to order them by last use.
+    clock: u64,
+    blobs: BTreeMap<String, CacheEntry>,
+    // set when the index was changed but not yet written to disk.
+    #[serde(skip)]
+    dirty: bool,
+    // blobs removed since the index was last written, so they are not
+    // brought back when it's merged with the index on disk.
+    #[serde(skip)]
+    removed: BTreeSet<String>,
+}
+
+impl CacheIndex {
+    fn touch(&mut self, key: &str) {
+        self.clock += 1;
+        let clock = self.clock;
+        if let Some(entry) = self.blobs.get_mut(key) {
+            entry.last_used = clock;
+            self.dirty = true;
+        }
+    }
+
+    fn total_size(&self) -> u64 {
+        self.blobs.values().map(|entry| entry.size).sum()
+    }
+}
+
+/// # Statistics of the local blob cache, as reported by `safe cache stats`.
+#[derive(Debug, Serialize)]
+pub struct CacheStats {
+    pub location: String,
+    pub blobs: u64,
+    pub size: u64,
+    pub size_cap: u64,
+}
+
+/// # On-disk cache of the immutable blobs fetched from the network.
+///
+/// Each blob is stored in a file named after its XorName, so the same
+/// content is reused whatever the URL it was fetched with. An index keeps
+/// the size and last use of each blob, to evict the least recently used
+/// ones once the total size exceeds the cap. Several processes can use the
+/// cache at the same time, the index being merged with the one on disk,
+/// under a lock, every time it's written.
+#[derive(Debug)]
+pub struct BlobCache {
+    dir: PathBuf,
+    size_cap: u64,
+    index: Mutex<CacheIndex>,
+}
+
+impl BlobCache {
+    // Opens the cache at `~/.safe/cli/cache`, creating it if it doesn't exist.
+    pub fn open() -> Result<Self> {
+        let dir = get_cli_config_path()?.join(CACHE_DIRNAME);
+        Self::open_at(dir, CACHE_SIZE_CAP)
+    }
+
+    // Opens the cache, only warning if it can't be opened since
+    // the content can still be fetched from the network.
+    pub fn open_or_warn() -> Option<Self> {
+        match Self::open() {
+            Ok(cache) => Some(cache),
+            Err(err) => {
+                warn!("Local cache couldn't be opened, it won't be used: {}", err);
+                None
+            }
+        }
+    }
+
+    pub fn open_at(dir: PathBuf, size_cap: u64) -> Result<Self> {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Couldn't create cache folder at '{}'", dir.display()))?;
+
+        let index_path = dir.join(CACHE_INDEX_FILENAME);
+        let index = if index_path.exists() {
+            match read_index(&index_path) {
+                Some(index) => index,
+                None => {
+                    warn!(
+                        "Cache index at '{}' couldn't be read, rebuilding it",
+                        index_path.display()
+                    );
+                    rebuild_index(&dir)?
+                }
+            }
+        } else {
+            CacheIndex::default()
+        };
+
+        debug!(
+            "Blob cache at '{}' opened with {} blobs",
+            dir.display(),
+            index.blobs.len()
+        );
+        Ok(Self {
+            dir,
+            size_cap,
+            index: Mutex::new(index),
+        })
+    }
+
+    // Returns the content of a blob, or of a range of it, if it's in the cache.
+    pub fn get(&self, url: &str, range: Range) -> Option<Vec<u8>> {
+        let key = blob_key(url).ok()?;
+        let size = {
+            let mut index = self.lock_index();
+            let size = index.blobs.get(&key)?.size;
+            index.touch(&key);
+            size
+        };
+
+        let (start, end) = match range {
+            None => (0, size),
+            Some((start, end)) => (start.unwrap_or(0), end.unwrap_or(size).min(size)),
+        };
+        if start > end {
+            return None;
+        }
+
+        match read_range(&self.blob_path(&key), start, end - start) {
+            Ok(data) => {
+                trace!("Cache hit for range [{}..{}) of {}", start, end, url);
+                Some(data)
+            }
+            Err(err) => {
+                warn!("Removing unreadable blob {} from cache: {}", key, err);
+                if let Err(err) = self.remove(&key) {
+                    warn!("{}", err);
+                }
+                None
+            }
+        }
+    }
+
+    // Stores the content of a blob in the cache.
+    pub fn insert(&self, url: &str, data: &[u8]) -> Result<()> {
+        let key = blob_key(url)?;
+        let size = data.len() as u64;
+        if self.lock_index().blobs.contains_key(&key) || size > self.size_cap {
+            return Ok(());
+        }
+
+        let tmp_path = self.tmp_path(&key);
+        fs::write(&tmp_path, data)
+            .with_context(|| format!("Failed to write blob to '{}'", tmp_path.display()))?;
+        self.add_blob(&key, &tmp_path, size)
+    }
+
+    // Stores the content of a blob in the cache from a local file which
+    // contains it, e.g. a file which has just been downloaded.
+    pub fn insert_file(&self, url: &str, path: &Path) -> Result<()> {
+        let key = blob_key(url)?;
+        let size = fs::metadata(path)
+            .with_context(|| format!("Failed to read metadata of '{}'", path.display()))?
+            .len();
+        if self.lock_index().blobs.contains_key(&key) || size > self.size_cap {
+            return Ok(());
+        }
+
+        let tmp_path = self.tmp_path(&key);
+        fs::copy(path, &tmp_path)
+            .with_context(|| format!("Failed to copy '{}' into the cache", path.display()))?;
+        self.add_blob(&key, &tmp_path, size)
+    }
+
+    // Removes a blob from the cache.
+    pub fn remove(&self, key: &str) -> Result<()> {
+        let mut index = self.lock_index();
+        if index.blobs.remove(key).is_some() {
+            index.dirty = true;
+            index.removed.insert(key.to_string());
+            remove_blob_file(&self.blob_path(key))?;
+        }
+        self.write_index(&mut index)
+    }
+
+    // Removes all the blobs from the cache, returning how many were removed,
+    // including those stored by other processes since it was opened.
+    pub fn clear(&self) -> Result<u64> {
+        let mut index = self.lock_index();
+        let _lock = IndexLock::acquire(&self.dir)?;
+        self.merge_index(&mut index)?;
+        let keys: Vec<String> = index.blobs.keys().cloned().collect();
+        for key in keys.iter() {
+            remove_blob_file(&self.blob_path(key))?;
+        }
+        *index = CacheIndex::default();
+        index.dirty = true;
+        self.save_index(&mut index)?;
+
+        Ok(keys.len() as u64)
+    }
+
+    // Returns the key, i.e. hex encoded XorName, and local path of each blob.
+    pub fn blobs(&self) -> Vec<(String, PathBuf)> {
+        self.lock_index()
+            .blobs
+            .keys()
+            .map(|key| (key.clone(), self.blob_path(key)))
+            .collect()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let index = self.lock_index();
+        CacheStats {
+            location: self.dir.display().to_string(),
+            blobs: index.blobs.len() as u64,
+            size: index.total_size(),
+            size_cap: self.size_cap,
+        }
+    }
+
+    // Private helpers
+
+    fn lock_index(&self) -> MutexGuard<CacheIndex> {
+        // The index is always left consistent, even if a thread
+        // panicked while holding the lock.
+        self.index
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn blob_path(&self, key: &str) -> PathBuf {
+        self.dir.join(key)
+    }
+
+    // Returns a path to write a blob to before moving it into the cache,
+    // unique to each write so concurrent ones don't clash.
+    fn tmp_path(&self, key: &str) -> PathBuf {
+        let random: String = thread_rng().sample_iter(&Alphanumeric).take(10).collect();
+        self.dir.join(format!("{}.{}.tmp", key, random))
+    }
+
+    // Moves a blob written to a temporary file into the cache,
+    // evicting the least recently used blobs if needed.
+    fn add_blob(&self, key: &str, tmp_path: &Path, size: u64) -> Result<()> {
+        let blob_path = self.blob_path(key);
+        fs::rename(tmp_path, &blob_path)
+            .with_context(|| format!("Failed to store blob at '{}'", blob_path.display()))?;
+
+        let mut index = self.lock_index();
+        index
+            .blobs
+            .insert(key.to_string(), CacheEntry { size, last_used: 0 });
+        index.removed.remove(key);
+        index.touch(key);
+        index.dirty = true;
+
+        self.write_index(&mut index)
+    }
+
+    // Writes the index to disk, merged with the changes
+    // other processes made to it since it was read.
+    fn write_index(&self, index: &mut CacheIndex) -> Result<()> {
+        if !index.dirty {
+            return Ok(());
+        }
+
+        let _lock = IndexLock::acquire(&self.dir)?;
+        self.merge_index(index)?;
+        self.save_index(index)
+    }
+
+    // Merges the index on disk into the one in memory, with the lock held.
+    // The blob files found in the cache folder are the ones indexed, thus
+    // those stored or evicted by other processes are accounted for, and the
+    // least recently used are then evicted if the size cap is exceeded.
+    fn merge_index(&self, index: &mut CacheIndex) -> Result<()> {
+        let on_disk = read_index(&self.dir.join(CACHE_INDEX_FILENAME)).unwrap_or_default();
+        let mut blobs = BTreeMap::new();
+        for (key, size) in blob_files(&self.dir)? {
+            if index.removed.contains(&key) {
+                continue;
+            }
+            let last_used = [&index.blobs, &on_disk.blobs]
+                .iter()
+                .filter_map(|blobs| blobs.get(&key))
+                .map(|entry| entry.last_used)
+                .max()
+                .unwrap_or(0);
+            blobs.insert(key, CacheEntry { size, last_used });
+        }
+        index.blobs = blobs;
+        index.clock = index.clock.max(on_disk.clock);
+        index.removed.clear();
+
+        while index.total_size() > self.size_cap {
+            let lru_key = match index
+                .blobs
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            {
+                Some(lru_key) => lru_key,
+                None => break,
+            };
+            debug!("Evicting blob {} from cache", lru_key);
+            index.blobs.remove(&lru_key);
+            remove_blob_file(&self.blob_path(&lru_key))?;
+        }
+
+        Ok(())
+    }
+
+    // Writes the index to disk, with the lock held.
+    fn save_index(&self, index: &mut CacheIndex) -> Result<()> {
+        let index_path = self.dir.join(CACHE_INDEX_FILENAME);
+        let tmp_path = self.tmp_path(CACHE_INDEX_FILENAME);
+        let serialised_index =
+            serde_json::to_string(&*index).context("Failed to serialise cache index")?;
+        fs::write(&tmp_path, serialised_index)
+            .and_then(|()| fs::rename(&tmp_path, &index_path))
+            .with_context(|| {
+                format!("Unable to write cache index to '{}'", index_path.display())
+            })?;
+        index.dirty = false;
+
+        Ok(())
+    }
+}
+
+impl Drop for BlobCache {
+    fn drop(&mut self) {
+        // Blobs read from the cache only update their last use in memory.
+        let mut index = self.lock_index();
+        if let Err(err) = self.write_index(&mut index) {
+            warn!("{}", err);
+        }
+    }
+}
+
+// Returns the key a blob is cached with, i.e. its XorName hex encoded.
+pub fn blob_key(url: &str) -> Result<String> {
+    let safeurl = SafeUrl::from_url(url)?;
+    Ok(hex::encode(safeurl.xorname().0))
+}
+
+fn read_index(path: &Path) -> Option<CacheIndex> {
+    fs::read(path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+}
+
+// Recreates the index from the blob files found in the cache folder,
+// in case it was lost or corrupted. The last use of the blobs is lost.
+fn rebuild_index(dir: &Path) -> Result<CacheIndex> {
+    let blobs = blob_files(dir)?
+        .into_iter()
+        .map(|(key, size)| (key, CacheEntry { size, last_used: 0 }))
+        .collect();
+
+    Ok(CacheIndex {
+        blobs,
+        dirty: true,
+        ..CacheIndex::default()
+    })
+}
+
+// Returns the key and size of each blob file in the cache folder,
+// i.e. the files named after a hex encoded XorName.
+fn blob_files(dir: &Path) -> Result<BTreeMap<String, u64>> {
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read cache folder at '{}'", dir.display()))?;
+    let mut blobs = BTreeMap::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_blob = name.len() == 64 && name.chars().all(|c| c.is_ascii_hexdigit());
+        if let (true, Ok(metadata)) = (is_blob, entry.metadata()) {
+            blobs.insert(name, metadata.len());
+        }
+    }
+
+    Ok(blobs)
+}
+
+// Lock on the index shared by all the processes using the cache, held while
+// the index is merged and written, as a file which only one can create.
+struct IndexLock {
+    path: PathBuf,
+}
+
+impl IndexLock {
+    fn acquire(dir: &Path) -> Result<Self> {
+        let path = dir.join(CACHE_LOCK_FILENAME);
+        let started = Instant::now();
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(_) => return Ok(Self { path }),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                    if is_stale_lock(&path) {
+                        warn!("Taking over stale cache lock at '{}'", path.display());
+                        let _ = fs::remove_file(&path);
+                    } else if started.elapsed() > CACHE_LOCK_TIMEOUT {
+                        bail!(
+                            "Timed out waiting for the cache lock at '{}'",
+                            path.display()
+                        );
+                    } else {
+                        thread::sleep(Duration::from_millis(20));
+                    }
+                }
+                Err(err) => {
+                    return Err(err).with_context(|| {
+                        format!("Failed to create cache lock at '{}'", path.display())
+                    })
+                }
+            }
+        }
+    }
+}
+
+impl Drop for IndexLock {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            warn!(
+                "Failed to remove cache lock at '{}': {}",
+                self.path.display(),
+                err
+            );
+        }
+    }
+}
+
+fn is_stale_lock(path: &Path) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .map_or(false, |age| age > CACHE_LOCK_STALE)
+}
+
+fn read_range(path: &Path, offset: u64, len: u64) -> Result<Vec<u8>> {
+    let mut file =
+        fs::File::open(path).with_context(|| format!("Failed to open '{}'", path.display()))?;
+    file.seek(SeekFrom::Start(offset))
+        .with_context(|| format!("Error seeking in '{}'", path.display()))?;
+    let mut data = vec![0; len as usize];
+    file.read_exact(&mut data)
+        .with_context(|| format!("Failed to read '{}'", path.display()))?;
+
+    Ok(data)
+}
+
+fn remove_blob_file(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            Err(err).with_context(|| format!("Failed to remove cached blob '{}'", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
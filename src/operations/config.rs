@@ -51,10 +51,32 @@ impl NetworkInfo {
     }
 }
 
+/// Size limits of the files uploaded with `files put`, `files sync` and `files add`
+#[derive(Deserialize, Debug, Serialize, Default, Clone, Copy, PartialEq)]
+pub struct UploadLimits {
+    /// Max size of a single file, in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_file_size: Option<u64>,
+    /// Max size of all the files uploaded at once, in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_total_size: Option<u64>,
+}
+
+impl UploadLimits {
+    pub fn is_empty(&self) -> bool {
+        self.max_file_size.is_none() && self.max_total_size.is_none()
+    }
+}
+
 #[derive(Deserialize, Debug, Serialize, Default)]
 pub struct Settings {
     networks: BTreeMap<String, NetworkInfo>,
     // contacts: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "UploadLimits::is_empty")]
+    upload_limits: UploadLimits,
+    // media types assigned to the uploaded files, by their lowercase extension
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    media_types: BTreeMap<String, String>,
 }
 
 #[derive(Debug)]
@@ -184,6 +206,76 @@ impl Config {
         Ok(())
     }
 
+    pub fn upload_limits(&self) -> UploadLimits {
+        self.settings.upload_limits
+    }
+
+    pub fn set_upload_limits(&mut self, upload_limits: UploadLimits) -> Result<()> {
+        self.settings.upload_limits = upload_limits;
+        self.write_settings_to_file()?;
+
+        debug!("Upload limits set in settings: {:?}", upload_limits);
+        Ok(())
+    }
+
+    pub fn print_upload_limits(&self) {
+        let limits = self.upload_limits();
+        if limits.is_empty() {
+            return;
+        }
+        let display = |size: Option<u64>| size.map_or("-".to_string(), |s| format!("{} bytes", s));
+        let mut table = Table::new();
+        table.add_row(row![bFg->"Upload limits"]);
+        table.add_row(row!["Max file size", display(limits.max_file_size)]);
+        table.add_row(row!["Max total size", display(limits.max_total_size)]);
+        table.printstd();
+    }
+
+    pub fn media_types(&self) -> &BTreeMap<String, String> {
+        &self.settings.media_types
+    }
+
+    pub fn add_media_type(&mut self, extension: &str, media_type: &str) -> Result<()> {
+        self.settings
+            .media_types
+            .insert(extension.to_string(), media_type.to_string());
+        self.write_settings_to_file()?;
+
+        debug!(
+            "Media type '{}' set in settings for extension '{}'",
+            media_type, extension
+        );
+        Ok(())
+    }
+
+    pub fn remove_media_type(&mut self, extension: &str) -> Result<()> {
+        if self.settings.media_types.remove(extension).is_some() {
+            self.write_settings_to_file()?;
+            debug!(
+                "Media type for extension '{}' removed from config",
+                extension
+            );
+        } else {
+            println!(
+                "No media type for extension '{}' was found in config",
+                extension
+            );
+        }
+        Ok(())
+    }
+
+    pub fn print_media_types(&self) {
+        if self.settings.media_types.is_empty() {
+            return;
+        }
+        let mut table = Table::new();
+        table.add_row(row![bFg->"Extension", bFg->"Media type"]);
+        for (extension, media_type) in self.settings.media_types.iter() {
+            table.add_row(row![extension, media_type]);
+        }
+        table.printstd();
+    }
+
     pub fn clear(&mut self) -> Result<()> {
         self.settings = Settings::default();
         self.write_settings_to_file()
@@ -256,6 +348,26 @@ impl Config {
     }
 }
 
+// Reads the upload limits from the config file, without creating
+// the file if it doesn't exist, in which case there are no limits.
+pub fn read_upload_limits() -> Result<UploadLimits> {
+    let file_path = get_cli_config_path()?.join(CONFIG_FILENAME);
+    if !file_path.exists() {
+        return Ok(UploadLimits::default());
+    }
+    Ok(Config::read()?.upload_limits())
+}
+
+// Reads the media types set in the config settings, without creating
+// the config file if it doesn't exist yet.
+pub fn read_media_types() -> Result<BTreeMap<String, String>> {
+    let file_path = get_cli_config_path()?.join(CONFIG_FILENAME);
+    if !file_path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    Ok(Config::read()?.settings.media_types)
+}
+
 pub fn read_current_network_conn_info() -> Result<(PathBuf, HashSet<SocketAddr>)> {
     let (_, file_path) = get_current_network_conn_info_path()?;
     let current_conn_info = fs::read(&file_path).with_context(||
@@ -363,7 +475,7 @@ fn get_current_network_conn_info_path() -> Result<(PathBuf, PathBuf)> {
     ))
 }
 
-fn get_cli_config_path() -> Result<PathBuf> {
+pub fn get_cli_config_path() -> Result<PathBuf> {
     let mut project_data_path =
         dirs_next::home_dir().ok_or_else(|| anyhow!("Couldn't find user's home directory"))?;
     project_data_path.push(".safe");
@@ -9,6 +9,7 @@
 
 pub mod auth_and_connect;
 pub mod auth_daemon;
+pub mod cache;
 pub mod config;
 mod helpers;
 pub mod node;
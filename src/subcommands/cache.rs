@@ -0,0 +1,110 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    helpers::{pluralize, serialise_output},
+    OutputFmt,
+};
+use crate::operations::cache::{blob_key, BlobCache};
+use anyhow::Result;
+use log::{debug, warn};
+use sn_api::Safe;
+use std::fs;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+pub enum CacheSubCommands {
+    #[structopt(name = "stats")]
+    /// Show the number of blobs, and their total size, kept in the local cache
+    Stats,
+    #[structopt(name = "clear")]
+    /// Remove all the blobs kept in the local cache
+    Clear,
+    #[structopt(name = "verify")]
+    /// Check the content of the blobs kept in the local cache, removing those which are corrupted
+    Verify,
+}
+
+pub async fn cache_commander(
+    cmd: Option<CacheSubCommands>,
+    output_fmt: OutputFmt,
+    safe: &mut Safe,
+) -> Result<()> {
+    let cache = BlobCache::open()?;
+    match cmd {
+        Some(CacheSubCommands::Clear) => {
+            let removed = cache.clear()?;
+            if OutputFmt::Pretty == output_fmt {
+                println!(
+                    "{} {} removed from the local cache",
+                    removed,
+                    pluralize("blob", "blobs", removed)
+                );
+            } else {
+                println!("{}", serialise_output(&removed, output_fmt));
+            }
+        }
+        Some(CacheSubCommands::Verify) => {
+            // The XOR-URL of each blob is calculated from its content, without
+            // storing it on the network, and compared with the blob's XorName.
+            let blobs = cache.blobs();
+            let mut corrupted = Vec::<String>::new();
+            for (key, path) in blobs.iter() {
+                let valid = match fs::read(path) {
+                    Ok(data) => match safe.files_store_public_blob(&data, None, true).await {
+                        Ok(xorurl) => blob_key(&xorurl)? == *key,
+                        Err(err) => {
+                            warn!("Failed to calculate XOR-URL of blob {}: {}", key, err);
+                            false
+                        }
+                    },
+                    Err(err) => {
+                        warn!("Failed to read blob {}: {}", key, err);
+                        false
+                    }
+                };
+
+                if !valid {
+                    debug!("Removing corrupted blob {} from cache", key);
+                    cache.remove(key)?;
+                    corrupted.push(key.clone());
+                }
+            }
+
+            if OutputFmt::Pretty == output_fmt {
+                let count = blobs.len() as u64;
+                println!(
+                    "{} {} verified, {} corrupted",
+                    count,
+                    pluralize("blob", "blobs", count),
+                    corrupted.len()
+                );
+                for key in corrupted.iter() {
+                    println!("Removed corrupted blob: {}", key);
+                }
+            } else {
+                println!("{}", serialise_output(&corrupted, output_fmt));
+            }
+        }
+        Some(CacheSubCommands::Stats) | None => {
+            let stats = cache.stats();
+            if OutputFmt::Pretty == output_fmt {
+                println!("Local cache at '{}':", stats.location);
+                println!(
+                    "Blobs: {}   Size: {}   Size cap: {}",
+                    stats.blobs, stats.size, stats.size_cap
+                );
+            } else {
+                println!("{}", serialise_output(&stats, output_fmt));
+            }
+        }
+    }
+
+    Ok(())
+}
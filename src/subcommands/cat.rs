@@ -11,10 +11,11 @@ use super::{
     helpers::{get_from_arg_or_stdin, print_nrs_map, serialise_output},
     OutputFmt,
 };
-use anyhow::{Context, Result};
-use log::debug;
+use crate::operations::cache::BlobCache;
+use anyhow::{bail, Context, Result};
+use log::{debug, warn};
 use prettytable::Table;
-use sn_api::{fetch::SafeData, Safe};
+use sn_api::{fetch::SafeData, Safe, SafeDataType, SafeUrl};
 use std::io::{self, Write};
 use structopt::StructOpt;
 
@@ -27,10 +28,35 @@ pub struct CatCommands {
     hexdump: bool,
 }
 
-pub async fn cat_commander(cmd: CatCommands, output_fmt: OutputFmt, safe: &mut Safe) -> Result<()> {
+pub async fn cat_commander(
+    cmd: CatCommands,
+    output_fmt: OutputFmt,
+    use_cache: bool,
+    safe: &mut Safe,
+) -> Result<()> {
     let url = get_from_arg_or_stdin(cmd.location, None)?;
     debug!("Running cat for: {:?}", &url);
 
+    // TODO: render private files once sn_api supports retrieving private blobs.
+    if let Ok(safeurl) = SafeUrl::from_url(&url) {
+        if let SafeDataType::PrivateBlob = safeurl.data_type() {
+            bail!("Rendering private files is not supported yet: {}", url);
+        }
+    }
+
+    // Public blobs are immutable, thus they can be read from the local cache
+    let cache = if use_cache {
+        BlobCache::open_or_warn()
+    } else {
+        None
+    };
+    if let Some(data) = cache
+        .as_ref()
+        .and_then(|cache| read_public_blob_from_cache(cache, &url))
+    {
+        return print_blob(&data, cmd.hexdump);
+    }
+
     let content = safe.fetch(&url, None).await?;
     match &content {
         SafeData::FilesContainer {
@@ -61,16 +87,13 @@ pub async fn cat_commander(cmd: CatCommands, output_fmt: OutputFmt, safe: &mut S
                 println!("{}", serialise_output(&(url, files_map), output_fmt));
             }
         }
-        SafeData::PublicBlob { data, .. } => {
-            if cmd.hexdump {
-                // Render hex representation of Blob file
-                println!("{}", pretty_hex::pretty_hex(data));
-            } else {
-                // Render Blob file
-                io::stdout()
-                    .write_all(data)
-                    .context("Failed to print out the content of the file")?;
+        SafeData::PublicBlob { xorurl, data, .. } => {
+            if let Some(cache) = &cache {
+                if let Err(err) = cache.insert(xorurl, data) {
+                    warn!("Failed to store {} in local cache: {}", xorurl, err);
+                }
             }
+            print_blob(data, cmd.hexdump)?
         }
         SafeData::Wallet { balances, .. } => {
             // Render Wallet
@@ -143,3 +166,32 @@ pub async fn cat_commander(cmd: CatCommands, output_fmt: OutputFmt, safe: &mut S
 
     Ok(())
 }
+
+// Reads the content of a URL which targets a public blob from the local
+// cache, if it's there. Any other URL, e.g. the path of a file in a
+// FilesContainer, is first resolved with a fetch, which also retrieves the
+// blob, thus its content is only stored in the cache afterwards.
+fn read_public_blob_from_cache(cache: &BlobCache, url: &str) -> Option<Vec<u8>> {
+    let safeurl = SafeUrl::from_url(url).ok()?;
+    if !matches!(safeurl.data_type(), SafeDataType::PublicBlob) {
+        return None;
+    }
+
+    let data = cache.get(url, None)?;
+    debug!("Content of {} read from local cache", url);
+    Some(data)
+}
+
+fn print_blob(data: &[u8], hexdump: bool) -> Result<()> {
+    if hexdump {
+        // Render hex representation of Blob file
+        println!("{}", pretty_hex::pretty_hex(&data));
+    } else {
+        // Render Blob file
+        io::stdout()
+            .write_all(data)
+            .context("Failed to print out the content of the file")?;
+    }
+
+    Ok(())
+}
@@ -7,8 +7,9 @@
 // specific language governing permissions and limitations relating to use of the SAFE Network
 // Software.
 
+use super::{files_media_type::normalise_extension, helpers::parse_size};
 use crate::operations::config::{Config, NetworkInfo};
-use anyhow::Result;
+use anyhow::{bail, Result};
 use log::debug;
 use structopt::StructOpt;
 
@@ -34,6 +35,25 @@ pub enum SettingAddCmd {
         /// Location of the network connection information. If this argument is not passed, it takes current network connection information and caches it
         config_location: Option<String>,
     },
+    #[structopt(name = "max-file-size")]
+    MaxFileSize {
+        /// Max size of a file uploaded with files put, sync or add, e.g. 500MiB
+        #[structopt(parse(try_from_str = parse_size))]
+        size: u64,
+    },
+    #[structopt(name = "max-total-size")]
+    MaxTotalSize {
+        /// Max size of all the files uploaded at once with files put, sync or add, e.g. 2GiB
+        #[structopt(parse(try_from_str = parse_size))]
+        size: u64,
+    },
+    #[structopt(name = "media-type")]
+    MediaType {
+        /// Extension of the files uploaded with files put, sync or add, e.g. 'md'
+        extension: String,
+        /// Media type assigned to the files with this extension, e.g. 'text/markdown'
+        media_type: String,
+    },
     // #[structopt(name = "contact")]
     // Contact {
     //    /// Contact friendly name
@@ -50,6 +70,18 @@ pub enum SettingRemoveCmd {
         /// Network to remove
         network_name: String,
     },
+    #[structopt(name = "max-file-size")]
+    /// Remove the max size of an uploaded file
+    MaxFileSize,
+    #[structopt(name = "max-total-size")]
+    /// Remove the max size of all the files uploaded at once
+    MaxTotalSize,
+    #[structopt(name = "media-type")]
+    /// Remove the media type assigned to the files with an extension
+    MediaType {
+        /// Extension to remove the media type of
+        extension: String,
+    },
     // #[structopt(name = "contact")]
     // Contact {
     //    /// Name of the contact to remove
@@ -66,16 +98,60 @@ pub async fn config_commander(cmd: Option<ConfigSubCommands>) -> Result<()> {
         })) => {
             config.add_network(&network_name, config_location.map(NetworkInfo::ConnInfoUrl))?;
         }
+        Some(ConfigSubCommands::Add(SettingAddCmd::MaxFileSize { size })) => {
+            let mut limits = config.upload_limits();
+            limits.max_file_size = Some(size);
+            config.set_upload_limits(limits)?;
+        }
+        Some(ConfigSubCommands::Add(SettingAddCmd::MaxTotalSize { size })) => {
+            let mut limits = config.upload_limits();
+            limits.max_total_size = Some(size);
+            config.set_upload_limits(limits)?;
+        }
+        Some(ConfigSubCommands::Add(SettingAddCmd::MediaType {
+            extension,
+            media_type,
+        })) => {
+            if media_type
+                .split('/')
+                .filter(|part| !part.is_empty())
+                .count()
+                != 2
+            {
+                bail!(
+                    "'{}' is not a valid media type, it must be of the form 'type/subtype', e.g. 'text/markdown'",
+                    media_type
+                );
+            }
+            config.add_media_type(&normalise_extension(&extension), &media_type)?;
+        }
         // Some(ConfigSubCommands::Add(SettingAddCmd::Contact { name, safeid })) => {}
         Some(ConfigSubCommands::Remove(SettingRemoveCmd::Network { network_name })) => {
             config.remove_network(&network_name)?
         }
+        Some(ConfigSubCommands::Remove(SettingRemoveCmd::MaxFileSize)) => {
+            let mut limits = config.upload_limits();
+            limits.max_file_size = None;
+            config.set_upload_limits(limits)?;
+        }
+        Some(ConfigSubCommands::Remove(SettingRemoveCmd::MaxTotalSize)) => {
+            let mut limits = config.upload_limits();
+            limits.max_total_size = None;
+            config.set_upload_limits(limits)?;
+        }
+        Some(ConfigSubCommands::Remove(SettingRemoveCmd::MediaType { extension })) => {
+            config.remove_media_type(&normalise_extension(&extension))?;
+        }
         // Some(ConfigSubCommands::Remove(SettingRemoveCmd::Contact { name })) => {}
         Some(ConfigSubCommands::Clear) => {
             config.clear()?;
             debug!("Config settings cleared out");
         }
-        None => config.print_networks().await,
+        None => {
+            config.print_networks().await;
+            config.print_upload_limits();
+            config.print_media_types();
+        }
     }
 
     Ok(())
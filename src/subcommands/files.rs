@@ -8,25 +8,48 @@
 // Software.
 
 use super::{
-    files_get::{process_get_command, FileExistsAction, ProgressIndicator},
+    files_archive::{process_get_archive_command, stage_archive},
+    files_du::{du_files_map, print_du_report},
+    files_filter::{
+        location_filter_root, stage_empty_dir, stage_location, FilesFilter, StagedLocation,
+        SKIPPED_MARKER,
+    },
+    files_find::{parse_date, process_find_command, FindOptions},
+    files_get::{
+        check_exists_action, process_get_command, pull_files, FileExistsAction, FilesGetOptions,
+        ProgressIndicator, JOURNAL_FILE_NAME,
+    },
+    files_limits::{
+        enforce_upload_limits, enforce_upload_limits_on_content, local_files,
+        resolve_upload_limits, LocalFile,
+    },
+    files_media_type::{check_single_file, store_typed_files, MediaTypeRules, TypedFile},
+    files_symlinks::{ExternalSymlinkPolicy, SymlinkPolicy},
     helpers::{
         gen_processed_files_table, get_from_arg_or_stdin, get_from_stdin, if_tty, notice_dry_run,
-        parse_stdin_arg, pluralize, serialise_output,
+        parse_size, parse_stdin_arg, pluralize, serialise_output,
     },
     OutputFmt,
 };
+#[cfg(target_os = "linux")]
+use super::{files_mount::process_mount_command, files_watch::LocationWatcher};
+use crate::operations::config::UploadLimits;
 use ansi_term::Colour;
 use anyhow::{bail, Context, Result};
-use log::debug;
+#[cfg(target_os = "linux")]
+use chrono::Local;
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
 use prettytable::{format::FormatBuilder, Table};
-use serde::Serialize;
+use serde::{Serialize, Serializer};
 use sn_api::{
-    fetch::SafeData,
+    fetch::{SafeContentType, SafeData},
     files::{FilesMap, ProcessedFiles},
     Safe, SafeUrl, XorUrl,
 };
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
     path::{Component, Path},
 };
 use structopt::StructOpt;
@@ -35,8 +58,16 @@ type FileDetails = BTreeMap<String, String>;
 
 const UNKNOWN_FILE_NAME: &str = "<unknown>";
 
+// Max number of file ranges retrieved concurrently by `safe files sync --pull`,
+// unless --jobs is passed.
+const PULL_JOBS: usize = 4;
+
+// Type tag of the Sequences FilesContainers are stored as, the one sn_api uses.
+const FILES_CONTAINER_TYPE_TAG: u64 = 1_100;
+
 // Differentiates between nodes in a file system.
 #[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
 enum FileTreeNodeType {
     File,
     Directory,
@@ -49,15 +80,14 @@ enum FileTreeNodeType {
 struct FileTreeNode {
     name: String,
 
-    // This field could be useful in json output, because presently json
-    // consumer cannot differentiate between an empty sub-directory and
-    // a file. Though also at present, SAFE does not appear to store and
-    // retrieve empty subdirectories.
-    #[serde(skip)]
     fs_type: FileTreeNodeType,
 
     details: FileDetails,
 
+    // aggregated size of the node and its content, only set by `--du`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    du: Option<u64>,
+
     #[serde(skip_serializing_if = "Vec::is_empty")]
     sub: Vec<FileTreeNode>,
 }
@@ -69,6 +99,7 @@ impl FileTreeNode {
             name: name.to_string(),
             fs_type,
             details,
+            du: None,
             sub: Vec::<FileTreeNode>::new(),
         }
     }
@@ -109,6 +140,27 @@ pub enum FilesSubCommands {
         /// Follow symlinks
         #[structopt(short = "l", long = "follow-links")]
         follow_links: bool,
+        /// Only include the files matching this glob pattern. Can be used multiple times
+        #[structopt(long = "include", number_of_values = 1)]
+        include: Vec<String>,
+        /// Exclude the files matching this glob pattern. Can be used multiple times
+        #[structopt(long = "exclude", number_of_values = 1)]
+        exclude: Vec<String>,
+        /// Upload the content of the archive (.tar, .tar.gz, .tgz or .zip) found at the source location, rather than the archive file itself
+        #[structopt(long = "archive")]
+        archive: bool,
+        /// Abort before uploading anything if a file to be uploaded is larger than this size, e.g. 100MiB. Overrides the max-file-size config setting
+        #[structopt(long = "max-file-size", parse(try_from_str = parse_size))]
+        max_file_size: Option<u64>,
+        /// Abort before uploading anything if the files to be uploaded add up to more than this size, e.g. 1GiB. Overrides the max-total-size config setting
+        #[structopt(long = "max-total-size", parse(try_from_str = parse_size))]
+        max_total_size: Option<u64>,
+        /// The media type to assign to the file uploaded, e.g. text/markdown. Only allowed when the source location is a single file
+        #[structopt(long = "media-type")]
+        media_type: Option<String>,
+        /// Don't detect the media type of the files without an extension from their content, e.g. text/plain or image/png, leaving them the default Raw type
+        #[structopt(long = "no-sniff-media-type")]
+        no_sniff_media_type: bool,
     },
     /// Get a file or folder from the SAFE Network
     Get {
@@ -122,16 +174,40 @@ pub enum FilesSubCommands {
         /// How to display progress.
         #[structopt(short = "i", long = "progress", possible_values = &["bars", "text", "none"], default_value="bars")]
         progress: ProgressIndicator,
-        /// Preserves modification times, access times, and modes from the original file
+        /// Preserves modification times and modes from the original file, when recorded
         #[structopt(short = "p", long = "preserve")]
         preserve: bool,
+        /// Max number of file ranges to download concurrently, from one or several files
+        #[structopt(short = "j", long = "jobs", default_value = "4")]
+        jobs: usize,
+        /// Resume an interrupted transfer, skipping the files and file ranges already retrieved
+        #[structopt(long = "resume")]
+        resume: bool,
+        /// Only include the files matching this glob pattern. Can be used multiple times
+        #[structopt(long = "include", number_of_values = 1)]
+        include: Vec<String>,
+        /// Exclude the files matching this glob pattern. Can be used multiple times
+        #[structopt(long = "exclude", number_of_values = 1)]
+        exclude: Vec<String>,
+        /// Verify the content of each file retrieved by recalculating its XOR-URL locally
+        #[structopt(long = "verify")]
+        verify: bool,
+        /// Retrieve the files into a tar or zip archive at this local path, instead of writing them to the file system. The format is determined by its extension: .tar, .tar.gz, .tgz or .zip
+        #[structopt(long = "archive", conflicts_with_all = &["dest", "preserve", "resume", "include", "exclude", "verify"])]
+        archive: Option<String>,
+        /// How to retrieve symlinks: recreate them, retrieve their target's content in their place, or skip them
+        #[structopt(long = "symlinks", possible_values = &["preserve", "follow", "skip"], default_value = "preserve")]
+        symlinks: SymlinkPolicy,
+        /// What to do with absolute symlinks, and those whose target is outside of the retrieved tree
+        #[structopt(long = "external-symlinks", possible_values = &["keep", "skip", "error"], default_value = "keep")]
+        external_symlinks: ExternalSymlinkPolicy,
     },
     #[structopt(name = "sync")]
-    /// Sync files to the SAFE Network
+    /// Sync files to the SAFE Network, or from it with --pull
     Sync {
-        /// The source location
+        /// The source location, or the source FilesContainer when pulling
         location: String,
-        /// The target FilesContainer to sync up source files with, optionally including the destination path (default is '/')
+        /// The target FilesContainer to sync up source files with, optionally including the destination path (default is '/'). When pulling, the local destination path (default is '.')
         target: Option<String>,
         /// Recursively sync folders and files found in the source location
         #[structopt(short = "r", long = "recursive")]
@@ -139,12 +215,42 @@ pub enum FilesSubCommands {
         /// Follow symlinks
         #[structopt(short = "l", long = "follow-links")]
         follow_links: bool,
-        /// Delete files found at the target FilesContainer that are not in the source location. This is only allowed when --recursive is passed as well
+        /// Delete files found at the target FilesContainer that are not in the source location. This is only allowed when --recursive is passed as well. When pulling, delete the local files not found in the source FilesContainer
         #[structopt(short = "d", long = "delete")]
         delete: bool,
         /// Automatically update the NRS name to link to the new version of the FilesContainer. This is only allowed if an NRS URL was provided, and if the NRS name is currently linked to a specific version of the FilesContainer
         #[structopt(short = "u", long = "update-nrs")]
         update_nrs: bool,
+        /// Only include the files matching this glob pattern. Can be used multiple times
+        #[structopt(long = "include", number_of_values = 1)]
+        include: Vec<String>,
+        /// Exclude the files matching this glob pattern. Can be used multiple times
+        #[structopt(long = "exclude", number_of_values = 1)]
+        exclude: Vec<String>,
+        /// Keep watching the source location for changes, syncing them up as they are made, until Ctrl-C is pressed (only supported on Linux)
+        #[structopt(short = "w", long = "watch")]
+        watch: bool,
+        /// Sync down a local location with the latest version of the source FilesContainer, only retrieving the entries which changed
+        #[structopt(long = "pull", conflicts_with_all = &["update_nrs", "watch", "follow_links", "max_file_size", "max_total_size"])]
+        pull: bool,
+        /// How to handle pre-existing local files which differ from those being pulled (default is 'overwrite')
+        #[structopt(short = "e", long = "exists", possible_values = &["ask", "preserve", "overwrite"], requires = "pull")]
+        exists: Option<FileExistsAction>,
+        /// When pulling, preserves modification times and modes from the original file, when recorded
+        #[structopt(short = "p", long = "preserve", requires = "pull")]
+        preserve: bool,
+        /// When pulling, max number of file ranges to download concurrently, from one or several files (default is 4)
+        #[structopt(short = "j", long = "jobs", requires = "pull")]
+        jobs: Option<usize>,
+        /// Abort before uploading anything if a file to be uploaded is larger than this size, e.g. 100MiB. Overrides the max-file-size config setting
+        #[structopt(long = "max-file-size", parse(try_from_str = parse_size))]
+        max_file_size: Option<u64>,
+        /// Abort before uploading anything if the files to be uploaded add up to more than this size, e.g. 1GiB. Overrides the max-total-size config setting
+        #[structopt(long = "max-total-size", parse(try_from_str = parse_size))]
+        max_total_size: Option<u64>,
+        /// Don't detect the media type of the files without an extension from their content, e.g. text/plain or image/png, leaving them the default Raw type
+        #[structopt(long = "no-sniff-media-type")]
+        no_sniff_media_type: bool,
     },
     #[structopt(name = "add")]
     /// Add a file to an existing FilesContainer on the network
@@ -168,6 +274,18 @@ pub enum FilesSubCommands {
         /// Follow symlinks
         #[structopt(short = "l", long = "follow-links")]
         follow_links: bool,
+        /// Abort before uploading anything if a file to be uploaded is larger than this size, e.g. 100MiB. Overrides the max-file-size config setting
+        #[structopt(long = "max-file-size", parse(try_from_str = parse_size))]
+        max_file_size: Option<u64>,
+        /// Abort before uploading anything if the files to be uploaded add up to more than this size, e.g. 1GiB. Overrides the max-total-size config setting
+        #[structopt(long = "max-total-size", parse(try_from_str = parse_size))]
+        max_total_size: Option<u64>,
+        /// The media type to assign to the file added, e.g. text/markdown. Only allowed when the source location is a local file
+        #[structopt(long = "media-type")]
+        media_type: Option<String>,
+        /// Don't detect the media type of the file added from its content if it has no extension, e.g. text/plain or image/png, leaving it the default Raw type
+        #[structopt(long = "no-sniff-media-type")]
+        no_sniff_media_type: bool,
     },
     #[structopt(name = "rm")]
     /// Remove a file from an existing FilesContainer on the network
@@ -181,11 +299,69 @@ pub enum FilesSubCommands {
         #[structopt(short = "r", long = "recursive")]
         recursive: bool,
     },
+    #[structopt(name = "mkdir")]
+    /// Create an empty directory, and any missing parent directories, on an existing FilesContainer
+    Mkdir {
+        /// The full URL of the directory to create
+        target: String,
+        /// Automatically update the NRS name to link to the new version of the FilesContainer. This is only allowed if an NRS URL was provided, and if the NRS name is currently linked to a specific version of the FilesContainer
+        #[structopt(short = "u", long = "update-nrs")]
+        update_nrs: bool,
+    },
+    #[structopt(name = "cp")]
+    /// Copy a file, or a directory, to another path of the same or a different FilesContainer, without uploading its content again. All the entries are copied with a single new version of the destination FilesContainer
+    Cp {
+        /// The full URL of the file, or directory, to copy
+        source: String,
+        /// The URL of the destination path, which can be in a different FilesContainer. If it ends with '/' the source is copied into it keeping its name
+        dest: String,
+        /// Recursively copy the files found in the source path
+        #[structopt(short = "r", long = "recursive")]
+        recursive: bool,
+        /// Overwrite the files on the destination FilesContainer if there already exist files with the same name
+        #[structopt(short = "f", long = "force")]
+        force: bool,
+        /// Automatically update the NRS name to link to the new version of the FilesContainer. This is only allowed if an NRS URL was provided, and if the NRS name is currently linked to a specific version of the FilesContainer
+        #[structopt(short = "u", long = "update-nrs")]
+        update_nrs: bool,
+    },
+    #[structopt(name = "mv")]
+    /// Move a file, or a directory, to another path of the same or a different FilesContainer, without uploading its content again. A single new version is created when moving within a FilesContainer, otherwise one for each FilesContainer, the source being only updated once all the entries were copied
+    Mv {
+        /// The full URL of the file, or directory, to move
+        source: String,
+        /// The URL of the destination path, which can be in a different FilesContainer. If it ends with '/' the source is moved into it keeping its name
+        dest: String,
+        /// Recursively move the files found in the source path
+        #[structopt(short = "r", long = "recursive")]
+        recursive: bool,
+        /// Overwrite the files on the destination FilesContainer if there already exist files with the same name
+        #[structopt(short = "f", long = "force")]
+        force: bool,
+        /// Automatically update the NRS name(s) to link to the new version of the FilesContainer(s). This is only allowed if NRS URLs were provided, and if the NRS names are currently linked to a specific version of the FilesContainer(s)
+        #[structopt(short = "u", long = "update-nrs")]
+        update_nrs: bool,
+    },
     #[structopt(name = "ls")]
     /// List files found in an existing FilesContainer on the network
     Ls {
         /// The target FilesContainer to list files from, optionally including a path (default is '/')
         target: Option<String>,
+        /// The order the files are listed in. Entries which are equal are listed by name
+        #[structopt(long = "sort", possible_values = &["name", "size", "modified", "created"], default_value = "name")]
+        sort: LsSort,
+        /// List the files in reverse order
+        #[structopt(short = "r", long = "reverse")]
+        reverse: bool,
+        /// Max number of files to list. The output then tells the offset to list the next files from
+        #[structopt(long = "limit")]
+        limit: Option<usize>,
+        /// Number of files to skip, in the order they are listed, before listing any
+        #[structopt(long = "offset", default_value = "0")]
+        offset: usize,
+        /// Use a long listing format, with the type and link of each file
+        #[structopt(short = "l", long = "long")]
+        long: bool,
     },
     #[structopt(name = "tree")]
     /// Recursively list files found in an existing FilesContainer on the network
@@ -195,6 +371,113 @@ pub enum FilesSubCommands {
         /// Include file details
         #[structopt(short = "d", long = "details")]
         details: bool,
+        /// Max depth of the directory tree to display
+        #[structopt(short = "L", long = "level")]
+        level: Option<usize>,
+        /// Only display directories, and symlinks to directories
+        #[structopt(long = "dirs-only")]
+        dirs_only: bool,
+        /// Only display the files matching this glob pattern, e.g. '*.md'. Directories are always displayed
+        #[structopt(short = "P", long = "pattern")]
+        pattern: Option<String>,
+        /// Display the size of each directory, as the total size of the files it contains
+        #[structopt(long = "du")]
+        du: bool,
+    },
+    #[structopt(name = "find")]
+    /// Find the files of an existing FilesContainer on the network matching all the criteria provided, printing their URLs
+    Find {
+        /// The target FilesContainer to search, optionally including a path (default is '/')
+        target: Option<String>,
+        /// Only find the files whose name, or path, matches this glob pattern, e.g. '*.md'
+        #[structopt(long = "name")]
+        name: Option<String>,
+        /// Only find the files of this media type, e.g. 'text/markdown', or of any subtype of it, e.g. 'text/*'
+        #[structopt(long = "type")]
+        media_type: Option<String>,
+        /// Only find the files of at least this size, e.g. 10KiB
+        #[structopt(long = "min-size", parse(try_from_str = parse_size))]
+        min_size: Option<u64>,
+        /// Only find the files of at most this size, e.g. 1MiB
+        #[structopt(long = "max-size", parse(try_from_str = parse_size))]
+        max_size: Option<u64>,
+        /// Only find the files modified after this date, e.g. 2020-01-28, or timestamp, e.g. 2020-01-28T20:26:05Z
+        #[structopt(long = "newer", parse(try_from_str = parse_date))]
+        newer: Option<DateTime<Utc>>,
+        /// Only find the files modified before this date, e.g. 2020-01-28, or timestamp, e.g. 2020-01-28T20:26:05Z
+        #[structopt(long = "older", parse(try_from_str = parse_date))]
+        older: Option<DateTime<Utc>>,
+        /// Run this command for each file found, instead of printing their URLs. Any '{}' arg is replaced with the file's URL, which is appended otherwise. The command ends with an arg set to ';', or at the end of the args
+        #[structopt(
+            long = "exec",
+            value_terminator = ";",
+            allow_hyphen_values = true,
+            min_values = 1
+        )]
+        exec: Vec<String>,
+    },
+    #[structopt(name = "du")]
+    /// Report the disk usage of a FilesContainer, or of a local location once uploaded, with the storage saved by files sharing the same content
+    Du {
+        /// The target FilesContainer, optionally including a path (default is '/'), or a local path
+        target: Option<String>,
+    },
+    #[structopt(name = "diff")]
+    /// Show the differences between two FilesContainers, two versions of a FilesContainer, or a FilesContainer and a local location
+    Diff {
+        /// The FilesContainer URL, or local path, to compare from
+        from: String,
+        /// The FilesContainer URL, or local path, to compare to
+        to: String,
+    },
+    #[structopt(name = "verify")]
+    /// Check that a local location matches the content of a FilesContainer, without retrieving any file
+    Verify {
+        /// The local location to verify
+        location: String,
+        /// The FilesContainer to verify the location against
+        target: Option<String>,
+        /// Recursively verify folders and files found in the local location
+        #[structopt(short = "r", long = "recursive")]
+        recursive: bool,
+        /// Follow symlinks
+        #[structopt(short = "l", long = "follow-links")]
+        follow_links: bool,
+        /// Only include the files matching this glob pattern. Can be used multiple times
+        #[structopt(long = "include", number_of_values = 1)]
+        include: Vec<String>,
+        /// Exclude the files matching this glob pattern. Can be used multiple times
+        #[structopt(long = "exclude", number_of_values = 1)]
+        exclude: Vec<String>,
+    },
+    #[structopt(name = "log")]
+    /// Show the version history of a FilesContainer, with the changes made by each version
+    Log {
+        /// The target FilesContainer
+        target: Option<String>,
+        /// List the changed entries of each version, not only a summary
+        #[structopt(short = "d", long = "details")]
+        details: bool,
+    },
+    #[structopt(name = "revert")]
+    /// Create a new version of a FilesContainer with the same content as one of its earlier versions
+    Revert {
+        /// The target FilesContainer
+        target: Option<String>,
+        /// The version of the FilesContainer whose content is to be restored
+        #[structopt(long = "to-version")]
+        to_version: u64,
+        /// Automatically update the NRS name to link to the new version of the FilesContainer. This is only allowed if an NRS URL was provided, and if the NRS name is currently linked to a specific version of the FilesContainer
+        #[structopt(short = "u", long = "update-nrs")]
+        update_nrs: bool,
+    },
+    #[structopt(name = "mount")]
+    /// Mount a FilesContainer as a read-only file system (only supported on Linux)
+    Mount {
+        /// The FilesContainer to mount, optionally including a path and version. Unless a version is specified, the mount follows its latest version
+        target: String,
+        /// The local directory to mount the FilesContainer on
+        mountpoint: String,
     },
 }
 
@@ -202,6 +485,7 @@ pub async fn files_commander(
     cmd: FilesSubCommands,
     output_fmt: OutputFmt,
     dry_run: bool,
+    use_cache: bool,
     safe: &mut Safe,
 ) -> Result<()> {
     match cmd {
@@ -210,20 +494,131 @@ pub async fn files_commander(
             dest,
             recursive,
             follow_links,
+            include,
+            exclude,
+            archive,
+            max_file_size,
+            max_total_size,
+            media_type,
+            no_sniff_media_type,
         } => {
             // create FilesContainer from a given path to local files/folders
             if dry_run && OutputFmt::Pretty == output_fmt {
                 notice_dry_run();
             }
-            let (files_container_xorurl, processed_files, _files_map) = safe
-                .files_container_create(
-                    Some(&location),
-                    dest.as_deref(),
-                    recursive,
-                    follow_links,
+            check_single_file(&media_type, &location)?;
+            let media_types = MediaTypeRules::new(media_type, !no_sniff_media_type)?;
+            // The content of an archive is extracted, to be uploaded in its place.
+            let extracted = if archive {
+                Some(stage_archive(&location)?)
+            } else {
+                None
+            };
+            let location = extracted.as_ref().map_or(location, |e| e.location.clone());
+            let recursive = recursive || extracted.is_some();
+            // The files a media type is chosen for are uploaded apart from the rest,
+            // so a single file with a media type leaves nothing else to upload.
+            let mut filter = location_filter(&location, &include, &exclude)?;
+            let typed_files =
+                media_types.typed_files(&location, recursive, follow_links, &filter)?;
+            filter.exclude_paths(typed_files.iter().map(|typed| typed.filter_path.clone()));
+            let typed_only = !typed_files.is_empty() && Path::new(&location).is_file();
+            let staged = if typed_only {
+                None
+            } else {
+                filter_location(&location, recursive, follow_links, &filter)?
+            };
+            let upload_location = staged.as_ref().map_or(&location, |s| &s.location);
+            // The limits are checked against the local files, before uploading anything.
+            let limits = resolve_upload_limits(max_file_size, max_total_size)?;
+            let mut files = if limits.is_empty() || typed_only {
+                vec![]
+            } else {
+                local_files(upload_location, recursive, follow_links)
+            };
+            if !limits.is_empty() {
+                files.extend(typed_files.iter().map(|typed| typed.file.clone()));
+            }
+            let original_path = |path: &str| {
+                let path = staged
+                    .as_ref()
+                    .map_or_else(|| path.to_string(), |s| s.original_path(path));
+                extracted
+                    .as_ref()
+                    .map_or_else(|| path.clone(), |e| e.original_path(&path))
+            };
+            if !dry_run {
+                enforce_upload_limits(&limits, &files, &original_path, None)?;
+            }
+            // A single file with a media type is all the FilesContainer is created
+            // with, otherwise the files with a media type are added to the one
+            // created with the rest of the location, with a single update.
+            let base_path = location_base_path(&location, dest.as_deref().unwrap_or(""));
+            let (files_container_xorurl, processed_files, version) = if typed_only {
+                let mut processed_files = ProcessedFiles::new();
+                let files_map = store_typed_files(
+                    safe,
+                    &typed_files,
+                    &base_path,
+                    &FilesMap::new(),
+                    true,
                     dry_run,
+                    &mut processed_files,
                 )
                 .await?;
+                let xorurl = if dry_run {
+                    let (xorurl, _, _) = safe
+                        .files_container_create(None, None, false, false, true)
+                        .await?;
+                    xorurl
+                } else {
+                    create_files_container(safe, &files_map).await?
+                };
+                (xorurl, processed_files, 0)
+            } else {
+                let (xorurl, processed_files, files_map) = safe
+                    .files_container_create(
+                        Some(upload_location.as_str()),
+                        dest.as_deref(),
+                        recursive,
+                        follow_links,
+                        dry_run,
+                    )
+                    .await?;
+                let mut processed_files = match &staged {
+                    Some(staged) => staged.restore_processed_files(processed_files),
+                    None => processed_files,
+                };
+                let version = add_typed_files(
+                    safe,
+                    &typed_files,
+                    &xorurl,
+                    &base_path,
+                    &files_map,
+                    0,
+                    true,
+                    false,
+                    dry_run,
+                    &mut processed_files,
+                )
+                .await?;
+                (xorurl, processed_files, version)
+            };
+            // The FilesContainer created is updated when adding the typed files.
+            let files_container_xorurl = if version > 0 {
+                let mut url = SafeUrl::from_url(&files_container_xorurl)?;
+                url.set_content_version(Some(version));
+                url.to_string()
+            } else {
+                files_container_xorurl
+            };
+            let mut processed_files = match &extracted {
+                Some(extracted) => extracted.restore_processed_files(processed_files),
+                None => processed_files,
+            };
+            if dry_run {
+                enforce_upload_limits(&limits, &files, &original_path, Some(&mut processed_files))?;
+            }
 
             // Now let's just print out a list of the files uploaded/processed
             if OutputFmt::Pretty == output_fmt {
@@ -243,7 +638,12 @@ pub async fn files_commander(
                 }
                 table.printstd();
             } else {
-                print_serialized_output(files_container_xorurl, 0, processed_files, output_fmt);
+                print_serialized_output(
+                    files_container_xorurl,
+                    version,
+                    processed_files,
+                    output_fmt,
+                );
             }
 
             Ok(())
@@ -255,221 +655,2001 @@ pub async fn files_commander(
             follow_links,
             delete,
             update_nrs,
+            include,
+            exclude,
+            watch,
+            pull,
+            exists,
+            preserve,
+            jobs,
+            max_file_size,
+            max_total_size,
+            no_sniff_media_type,
         } => {
+            if pull {
+                // A sync down is often scripted, thus the local files which
+                // differ are overwritten unless asked otherwise.
+                let exists = exists.unwrap_or(FileExistsAction::Overwrite);
+                check_exists_action(&exists, output_fmt)?;
+                let jobs = jobs.unwrap_or(PULL_JOBS);
+                if jobs == 0 {
+                    bail!("The number of jobs must be greater than zero");
+                }
+                if dry_run && OutputFmt::Pretty == output_fmt {
+                    notice_dry_run();
+                }
+                let dest = target.unwrap_or_else(|| ".".to_string());
+                let opts = FilesGetOptions {
+                    exists,
+                    progress: ProgressIndicator::Bars,
+                    preserve,
+                    jobs,
+                    resume: false,
+                    include,
+                    exclude,
+                    use_cache,
+                    verify: false,
+                    symlinks: SymlinkPolicy::Preserve,
+                    external_symlinks: ExternalSymlinkPolicy::Keep,
+                };
+                return process_pull_command(
+                    safe, &location, &dest, delete, opts, dry_run, output_fmt,
+                )
+                .await;
+            }
+
             let target = get_from_arg_or_stdin(target, None)?;
             if dry_run && OutputFmt::Pretty == output_fmt {
                 notice_dry_run();
             }
-            // Update the FilesContainer on the Network
-            let (version, processed_files, _files_map) = safe
-                .files_container_sync(
-                    &location,
-                    &target,
-                    recursive,
-                    follow_links,
-                    delete,
+            let opts = SyncOptions {
+                recursive,
+                follow_links,
+                delete,
+                update_nrs,
+                include,
+                exclude,
+                dry_run,
+                limits: resolve_upload_limits(max_file_size, max_total_size)?,
+                media_types: MediaTypeRules::new(None, !no_sniff_media_type)?,
+            };
+            if watch {
+                return process_sync_watch_command(safe, &location, target, &opts, output_fmt)
+                    .await;
+            }
+
+            let (version, processed_files) = sync_location(safe, &location, &target, &opts).await?;
+
+            // Now let's just print out a list of the files synced/processed
+            if OutputFmt::Pretty == output_fmt {
+                let (table, success_count) = gen_processed_files_table(&processed_files, true);
+                if success_count > 0 {
+                    println!(
+                        "FilesContainer synced up (version {}): \"{}\"",
+                        version,
+                        synced_url(&target, version)
+                    );
+                    table.printstd();
+                } else if !processed_files.is_empty() {
+                    println!(
+                        "No changes were made to FilesContainer (version {}) at \"{}\"",
+                        version, target
+                    );
+                    table.printstd();
+                } else {
+                    println!("No changes were required, source location is already in sync with FilesContainer (version {}) at: \"{}\"", version, target);
+                }
+            } else {
+                print_serialized_output(target, version, processed_files, output_fmt);
+            }
+            Ok(())
+        }
+        FilesSubCommands::Add {
+            location,
+            target,
+            update_nrs,
+            follow_links,
+            force,
+            max_file_size,
+            max_total_size,
+            media_type,
+            no_sniff_media_type,
+        } => {
+            // Validate that location and target are not both "", ie stdin.
+            let target_url = target.unwrap_or_else(|| "".to_string());
+            if target_url.is_empty() && location.is_empty() {
+                bail!("Cannot read both <location> and <target> from stdin");
+            }
+
+            let target_url =
+                get_from_arg_or_stdin(Some(target_url), Some("...awaiting target URl from STDIN"))?;
+
+            if dry_run && OutputFmt::Pretty == output_fmt {
+                notice_dry_run();
+            }
+
+            check_single_file(&media_type, &location)?;
+            let limits = resolve_upload_limits(max_file_size, max_total_size)?;
+            // If location is empty then we read arg from STDIN, which can still be a safe:// URL
+            let (version, processed_files, _files_map) = if location.is_empty() {
+                let file_content =
+                    get_from_stdin(Some("...awaiting file's content to add from STDIN"))?;
+                enforce_upload_limits_on_content(&limits, &file_content)?;
+                // Update the FilesContainer on the Network
+                safe.files_container_add_from_raw(
+                    &file_content,
+                    &target_url,
+                    force,
                     update_nrs,
                     dry_run,
                 )
+                .await?
+            } else {
+                // The limits are checked against the local files, before uploading anything.
+                let files = if limits.is_empty() {
+                    vec![]
+                } else {
+                    local_files(&location, true, follow_links)
+                };
+                let original_path = |path: &str| path.to_string();
+                if !dry_run {
+                    enforce_upload_limits(&limits, &files, &original_path, None)?;
+                }
+                let typed_files = MediaTypeRules::new(media_type, !no_sniff_media_type)?
+                    .typed_files(
+                        &location,
+                        false,
+                        follow_links,
+                        &FilesFilter::from_globs(&[], &[])?,
+                    )?;
+                // Update the FilesContainer on the Network
+                let (version, mut processed_files, files_map) = if typed_files.is_empty() {
+                    safe.files_container_add(
+                        &location,
+                        &target_url,
+                        force,
+                        update_nrs,
+                        follow_links,
+                        dry_run,
+                    )
+                    .await?
+                } else {
+                    // The file is stored with its media type, and then added by its link.
+                    let (_, version, files_map) = fetch_latest_files_map(safe, &target_url).await?;
+                    let mut path = SafeUrl::from_url(&target_url)?.path_decoded()?;
+                    if path.is_empty() {
+                        path.push('/');
+                    }
+                    if path.ends_with('/') {
+                        if let Some(name) = Path::new(&location).file_name() {
+                            path.push_str(&name.to_string_lossy());
+                        }
+                    }
+                    let mut processed_files = ProcessedFiles::new();
+                    let version = add_typed_files(
+                        safe,
+                        &typed_files,
+                        &target_url,
+                        &path,
+                        &files_map,
+                        version,
+                        force,
+                        update_nrs,
+                        dry_run,
+                        &mut processed_files,
+                    )
+                    .await?;
+                    (version, processed_files, files_map)
+                };
+                if dry_run {
+                    enforce_upload_limits(
+                        &limits,
+                        &files,
+                        &original_path,
+                        Some(&mut processed_files),
+                    )?;
+                }
+                (version, processed_files, files_map)
+            };
+
+            // Now let's just print out a list of the files synced/processed
+            output_processed_files_list(output_fmt, processed_files, version, target_url);
+            Ok(())
+        }
+        FilesSubCommands::Rm {
+            target,
+            update_nrs,
+            recursive,
+        } => {
+            let target_url =
+                get_from_arg_or_stdin(Some(target), Some("...awaiting target URl from STDIN"))?;
+
+            if dry_run && OutputFmt::Pretty == output_fmt {
+                notice_dry_run();
+            }
+
+            // Update the FilesContainer on the Network
+            let (version, processed_files, _files_map) = safe
+                .files_container_remove_path(&target_url, recursive, update_nrs, dry_run)
                 .await?;
 
-            // Now let's just print out a list of the files synced/processed
-            if OutputFmt::Pretty == output_fmt {
-                let (table, success_count) = gen_processed_files_table(&processed_files, true);
-                if success_count > 0 {
-                    let url = match SafeUrl::from_url(&target) {
-                        Ok(mut safeurl) => {
-                            safeurl.set_content_version(Some(version));
-                            safeurl.set_path("");
-                            safeurl.to_string()
-                        }
-                        Err(_) => target,
-                    };
+            // Now let's just print out a list of the files removed
+            output_processed_files_list(output_fmt, processed_files, version, target_url);
+            Ok(())
+        }
+        FilesSubCommands::Mkdir { target, update_nrs } => {
+            if dry_run && OutputFmt::Pretty == output_fmt {
+                notice_dry_run();
+            }
+
+            let (version, processed_files) = make_dir(safe, &target, update_nrs, dry_run).await?;
+
+            // Now let's just print out the directories created
+            output_processed_files_list(output_fmt, processed_files, version, target);
+            Ok(())
+        }
+        FilesSubCommands::Cp {
+            source,
+            dest,
+            recursive,
+            force,
+            update_nrs,
+        } => {
+            if dry_run && OutputFmt::Pretty == output_fmt {
+                notice_dry_run();
+            }
+
+            let (version, processed_files) = copy_entries(
+                safe, &source, &dest, recursive, force, update_nrs, false, dry_run,
+            )
+            .await?;
+
+            // Now let's just print out a list of the files copied
+            output_processed_files_list(output_fmt, processed_files, version, dest);
+            Ok(())
+        }
+        FilesSubCommands::Mv {
+            source,
+            dest,
+            recursive,
+            force,
+            update_nrs,
+        } => {
+            if dry_run && OutputFmt::Pretty == output_fmt {
+                notice_dry_run();
+            }
+
+            // Within the same FilesContainer, the source is removed by the
+            // same update which copies its entries.
+            let is_same_container = is_same_container(&source, &dest)?;
+            let (version, mut processed_files) = copy_entries(
+                safe,
+                &source,
+                &dest,
+                recursive,
+                force,
+                update_nrs,
+                is_same_container,
+                dry_run,
+            )
+            .await?;
+
+            // Otherwise the source is only removed once all its entries were copied
+            if !is_same_container {
+                let mut safeurl = SafeUrl::from_url(&source)?;
+                safeurl.set_content_version(None);
+                let (source_version, removed_files, _files_map) = safe
+                    .files_container_remove_path(
+                        &safeurl.to_string(),
+                        recursive,
+                        update_nrs,
+                        dry_run,
+                    )
+                    .await?;
+                if OutputFmt::Pretty == output_fmt {
+                    println!(
+                        "Source FilesContainer updated (version {}): \"{}\"",
+                        source_version, source
+                    );
+                }
+                processed_files.extend(removed_files);
+            }
+
+            // Now let's just print out a list of the files moved
+            output_processed_files_list(output_fmt, processed_files, version, dest);
+            Ok(())
+        }
+        FilesSubCommands::Ls {
+            target,
+            sort,
+            reverse,
+            limit,
+            offset,
+            long,
+        } => {
+            let target_url =
+                get_from_arg_or_stdin(target, Some("...awaiting target URl from STDIN"))?;
+
+            debug!("Getting files in container {:?}", target_url);
+            let mut resolution_chain = safe.inspect(&target_url).await?;
+            let resolved_content = resolution_chain
+                .pop()
+                .context("Unexpectedly failed to obtain the resolved content")?;
+
+            let (container_xorurl, version, files_map, total) = match resolved_content {
+                SafeData::FilesContainer {
+                    xorurl,
+                    version,
+                    files_map,
+                    ..
+                } => {
+                    let (total, filtered_filesmap) = filter_files_map(&files_map, &target_url)?;
+                    (xorurl, version, filtered_filesmap, total)
+                }
+                SafeData::PublicBlob { metadata, .. } => {
+                    if let Some(file_item) = metadata {
+                        let mut files_map = FilesMap::new();
+                        let name = match file_item.get("name") {
+                            Some(name) => name,
+                            None => UNKNOWN_FILE_NAME,
+                        };
+                        files_map.insert(name.to_string(), file_item);
+
+                        let (container_xorurl, container_version) = match resolution_chain.pop() {
+                            Some(SafeData::FilesContainer {
+                                xorurl, version, ..
+                            }) => (xorurl, version),
+                            _ => bail!("Unexpectedly failed to obtain the container's version"),
+                        };
+
+                        (container_xorurl, container_version, files_map, 1)
+                    } else {
+                        bail!(
+                            "You can target files only by providing a FilesContainer with the file's path"
+                        );
+                    }
+                }
+                _other_type => bail!("Make sure the URL targets a FilesContainer"),
+            };
+
+            let (entries, next_offset) = sort_files_map(&files_map, sort, reverse, offset, limit);
+            // The next entries are to be listed from the same version of the
+            // FilesContainer, so none is skipped or listed twice if it's updated.
+            let next_page = match next_offset {
+                Some(offset) => Some(NextPage {
+                    url: version_url(&container_xorurl, &target_url, version)?,
+                    offset,
+                }),
+                None => None,
+            };
+            if OutputFmt::Pretty == output_fmt {
+                print_files_map(&files_map, &entries, total, version, &target_url, long);
+                if let Some(next_page) = next_page {
+                    println!(
+                        "Listed {} of {} entries, use \"{}\" with --offset={} to list the next ones",
+                        entries.len(),
+                        files_map.len(),
+                        next_page.url,
+                        next_page.offset
+                    );
+                }
+            } else if limit.is_some() || offset > 0 {
+                // where to list the next entries from is only included
+                // when paginating, or null on the last page.
+                let listed = ListedEntries(&entries);
+                println!(
+                    "{}",
+                    serialise_output(&(target_url, listed, next_page), output_fmt)
+                );
+            } else {
+                let listed = ListedEntries(&entries);
+                println!("{}", serialise_output(&(target_url, listed), output_fmt));
+            }
+
+            Ok(())
+        }
+        FilesSubCommands::Tree {
+            target,
+            details,
+            level,
+            dirs_only,
+            pattern,
+            du,
+        } => {
+            let opts = TreeOptions {
+                details,
+                level,
+                dirs_only,
+                pattern,
+                du,
+            };
+            process_tree_command(safe, target, opts, output_fmt).await
+        }
+        FilesSubCommands::Find {
+            target,
+            name,
+            media_type,
+            min_size,
+            max_size,
+            newer,
+            older,
+            exec,
+        } => {
+            let opts = FindOptions {
+                name,
+                media_type,
+                min_size,
+                max_size,
+                newer,
+                older,
+                exec,
+            };
+            process_find_command(safe, target, opts, output_fmt).await
+        }
+        FilesSubCommands::Du { target } => {
+            let target = get_from_arg_or_stdin(target, Some("...awaiting target URl from STDIN"))?;
+            let files_map = get_files_map_of(safe, &target).await?;
+            let report = du_files_map(&target, &files_map)?;
+            print_du_report(&report, output_fmt);
+            Ok(())
+        }
+        FilesSubCommands::Diff { from, to } => {
+            process_diff_command(safe, from, to, output_fmt).await
+        }
+        FilesSubCommands::Verify {
+            location,
+            target,
+            recursive,
+            follow_links,
+            include,
+            exclude,
+        } => {
+            let target = get_from_arg_or_stdin(target, None)?;
+            let filter = location_filter(&location, &include, &exclude)?;
+            let staged = filter_location(&location, recursive, follow_links, &filter)?;
+            let verify_location = staged.as_ref().map_or(&location, |s| &s.location);
+            process_verify_command(
+                safe,
+                &location,
+                verify_location,
+                &target,
+                recursive,
+                follow_links,
+                output_fmt,
+            )
+            .await
+        }
+        FilesSubCommands::Log { target, details } => {
+            process_log_command(safe, target, details, output_fmt).await
+        }
+        FilesSubCommands::Revert {
+            target,
+            to_version,
+            update_nrs,
+        } => {
+            let target_url =
+                get_from_arg_or_stdin(target, Some("...awaiting target URl from STDIN"))?;
+
+            if dry_run && OutputFmt::Pretty == output_fmt {
+                notice_dry_run();
+            }
+
+            let (version, processed_files) =
+                revert_files_container(safe, &target_url, to_version, update_nrs, dry_run).await?;
+
+            // Now let's just print out a list of the files restored/removed
+            output_processed_files_list(output_fmt, processed_files, version, target_url);
+            Ok(())
+        }
+        FilesSubCommands::Get {
+            source,
+            dest,
+            exists,
+            progress,
+            preserve,
+            jobs,
+            resume,
+            include,
+            exclude,
+            verify,
+            archive,
+            symlinks,
+            external_symlinks,
+        } => {
+            let opts = FilesGetOptions {
+                exists,
+                progress,
+                preserve,
+                jobs,
+                resume,
+                include,
+                exclude,
+                use_cache,
+                verify,
+                symlinks,
+                external_symlinks,
+            };
+            match archive {
+                Some(archive) => {
+                    process_get_archive_command(safe, &source, &archive, &opts, output_fmt).await
+                }
+                None => process_get_command(safe, source, dest, opts, output_fmt).await,
+            }
+        }
+        FilesSubCommands::Mount { target, mountpoint } => {
+            process_mount_command(safe, &target, &mountpoint, use_cache, output_fmt).await
+        }
+    }
+}
+
+// processes the `safe files sync --pull` command. The local location is
+// compared with the FilesContainer, the same way `safe files verify` does, so
+// only the entries missing locally or which differ are retrieved.
+async fn process_pull_command(
+    safe: &mut Safe,
+    source: &str,
+    dest: &str,
+    delete: bool,
+    opts: FilesGetOptions,
+    dry_run: bool,
+    output_fmt: OutputFmt,
+) -> Result<()> {
+    let (version, remote_files_map) = match safe.fetch(source, None).await? {
+        SafeData::FilesContainer {
+            version, files_map, ..
+        } => (version, files_map),
+        _other_type => bail!("Make sure the URL targets a FilesContainer"),
+    };
+
+    let dest_path = Path::new(dest);
+    if dest_path.exists() && !dest_path.is_dir() {
+        bail!(
+            "cannot sync down a FilesContainer onto non-directory '{}'",
+            dest
+        );
+    }
+    let is_empty_dir = dest_path
+        .read_dir()
+        .map_or(true, |mut entries| entries.next().is_none());
+    let local_files_map = if is_empty_dir {
+        FilesMap::new()
+    } else {
+        debug!("Generating FilesMap of local location {:?}", dest);
+        let location = format!("{}/", dest.trim_end_matches('/'));
+        let (_xorurl, _processed_files, files_map) = safe
+            .files_container_create(Some(&location), None, true, false, true)
+            .await?;
+        files_map
+    };
+
+    // Filtered out entries are neither retrieved nor deleted, and
+    // neither is the journal left by an interrupted pull.
+    let filter = FilesFilter::new(dest_path, &opts.include, &opts.exclude)?;
+    let is_included = |path: &String, details: &FileDetails| {
+        let is_dir = details
+            .get("type")
+            .map_or(false, |t| t == "inode/directory");
+        let rel_path = Path::new(path.trim_start_matches('/'));
+        !filter.is_excluded(rel_path, is_dir) && rel_path != Path::new(JOURNAL_FILE_NAME)
+    };
+    let (remote_files_map, skipped): (FilesMap, FilesMap) = remote_files_map
+        .into_iter()
+        .partition(|(path, details)| is_included(path, details));
+    let local_files_map: FilesMap = local_files_map
+        .into_iter()
+        .filter(|(path, details)| is_included(path, details))
+        .collect();
+
+    let VerifyReport {
+        missing,
+        extra,
+        different,
+    } = verify_files_maps(&local_files_map, &remote_files_map);
+    let to_remove: Vec<String> = if delete { extra } else { vec![] };
+    let missing: HashSet<String> = missing.into_iter().collect();
+    let different: HashSet<String> = different.into_iter().collect();
+    let to_retrieve: FilesMap = remote_files_map
+        .into_iter()
+        .filter(|(path, _)| missing.contains(path) || different.contains(path))
+        .collect();
+    let change = |path: &str| {
+        if different.contains(path) {
+            "*"
+        } else {
+            "+"
+        }
+    };
+
+    let mut processed_files = ProcessedFiles::new();
+    let mut preserves = 0;
+    if dry_run {
+        for (path, details) in to_retrieve.iter() {
+            let link = details.get("link").cloned().unwrap_or_default();
+            processed_files.insert(path.to_string(), (change(path).to_string(), link));
+        }
+        for path in to_remove.iter() {
+            processed_files.insert(path.to_string(), ("-".to_string(), "".to_string()));
+        }
+    } else {
+        // Entries are removed first, children before their parent dirs.
+        for path in to_remove.iter().rev() {
+            let local_path = dest_path.join(path.trim_start_matches('/'));
+            let result = match fs::symlink_metadata(&local_path) {
+                Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(&local_path),
+                Ok(_) => fs::remove_file(&local_path),
+                Err(_) => Ok(()),
+            };
+            let change = match result {
+                Ok(()) => ("-".to_string(), "".to_string()),
+                Err(err) => ("E".to_string(), format!("<{}>", err)),
+            };
+            processed_files.insert(path.to_string(), change);
+        }
+
+        if !to_retrieve.is_empty() {
+            let (retrieved, _overwrites, preserved) =
+                pull_files(safe, source, version, &to_retrieve, dest, &opts, output_fmt).await?;
+            preserves = preserved;
+            for (path, (marker, link)) in retrieved {
+                let marker = if marker == "+" {
+                    change(&path).to_string()
+                } else {
+                    marker
+                };
+                processed_files.insert(path, (marker, link));
+            }
+
+            // Dirs and symlinks have no content, so they are not reported as
+            // retrieved, but they were created unless a local entry was preserved.
+            for (path, details) in to_retrieve.iter() {
+                let local_path = dest_path.join(path.trim_start_matches('/'));
+                let expected_type = details.get("type").map(String::as_str);
+                let local_type = fs::symlink_metadata(&local_path).map(|m| m.file_type());
+                let created = match (expected_type, local_type) {
+                    (Some("inode/directory"), Ok(t)) => t.is_dir(),
+                    (Some("inode/symlink"), Ok(t)) => {
+                        t.is_symlink()
+                            && fs::read_link(&local_path).ok().as_deref()
+                                == details.get("symlink_target").map(Path::new)
+                    }
+                    _ => false,
+                };
+                if created {
+                    processed_files
+                        .insert(path.to_string(), (change(path).to_string(), "".to_string()));
+                }
+            }
+        }
+    }
+    for (path, _details) in skipped {
+        processed_files.insert(path, (SKIPPED_MARKER.to_string(), "".to_string()));
+    }
+
+    if OutputFmt::Pretty == output_fmt {
+        let (table, success_count) = gen_processed_files_table(&processed_files, true);
+        if success_count > 0 {
+            println!(
+                "Local location \"{}\" synced down with FilesContainer (version {}) at \"{}\"",
+                dest, version, source
+            );
+            table.printstd();
+        } else if !processed_files.is_empty() {
+            println!(
+                "No changes were made to local location \"{}\" from FilesContainer (version {}) at \"{}\"",
+                dest, version, source
+            );
+            table.printstd();
+        } else {
+            println!("No changes were required, local location \"{}\" is already in sync with FilesContainer (version {}) at: \"{}\"", dest, version, source);
+        }
+        if preserves > 0 {
+            println!("  pre-existing files preserved: {}", preserves);
+        }
+    } else {
+        print_serialized_output(
+            source.to_string(),
+            version,
+            processed_files.clone(),
+            output_fmt,
+        );
+    }
+
+    let failed = processed_files
+        .values()
+        .filter(|(change, _)| change == "E")
+        .count() as u64;
+    if failed > 0 {
+        bail!(
+            "{} {} could not be synced down",
+            failed,
+            pluralize("entry", "entries", failed)
+        );
+    }
+
+    Ok(())
+}
+
+// Options of a `safe files sync`, applied to each sync made in watch mode.
+struct SyncOptions {
+    recursive: bool,
+    follow_links: bool,
+    delete: bool,
+    update_nrs: bool,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    dry_run: bool,
+    limits: UploadLimits,
+    media_types: MediaTypeRules,
+}
+
+// Syncs up a local location, after applying the include/exclude
+// filter, with the target FilesContainer.
+async fn sync_location(
+    safe: &mut Safe,
+    location: &str,
+    target: &str,
+    opts: &SyncOptions,
+) -> Result<(u64, ProcessedFiles)> {
+    // The files a media type is chosen for are uploaded apart from the rest,
+    // so a single file with a media type leaves nothing else to sync.
+    let mut filter = location_filter(location, &opts.include, &opts.exclude)?;
+    let typed_files =
+        opts.media_types
+            .typed_files(location, opts.recursive, opts.follow_links, &filter)?;
+    filter.exclude_paths(typed_files.iter().map(|typed| typed.filter_path.clone()));
+    let typed_only = !typed_files.is_empty() && Path::new(location).is_file();
+    let staged = if typed_only {
+        None
+    } else {
+        filter_location(location, opts.recursive, opts.follow_links, &filter)?
+    };
+    let sync_location = staged.as_ref().map_or(location, |s| &s.location);
+    // sn_api would delete the files filtered out, which are missing from the
+    // staged location, thus the entries are deleted by delete_missing_entries.
+    let delete = opts.delete && staged.is_none();
+    // The limits are checked against the local files, before uploading anything.
+    let files = if opts.limits.is_empty() {
+        vec![]
+    } else {
+        let mut files = if typed_only {
+            vec![]
+        } else {
+            local_files(sync_location, opts.recursive, opts.follow_links)
+        };
+        files.extend(typed_files.iter().map(|typed| typed.file.clone()));
+        files_to_sync(safe, location, target, files).await?
+    };
+    let original_path = |path: &str| {
+        staged
+            .as_ref()
+            .map_or_else(|| path.to_string(), |s| s.original_path(path))
+    };
+    if !opts.dry_run {
+        enforce_upload_limits(&opts.limits, &files, &original_path, None)?;
+    }
+    // Update the FilesContainer on the Network
+    let (version, processed_files, files_map) = if typed_only {
+        let (_, version, files_map) = fetch_latest_files_map(safe, target).await?;
+        (version, ProcessedFiles::new(), files_map)
+    } else {
+        safe.files_container_sync(
+            sync_location,
+            target,
+            opts.recursive,
+            opts.follow_links,
+            delete,
+            opts.update_nrs,
+            opts.dry_run,
+        )
+        .await?
+    };
+    let mut processed_files = match &staged {
+        Some(staged) => staged.restore_processed_files(processed_files),
+        None => processed_files,
+    };
+    let version = if typed_files.is_empty() {
+        version
+    } else {
+        let base_path = sync_base_path(location, &SafeUrl::from_url(target)?)?;
+        add_typed_files(
+            safe,
+            &typed_files,
+            target,
+            &base_path,
+            &files_map,
+            version,
+            true,
+            opts.update_nrs,
+            opts.dry_run,
+            &mut processed_files,
+        )
+        .await?
+    };
+    let version = match &staged {
+        Some(staged) if opts.delete => {
+            delete_missing_entries(
+                safe,
+                &filter,
+                staged,
+                target,
+                version,
+                &mut processed_files,
+                opts,
+            )
+            .await?
+        }
+        _ => version,
+    };
+    if opts.dry_run {
+        enforce_upload_limits(
+            &opts.limits,
+            &files,
+            &original_path,
+            Some(&mut processed_files),
+        )?;
+    }
+
+    Ok((version, processed_files))
+}
+
+// Lists the local files of a location a sync would upload, for them to be
+// checked against the upload limits. The files found in the target
+// FilesContainer with the same size are left out, as they're not uploaded
+// again unless their content changed.
+async fn files_to_sync(
+    safe: &mut Safe,
+    location: &str,
+    target: &str,
+    files: Vec<LocalFile>,
+) -> Result<Vec<LocalFile>> {
+    let base_path = sync_base_path(location, &SafeUrl::from_url(target)?)?;
+    let (_, _, files_map) = fetch_latest_files_map(safe, target).await?;
+    let files = files
+        .into_iter()
+        .filter(|file| {
+            let path = if file.rel_path.is_empty() {
+                base_path.clone()
+            } else {
+                format!("{}/{}", base_path, file.rel_path.replace('\\', "/"))
+            };
+            files_map.get(&path).map_or(true, |details| {
+                details.get("size") != Some(&file.size.to_string())
+            })
+        })
+        .collect();
+    Ok(files)
+}
+
+// Returns the path within the target FilesContainer which the files of a
+// location are synced up to, i.e. the target path, or a dir named as the
+// location within it, unless the location ends with a slash.
+fn sync_base_path(location: &str, target_url: &SafeUrl) -> Result<String> {
+    Ok(location_base_path(location, &target_url.path_decoded()?))
+}
+
+// Returns the path within a FilesContainer which the files of a location are
+// uploaded to, given the destination path, as sync_base_path does.
+fn location_base_path(location: &str, dest_path: &str) -> String {
+    let dest_path = dest_path.trim_matches('/');
+    let dest_path = if dest_path.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", dest_path)
+    };
+    if location.ends_with('/') {
+        return dest_path;
+    }
+    let name = Path::new(location)
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().to_string());
+    format!("{}/{}", dest_path, name)
+}
+
+// Fetches the FilesMap of the latest version of the FilesContainer a URL
+// targets, along with the URL of the FilesContainer, without path or version,
+// and the latest version.
+async fn fetch_latest_files_map(safe: &mut Safe, url: &str) -> Result<(SafeUrl, u64, FilesMap)> {
+    let xorurl = match safe.fetch(url, None).await? {
+        SafeData::FilesContainer { xorurl, .. } => xorurl,
+        _other_type => bail!("Make sure the URL targets a FilesContainer"),
+    };
+    let mut base_url = SafeUrl::from_url(&xorurl)?;
+    base_url.set_path("");
+    base_url.set_content_version(None);
+    let (version, files_map) = fetch_files_container(safe, &base_url.to_string()).await?;
+    Ok((base_url, version, files_map))
+}
+
+// Deletes the entries of the target FilesContainer which are not found in
+// the staged location, as `files sync --delete` does, except those filtered
+// out which are kept as rsync does when not using --delete-excluded. A dir
+// is only deleted if none of the entries it contains is kept. Returns the
+// version of the FilesContainer once updated.
+async fn delete_missing_entries(
+    safe: &mut Safe,
+    filter: &FilesFilter,
+    staged: &StagedLocation,
+    target: &str,
+    version: u64,
+    processed_files: &mut ProcessedFiles,
+    opts: &SyncOptions,
+) -> Result<u64> {
+    let staged_root = Path::new(staged.location.trim_end_matches('/'));
+    if !staged_root.is_dir() {
+        return Ok(version);
+    }
+    let target_url = SafeUrl::from_url(target)?;
+    let prefix = format!("{}/", sync_base_path(&staged.location, &target_url)?);
+
+    // If the NRS name is to be updated, the entries are deleted through it,
+    // otherwise on the latest version of the FilesContainer.
+    let (mut base_url, _, files_map) = fetch_latest_files_map(safe, target).await?;
+    if opts.update_nrs {
+        base_url = target_url;
+    }
+
+    let mut kept = Vec::<&str>::new();
+    let mut missing = Vec::<(&str, bool)>::new();
+    for (path, details) in files_map.iter() {
+        let rel_path = match path.strip_prefix(&prefix) {
+            Some(rel_path) if !rel_path.is_empty() => rel_path,
+            _ => continue,
+        };
+        let is_dir = is_dir_entry(details);
+        if filter.is_excluded(Path::new(rel_path), is_dir) {
+            kept.push(path);
+        } else if fs::symlink_metadata(staged_root.join(rel_path)).is_err() {
+            missing.push((path, is_dir));
+        }
+    }
+
+    // A dir is deleted along with all the entries it contains.
+    let is_within = |path: &str, dir: &str| {
+        path.strip_prefix(dir)
+            .map_or(false, |rest| rest.starts_with('/'))
+    };
+    let deleted_dirs: Vec<&str> = missing
+        .iter()
+        .filter(|(dir, is_dir)| *is_dir && !kept.iter().any(|path| is_within(path, dir)))
+        .map(|(dir, _)| *dir)
+        .collect();
+
+    let mut version = version;
+    for (path, is_dir) in missing {
+        let is_deleted_dir = deleted_dirs.contains(&path);
+        if (is_dir && !is_deleted_dir) || deleted_dirs.iter().any(|dir| is_within(path, dir)) {
+            continue;
+        }
+        base_url.set_path(path);
+        let (new_version, deleted_files, _files_map) = safe
+            .files_container_remove_path(
+                &base_url.to_string(),
+                is_deleted_dir,
+                opts.update_nrs,
+                opts.dry_run,
+            )
+            .await?;
+        version = new_version;
+        processed_files.extend(deleted_files);
+    }
+
+    Ok(version)
+}
+
+// Returns the URL of the version of the FilesContainer a sync resulted in.
+fn synced_url(target: &str, version: u64) -> String {
+    match SafeUrl::from_url(target) {
+        Ok(mut safeurl) => {
+            safeurl.set_content_version(Some(version));
+            safeurl.set_path("");
+            safeurl.to_string()
+        }
+        Err(_) => target.to_string(),
+    }
+}
+
+// processes the `safe files sync --watch` command. The location is synced up
+// once, and then again every time it changes, until Ctrl-C is pressed.
+#[cfg(target_os = "linux")]
+async fn process_sync_watch_command(
+    safe: &mut Safe,
+    location: &str,
+    target: String,
+    opts: &SyncOptions,
+    output_fmt: OutputFmt,
+) -> Result<()> {
+    let mut watcher = LocationWatcher::new(location, opts.recursive, opts.follow_links)?;
+    if OutputFmt::Pretty == output_fmt {
+        println!(
+            "Watching \"{}\" for changes to sync up with \"{}\", press Ctrl-C to stop",
+            location, target
+        );
+    }
+
+    loop {
+        // A failure to sync up the changes, e.g. when the network is not
+        // reachable, is reported and they are synced up with the next ones.
+        match sync_location(safe, location, &target, opts).await {
+            Ok((version, processed_files)) => {
+                let (table, success_count) = gen_processed_files_table(&processed_files, true);
+                if success_count == 0 {
+                    debug!(
+                        "No changes were required, FilesContainer is at version {}",
+                        version
+                    );
+                } else if OutputFmt::Pretty == output_fmt {
+                    println!(
+                        "[{}] FilesContainer synced up (version {}): \"{}\"",
+                        Local::now().format("%Y-%m-%d %H:%M:%S"),
+                        version,
+                        synced_url(&target, version)
+                    );
+                    table.printstd();
+                } else {
+                    print_serialized_output(target.clone(), version, processed_files, output_fmt);
+                }
+            }
+            Err(err) => {
+                warn!("Failed to sync up \"{}\": {:?}", location, err);
+                eprintln!(
+                    "[{}] Failed to sync up changes, they will be retried along with the next ones: {}",
+                    Local::now().format("%Y-%m-%d %H:%M:%S"),
+                    err
+                );
+            }
+        }
+
+        // dirs created by the changes synced up need to be watched too.
+        if let Err(err) = watcher.watch_dirs() {
+            warn!("{:?}", err);
+        }
+        if !watcher.wait_for_changes().await? {
+            break;
+        }
+    }
+
+    if OutputFmt::Pretty == output_fmt {
+        println!("Stopped watching \"{}\"", location);
+    }
+    Ok(())
+}
+
+// Watching a location relies on inotify, which is only available on Linux.
+#[cfg(not(target_os = "linux"))]
+async fn process_sync_watch_command(
+    _safe: &mut Safe,
+    _location: &str,
+    _target: String,
+    _opts: &SyncOptions,
+    _output_fmt: OutputFmt,
+) -> Result<()> {
+    bail!("Watching a location for changes is only supported on Linux")
+}
+
+// FUSE mounts are only supported on Linux.
+#[cfg(not(target_os = "linux"))]
+async fn process_mount_command(
+    _safe: &Safe,
+    _url: &str,
+    _mountpoint: &str,
+    _use_cache: bool,
+    _output_fmt: OutputFmt,
+) -> Result<()> {
+    bail!("Mounting a FilesContainer is only supported on Linux")
+}
+
+// Returns the filter applying the include/exclude globs, and the .safeignore
+// file, to the files of a local location, which also leaves out the files
+// the CLI never uploads.
+fn location_filter(location: &str, include: &[String], exclude: &[String]) -> Result<FilesFilter> {
+    FilesFilter::for_upload(&location_filter_root(location), include, exclude)
+}
+
+// Applies a filter to a local location. If any file may be filtered out, a
+// filtered copy of the location is returned, which is to be uploaded
+// instead of the original location.
+fn filter_location(
+    location: &str,
+    recursive: bool,
+    follow_links: bool,
+    filter: &FilesFilter,
+) -> Result<Option<StagedLocation>> {
+    if filter.is_empty() && !filter.excludes_any(location, recursive, follow_links) {
+        return Ok(None);
+    }
+
+    let staged = stage_location(location, recursive, follow_links, filter)?;
+    if !Path::new(&staged.location).exists() {
+        bail!("All the files in \"{}\" were filtered out", location);
+    }
+    Ok(Some(staged))
+}
+
+// Options of a `safe files tree`, to choose which part of the tree is displayed.
+struct TreeOptions {
+    details: bool,
+    level: Option<usize>,
+    dirs_only: bool,
+    pattern: Option<String>,
+    du: bool,
+}
+
+// processes the `safe files tree` command.
+async fn process_tree_command(
+    safe: &mut Safe,
+    target: Option<XorUrl>,
+    opts: TreeOptions,
+    output_fmt: OutputFmt,
+) -> Result<()> {
+    if opts.level == Some(0) {
+        bail!("Invalid level, it must be greater than 0");
+    }
+    let pattern_filter = match &opts.pattern {
+        Some(pattern) => Some(FilesFilter::from_globs(&[pattern.clone()], &[])?),
+        None => None,
+    };
+    let target_url = get_from_arg_or_stdin(target, Some("...awaiting target URl from STDIN"))?;
+
+    debug!("Getting files in container {:?}", target_url);
+    let files_map = match safe.fetch(&target_url, None).await? {
+        SafeData::FilesContainer { files_map, .. } => files_map,
+        _other_type => bail!("Make sure the URL targets a FilesContainer"),
+    };
+
+    // Create a top/root node representing `target_url`.
+    let mut top = FileTreeNode::new(
+        &target_url,
+        FileTreeNodeType::Directory,
+        FileDetails::default(),
+    );
+    // Transform flat list in `files_map` to a hierarchy in `top`
+    for (name, file_details) in files_map.iter() {
+        let path_parts: Vec<String> = name
+            .to_string()
+            .trim_matches('/')
+            .split('/')
+            .map(|s| s.to_string())
+            .collect();
+        build_tree(&mut top, &path_parts, file_details, 0);
+    }
+
+    // Sizes are aggregated once the files not matching the pattern are
+    // removed, but before the tree is cut to the dirs or depth displayed.
+    if let Some(filter) = &pattern_filter {
+        filter_tree(&mut top, "", filter);
+    }
+    if opts.du {
+        compute_tree_du(&mut top);
+    }
+    if opts.dirs_only {
+        retain_tree_dirs(&mut top);
+    }
+    if let Some(level) = opts.level {
+        truncate_tree(&mut top, level);
+    }
+    let (dirs, files) = count_tree_nodes(&top);
+
+    // Display.  with or without details.
+    if OutputFmt::Pretty == output_fmt {
+        if opts.details {
+            print_file_system_node_details(&top, dirs, files);
+        } else {
+            print_file_system_node(&top, dirs, files);
+        }
+    } else {
+        println!("{}", serialise_output(&top, output_fmt));
+    }
+
+    Ok(())
+}
+
+// processes the `safe files diff` command.
+async fn process_diff_command(
+    safe: &mut Safe,
+    from: String,
+    to: String,
+    output_fmt: OutputFmt,
+) -> Result<()> {
+    let from_files_map = get_files_map_of(safe, &from).await?;
+    let to_files_map = get_files_map_of(safe, &to).await?;
+
+    let changes = diff_files_maps(&from_files_map, &to_files_map);
+
+    if OutputFmt::Pretty == output_fmt {
+        if changes.is_empty() {
+            println!("No differences found between \"{}\" and \"{}\"", from, to);
+            return Ok(());
+        }
+        println!("Differences between \"{}\" and \"{}\":", from, to);
+
+        // Changes are displayed as a tree, which also needs the
+        // directories containing the changed entries.
+        let mut tree_entries = BTreeMap::<&str, FileDetails>::new();
+        for (path, (change, _link)) in changes.iter() {
+            let mut details = to_files_map
+                .get(path)
+                .or_else(|| from_files_map.get(path))
+                .cloned()
+                .unwrap_or_default();
+            details.insert("change".to_string(), change.to_string());
+            tree_entries.insert(path.as_str(), details);
+
+            for (idx, _) in path.trim_end_matches('/').match_indices('/').skip(1) {
+                let dir = &path[..idx];
+                tree_entries.entry(dir).or_insert_with(|| {
+                    let mut dir_details = FileDetails::new();
+                    dir_details.insert("type".to_string(), "inode/directory".to_string());
+                    dir_details
+                });
+            }
+        }
+
+        let mut top = FileTreeNode::new(&to, FileTreeNodeType::Directory, FileDetails::default());
+        for (path, details) in tree_entries.iter() {
+            let path_parts: Vec<String> = path
+                .trim_matches('/')
+                .split('/')
+                .map(|s| s.to_string())
+                .collect();
+            build_tree(&mut top, &path_parts, details, 0);
+        }
+        print_file_system_node_changes(&top, &changes);
+    } else {
+        println!("{}", serialise_output(&(from, to, changes), output_fmt));
+    }
+
+    Ok(())
+}
+
+// The entries which differ between a local location and a FilesContainer,
+// as reported by `safe files verify`
+#[derive(Debug, Default, Serialize)]
+struct VerifyReport {
+    // found in the FilesContainer but not in the local location
+    missing: Vec<String>,
+    // found in the local location but not in the FilesContainer
+    extra: Vec<String>,
+    // found in both, but with a different type, content or symlink target
+    different: Vec<String>,
+}
+
+impl VerifyReport {
+    fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.different.is_empty()
+    }
+}
+
+// processes the `safe files verify` command.
+// The XOR-URLs of the local files are calculated with a dry-run of a put, the
+// same way `safe xorurl` does, so no file is either uploaded or retrieved.
+async fn process_verify_command(
+    safe: &mut Safe,
+    location: &str,
+    verify_location: &str,
+    target: &str,
+    recursive: bool,
+    follow_links: bool,
+    output_fmt: OutputFmt,
+) -> Result<()> {
+    debug!("Generating FilesMap of local location {:?}", location);
+    let (_xorurl, _processed_files, local_files_map) = safe
+        .files_container_create(Some(verify_location), None, recursive, follow_links, true)
+        .await?;
+    let (version, remote_files_map) = fetch_files_container(safe, target).await?;
+
+    let report = verify_files_maps(&local_files_map, &remote_files_map);
+
+    if OutputFmt::Pretty == output_fmt {
+        if report.is_empty() {
+            println!(
+                "Local location \"{}\" matches FilesContainer (version {}) at \"{}\"",
+                location, version, target
+            );
+            return Ok(());
+        }
+
+        println!(
+            "Local location \"{}\" doesn't match FilesContainer (version {}) at \"{}\":",
+            location, version, target
+        );
+        let sections = [
+            ("Missing locally", &report.missing),
+            ("Extra locally", &report.extra),
+            ("Different", &report.different),
+        ];
+        for (title, paths) in sections.iter().filter(|(_, paths)| !paths.is_empty()) {
+            println!("\n{} ({}):", title, paths.len());
+            for path in paths.iter() {
+                println!("  {}", path);
+            }
+        }
+        println!(
+            "\n{} missing, {} extra, {} different",
+            report.missing.len(),
+            report.extra.len(),
+            report.different.len()
+        );
+    } else {
+        println!("{}", serialise_output(&report, output_fmt));
+    }
+
+    if !report.is_empty() {
+        bail!(
+            "Local location \"{}\" doesn't match the FilesContainer",
+            location
+        );
+    }
+
+    Ok(())
+}
+
+// The changes made to a FilesContainer by one of its versions,
+// as reported by `safe files log`
+#[derive(Debug, Serialize)]
+struct FilesContainerVersion {
+    version: u64,
+    url: String,
+    changes: ProcessedFiles,
+}
+
+// processes the `safe files log` command.
+async fn process_log_command(
+    safe: &mut Safe,
+    target: Option<XorUrl>,
+    details: bool,
+    output_fmt: OutputFmt,
+) -> Result<()> {
+    let target_url = get_from_arg_or_stdin(target, Some("...awaiting target URl from STDIN"))?;
+
+    debug!("Getting version history of container {:?}", target_url);
+    let (xorurl, latest_version) = match safe.fetch(&target_url, None).await? {
+        SafeData::FilesContainer {
+            xorurl, version, ..
+        } => (xorurl, version),
+        _other_type => bail!("Make sure the URL targets a FilesContainer"),
+    };
+
+    // Each version is compared with the previous one, and the
+    // first version with an empty FilesMap.
+    let mut safeurl = SafeUrl::from_url(&xorurl)?;
+    let mut history = Vec::<FilesContainerVersion>::new();
+    let mut previous_files_map = FilesMap::new();
+    for version in 0..=latest_version {
+        safeurl.set_content_version(Some(version));
+        let url = safeurl.to_string();
+        let (_, files_map) = fetch_files_container(safe, &url).await?;
+
+        let changes = diff_files_maps(&previous_files_map, &files_map);
+        history.push(FilesContainerVersion {
+            version,
+            url,
+            changes,
+        });
+        previous_files_map = files_map;
+    }
+
+    if OutputFmt::Pretty == output_fmt {
+        println!("Version history of FilesContainer at \"{}\":", target_url);
+        // most recent version first
+        for entry in history.iter().rev() {
+            let changes_count = entry.changes.len() as u64;
+            println!(
+                "\nVersion {}: {} {} ({})",
+                entry.version,
+                changes_count,
+                pluralize("change", "changes", changes_count),
+                changes_summary(&entry.changes)
+            );
+            println!("  {}", entry.url);
+            if details && !entry.changes.is_empty() {
+                let (table, _) = gen_processed_files_table(&entry.changes, true);
+                table.printstd();
+            }
+        }
+    } else {
+        println!("{}", serialise_output(&(target_url, history), output_fmt));
+    }
+
+    Ok(())
+}
+
+// Creates a new version of a FilesContainer with the same content as the
+// given version, returning the resulting version along with the changes made.
+// Each version of a FilesContainer is an entry of its Sequence, linking to
+// the FilesMap of that version, thus the entry of the given version is
+// appended again, so the FilesMap of the new version is exactly the same and
+// it's created with a single update. Nothing is changed if the content of
+// the latest version is already the same. The version reported by a dry run
+// is the one the revert would result in.
+async fn revert_files_container(
+    safe: &mut Safe,
+    target_url: &str,
+    to_version: u64,
+    update_nrs: bool,
+    dry_run: bool,
+) -> Result<(u64, ProcessedFiles)> {
+    check_update_nrs(target_url, update_nrs)?;
+    let xorurl = match safe.fetch(target_url, None).await? {
+        SafeData::FilesContainer { xorurl, .. } => xorurl,
+        _other_type => bail!("Make sure the URL targets a FilesContainer"),
+    };
+
+    // The content is always reverted from the latest version
+    let mut safeurl = SafeUrl::from_url(&xorurl)?;
+    safeurl.set_path("");
+    safeurl.set_content_version(None);
+    let container_url = safeurl.to_string();
+    let (latest_version, current_files_map) = fetch_files_container(safe, &container_url).await?;
+    if to_version > latest_version {
+        bail!(
+            "Cannot revert to version {}, the latest version of the FilesContainer is {}",
+            to_version,
+            latest_version
+        );
+    }
+
+    safeurl.set_content_version(Some(to_version));
+    let (_, files_map) = fetch_files_container(safe, &safeurl.to_string()).await?;
+    let changes = diff_files_maps(&current_files_map, &files_map);
+    if changes.is_empty() {
+        return Ok((latest_version, changes));
+    }
+    if dry_run {
+        return Ok((latest_version + 1, changes));
+    }
+
+    let (_, files_map_link) = safe
+        .sequence_get(&safeurl.to_string())
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to fetch version {} of the FilesContainer",
+                to_version
+            )
+        })?;
+    let nrs_url = if update_nrs { Some(target_url) } else { None };
+    let version = link_new_version(
+        safe,
+        &container_url,
+        &String::from_utf8_lossy(&files_map_link),
+        latest_version,
+        nrs_url,
+    )
+    .await?;
+
+    Ok((version, changes))
+}
+
+// Creates an empty directory on a FilesContainer. There is no API to add a
+// directory entry to a FilesMap, thus the directory is created locally in a
+// temporary dir which is then synced up with the FilesContainer.
+async fn make_dir(
+    safe: &mut Safe,
+    target: &str,
+    update_nrs: bool,
+    dry_run: bool,
+) -> Result<(u64, ProcessedFiles)> {
+    let mut safeurl = SafeUrl::from_url(target)?;
+    let dir_path = safeurl.path_decoded()?;
+    let dir_path = dir_path.trim_end_matches('/');
+    if dir_path.is_empty() {
+        bail!("The target URL must include the path of the directory to create");
+    }
+
+    safeurl.set_path("");
+    let container_url = safeurl.to_string();
+    let (_, files_map) = fetch_files_container(safe, &container_url).await?;
+    let dir_prefix = format!("{}/", dir_path);
+    if files_map.contains_key(dir_path) || files_map.keys().any(|p| p.starts_with(&dir_prefix)) {
+        bail!("A file or directory already exists at \"{}\"", target);
+    }
+    // None of its parents can be other than a directory, e.g. a file
+    let parent_file = Path::new(dir_path)
+        .ancestors()
+        .skip(1)
+        .filter_map(|parent| parent.to_str())
+        .find(|parent| files_map.get(*parent).map_or(false, |d| !is_dir_entry(d)));
+    if let Some(parent) = parent_file {
+        bail!(
+            "Cannot create a directory within \"{}\", which is not a directory",
+            parent
+        );
+    }
+
+    let staged = stage_empty_dir(dir_path)?;
+    let (version, processed_files, _files_map) = safe
+        .files_container_sync(
+            &staged.location,
+            &container_url,
+            true,
+            false,
+            false,
+            update_nrs,
+            dry_run,
+        )
+        .await?;
+
+    Ok((version, staged.restore_processed_files(processed_files)))
+}
+
+// Copies the entries of the source path, a file or a directory, to the
+// destination path, by linking the new entries to the existing blobs. All the
+// entries are checked before making any change, and then copied with a single
+// update of the destination FilesContainer, its new version being returned.
+// If the source is to be removed, which is only supported when it's in the
+// same FilesContainer, its entries are removed by that same update.
+#[allow(clippy::too_many_arguments)]
+async fn copy_entries(
+    safe: &mut Safe,
+    source: &str,
+    dest: &str,
+    recursive: bool,
+    force: bool,
+    update_nrs: bool,
+    remove_source: bool,
+    dry_run: bool,
+) -> Result<(u64, ProcessedFiles)> {
+    check_update_nrs(dest, update_nrs)?;
+    let mut src_safeurl = SafeUrl::from_url(source)?;
+    let src_path = src_safeurl.path_decoded()?;
+    let src_path = src_path.trim_end_matches('/');
+    if src_path.is_empty() {
+        bail!("The source URL must include the path of the file or directory to copy");
+    }
+    src_safeurl.set_path("");
+    let (_, src_files_map) = fetch_files_container(safe, &src_safeurl.to_string()).await?;
+
+    // Find the entries to copy with their destination paths
+    let mut dest_safeurl = SafeUrl::from_url(dest)?;
+    let dest_path = dest_safeurl.path_decoded()?;
+    let dest_path = if dest_path.is_empty() || dest_path.ends_with('/') {
+        let name = src_path.rsplit('/').next().unwrap_or_default();
+        format!("{}/{}", dest_path.trim_end_matches('/'), name)
+    } else {
+        dest_path
+    };
+
+    let dir_prefix = format!("{}/", src_path);
+    if is_same_container(source, dest)?
+        && (dest_path == src_path || dest_path.starts_with(&dir_prefix))
+    {
+        bail!(
+            "Cannot copy \"{}\" into itself, the destination path is \"{}\"",
+            source,
+            dest_path
+        );
+    }
+
+    let entries: Vec<(String, &FileDetails)> = match src_files_map.get(src_path) {
+        Some(details) if !is_dir_entry(details) => {
+            vec![(dest_path, details)]
+        }
+        _ => {
+            let entries = src_files_map
+                .iter()
+                .filter(|(path, _)| path.starts_with(&dir_prefix))
+                .map(|(path, details)| {
+                    let rel_path = &path[dir_prefix.len()..];
+                    (format!("{}/{}", dest_path, rel_path), details)
+                })
+                .collect::<Vec<_>>();
+            if entries.is_empty() {
+                bail!("No file or directory found at \"{}\"", source);
+            } else if !recursive {
+                bail!(
+                    "\"{}\" is a directory, use the --recursive flag to copy its files",
+                    source
+                );
+            }
+            entries
+        }
+    };
+
+    dest_safeurl.set_path("");
+    dest_safeurl.set_content_version(None);
+    let (latest_version, mut dest_files_map) =
+        fetch_files_container(safe, &dest_safeurl.to_string()).await?;
+
+    let mut processed_files = ProcessedFiles::new();
+    let mut copied = Vec::<(String, FileDetails)>::new();
+    for (path, details) in entries {
+        let attr = |name: &str| details.get(name).map_or("", String::as_str);
+        let change = match dest_files_map.get(&path) {
+            Some(existing) if !is_same_entry(existing, details) => {
+                if !force {
+                    bail!(
+                        "An entry already exists at \"{}\", use the --force flag to overwrite it",
+                        path
+                    );
+                }
+                "*"
+            }
+            Some(_) => continue,
+            None => "+",
+        };
+        let target = match attr("type") {
+            "inode/directory" => "",
+            "inode/symlink" => attr("symlink_target"),
+            _ if attr("link").is_empty() => {
+                bail!("Entry at \"{}\" cannot be copied, it has no link", path)
+            }
+            _ => attr("link"),
+        };
+        processed_files.insert(path.clone(), (change.to_string(), target.to_string()));
+        copied.push((path, details.clone()));
+    }
+
+    // The source entries are removed from the latest version
+    let removed: Vec<String> = if remove_source {
+        dest_files_map
+            .keys()
+            .filter(|path| path.as_str() == src_path || path.starts_with(&dir_prefix))
+            .cloned()
+            .collect()
+    } else {
+        vec![]
+    };
+    for path in removed.iter() {
+        if let Some(details) = dest_files_map.remove(path) {
+            let link = details.get("link").cloned().unwrap_or_default();
+            processed_files.insert(path.to_string(), ("-".to_string(), link));
+        }
+    }
+
+    if processed_files.is_empty() {
+        return Ok((latest_version, processed_files));
+    }
+    if dry_run {
+        return Ok((latest_version + 1, processed_files));
+    }
+
+    dest_files_map.extend(copied);
+    let nrs_url = if update_nrs { Some(dest) } else { None };
+    let version = publish_files_map(
+        safe,
+        &dest_safeurl.to_string(),
+        &dest_files_map,
+        latest_version,
+        nrs_url,
+    )
+    .await?;
+
+    Ok((version, processed_files))
+}
+
+// Returns true if the FilesMap entry is a directory
+fn is_dir_entry(details: &FileDetails) -> bool {
+    details.get("type").map(String::as_str) == Some("inode/directory")
+}
+
+// Returns true if both URLs target the same FilesContainer
+fn is_same_container(url: &str, other_url: &str) -> Result<bool> {
+    let container_url = |url: &str| -> Result<String> {
+        let mut safeurl = SafeUrl::from_url(url)?;
+        safeurl.set_path("");
+        safeurl.set_content_version(None);
+        Ok(safeurl.to_string())
+    };
+    Ok(container_url(url)? == container_url(other_url)?)
+}
+
+// Fetches a FilesContainer, returning its version and FilesMap
+async fn fetch_files_container(safe: &mut Safe, url: &str) -> Result<(u64, FilesMap)> {
+    match safe.fetch(url, None).await? {
+        SafeData::FilesContainer {
+            version, files_map, ..
+        } => Ok((version, files_map)),
+        _other_type => bail!("Unexpectedly failed to fetch FilesContainer at: {}", url),
+    }
+}
+
+// Fails if the NRS name is to be updated but the URL provided isn't an NRS URL.
+fn check_update_nrs(url: &str, update_nrs: bool) -> Result<()> {
+    if update_nrs && !SafeUrl::from_url(url)?.is_nrsurl() {
+        bail!("--update-nrs is only allowed when an NRS URL is provided");
+    }
+    Ok(())
+}
+
+// Creates a new version of a FilesContainer, given by its URL without path or
+// version, whose FilesMap is the one provided, with a single update. As sn_api
+// does, the FilesMap is stored as a Public Blob, which the new version links to.
+async fn publish_files_map(
+    safe: &mut Safe,
+    container_url: &str,
+    files_map: &FilesMap,
+    latest_version: u64,
+    nrs_url: Option<&str>,
+) -> Result<u64> {
+    let files_map_link = store_files_map(safe, files_map).await?;
+    link_new_version(
+        safe,
+        container_url,
+        &files_map_link,
+        latest_version,
+        nrs_url,
+    )
+    .await
+}
 
-                    println!(
-                        "FilesContainer synced up (version {}): \"{}\"",
-                        version, url
-                    );
-                    table.printstd();
-                } else if !processed_files.is_empty() {
-                    println!(
-                        "No changes were made to FilesContainer (version {}) at \"{}\"",
-                        version, target
-                    );
-                    table.printstd();
-                } else {
-                    println!("No changes were required, source location is already in sync with FilesContainer (version {}) at: \"{}\"", version, target);
-                }
-            } else {
-                print_serialized_output(target, version, processed_files, output_fmt);
-            }
-            Ok(())
-        }
-        FilesSubCommands::Add {
-            location,
-            target,
-            update_nrs,
-            follow_links,
-            force,
-        } => {
-            // Validate that location and target are not both "", ie stdin.
-            let target_url = target.unwrap_or_else(|| "".to_string());
-            if target_url.is_empty() && location.is_empty() {
-                bail!("Cannot read both <location> and <target> from stdin");
-            }
+// Creates a FilesContainer whose first version's FilesMap is the one
+// provided, as sn_api does, returning the URL of that version.
+async fn create_files_container(safe: &mut Safe, files_map: &FilesMap) -> Result<String> {
+    let files_map_link = store_files_map(safe, files_map).await?;
+    let xorurl = safe
+        .sequence_create(
+            files_map_link.as_bytes(),
+            None,
+            FILES_CONTAINER_TYPE_TAG,
+            false,
+        )
+        .await
+        .context("Failed to create the FilesContainer")?;
+    let mut safeurl = SafeUrl::from_url(&xorurl)?;
+    safeurl.set_content_type(SafeContentType::FilesContainer)?;
+    safeurl.set_content_version(Some(0));
+    Ok(safeurl.to_string())
+}
 
-            let target_url =
-                get_from_arg_or_stdin(Some(target_url), Some("...awaiting target URl from STDIN"))?;
+// Stores a FilesMap as a Public Blob, returning its link.
+async fn store_files_map(safe: &mut Safe, files_map: &FilesMap) -> Result<String> {
+    let serialised_files_map =
+        serde_json::to_string(files_map).context("Failed to serialise the FilesMap")?;
+    let link = safe
+        .files_store_public_blob(serialised_files_map.as_bytes(), None, false)
+        .await
+        .context("Failed to store the FilesMap")?;
+    let mut files_map_url = SafeUrl::from_url(&link)?;
+    files_map_url.set_content_type(SafeContentType::FilesMap)?;
+    Ok(files_map_url.to_string())
+}
 
-            if dry_run && OutputFmt::Pretty == output_fmt {
-                notice_dry_run();
-            }
+// Adds the files a media type is chosen for to the FilesContainer a URL
+// targets, at `base_path` followed by their path within the location. The
+// FilesContainer's latest version is `version`, whose FilesMap is `files_map`.
+// The files are stored first, and then added with a single update. Returns
+// the version once updated, which for a dry run is the version it would have.
+#[allow(clippy::too_many_arguments)]
+async fn add_typed_files(
+    safe: &mut Safe,
+    typed_files: &[TypedFile],
+    target: &str,
+    base_path: &str,
+    files_map: &FilesMap,
+    version: u64,
+    force: bool,
+    update_nrs: bool,
+    dry_run: bool,
+    processed_files: &mut ProcessedFiles,
+) -> Result<u64> {
+    if typed_files.is_empty() {
+        return Ok(version);
+    }
+    check_update_nrs(target, update_nrs)?;
+    let entries = store_typed_files(
+        safe,
+        typed_files,
+        base_path,
+        files_map,
+        force,
+        dry_run,
+        processed_files,
+    )
+    .await?;
+    if entries.is_empty() {
+        return Ok(version);
+    }
+    if dry_run {
+        return Ok(version + 1);
+    }
 
-            let (version, processed_files, _files_map) =
-                // If location is empty then we read arg from STDIN, which can still be a safe:// URL
-                if location.is_empty() {
-                    let file_content = get_from_stdin(Some("...awaiting file's content to add from STDIN"))?;
-                    // Update the FilesContainer on the Network
-                    safe.files_container_add_from_raw(&file_content, &target_url, force, update_nrs, dry_run).await?
-                } else {
-                    // Update the FilesContainer on the Network
-                    safe.files_container_add(&location, &target_url, force, update_nrs, follow_links, dry_run).await?
-                };
+    let mut files_map = files_map.clone();
+    files_map.extend(entries);
+    let mut container_url = SafeUrl::from_url(target)?;
+    container_url.set_path("");
+    container_url.set_content_version(None);
+    let nrs_url = if update_nrs { Some(target) } else { None };
+    publish_files_map(
+        safe,
+        &container_url.to_string(),
+        &files_map,
+        version,
+        nrs_url,
+    )
+    .await
+}
 
-            // Now let's just print out a list of the files synced/processed
-            output_processed_files_list(output_fmt, processed_files, version, target_url);
-            Ok(())
+// Creates a new version of a FilesContainer, given by its URL without path or
+// version, linking to a FilesMap already stored. Each version is an entry of
+// the FilesContainer's Sequence, thus the link is appended to it. If an NRS URL
+// is provided, its NRS name is then linked to the new version, as sn_api does
+// when updating a FilesContainer through it.
+async fn link_new_version(
+    safe: &mut Safe,
+    container_url: &str,
+    files_map_link: &str,
+    latest_version: u64,
+    nrs_url: Option<&str>,
+) -> Result<u64> {
+    safe.append_to_sequence(container_url, files_map_link.as_bytes())
+        .await
+        .context("Failed to create a new version of the FilesContainer")?;
+    let version = latest_version + 1;
+
+    if let Some(nrs_url) = nrs_url {
+        let mut nrs_url = SafeUrl::from_url(nrs_url)?;
+        nrs_url.set_path("");
+        nrs_url.set_content_version(None);
+        let mut version_url = SafeUrl::from_url(container_url)?;
+        version_url.set_content_version(Some(version));
+        safe.nrs_map_container_add(
+            &nrs_url.to_string(),
+            &version_url.to_string(),
+            false,
+            true,
+            false,
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "FilesContainer updated to version {}, but failed to update the NRS name to link to it",
+                version
+            )
+        })?;
+    }
+    Ok(version)
+}
+
+// Obtains the FilesMap of a FilesContainer URL, or the FilesMap a local
+// location would have once uploaded, by doing a dry-run of a put. The files
+// of a local dir are at the root of its FilesMap, even without a trailing
+// slash, so it can be compared with the FilesContainer it was uploaded to.
+async fn get_files_map_of(safe: &mut Safe, location: &str) -> Result<FilesMap> {
+    let path = Path::new(location);
+    if path.exists() {
+        let location = if path.is_dir() && !location.ends_with('/') {
+            format!("{}/", location)
+        } else {
+            location.to_string()
+        };
+        debug!("Generating FilesMap of local location {:?}", location);
+        let (_xorurl, _processed_files, files_map) = safe
+            .files_container_create(Some(&location), None, true, false, true)
+            .await?;
+        Ok(files_map)
+    } else {
+        debug!("Getting files in container {:?}", location);
+        match safe.fetch(location, None).await? {
+            SafeData::FilesContainer { files_map, .. } => Ok(files_map),
+            _other_type => bail!(
+                "Make sure the URL targets a FilesContainer, or is an existing local path: {}",
+                location
+            ),
         }
-        FilesSubCommands::Rm {
-            target,
-            update_nrs,
-            recursive,
-        } => {
-            let target_url =
-                get_from_arg_or_stdin(Some(target), Some("...awaiting target URl from STDIN"))?;
+    }
+}
 
-            if dry_run && OutputFmt::Pretty == output_fmt {
-                notice_dry_run();
-            }
+// Returns true if two entries of FilesMaps have the same content. The content
+// of files is compared using the XorName of their links, and only whether
+// entries are files, directories or symlinks is compared, since the media
+// type of a file is only a hint which may have been set differently when it
+// was uploaded, and it's part of its link.
+fn is_same_entry(details: &FileDetails, other: &FileDetails) -> bool {
+    let xorname = |details: &FileDetails| {
+        details
+            .get("link")
+            .and_then(|link| SafeUrl::from_url(link).ok())
+            .map(|safeurl| safeurl.xorname())
+    };
+    let kind = |details: &FileDetails| match details.get("type").map(String::as_str) {
+        Some("inode/directory") => "directory",
+        Some("inode/symlink") => "symlink",
+        _ => "file",
+    };
 
-            // Update the FilesContainer on the Network
-            let (version, processed_files, _files_map) = safe
-                .files_container_remove_path(&target_url, recursive, update_nrs, dry_run)
-                .await?;
+    kind(details) == kind(other)
+        && details.get("symlink_target") == other.get("symlink_target")
+        && xorname(details) == xorname(other)
+}
 
-            // Now let's just print out a list of the files removed
-            output_processed_files_list(output_fmt, processed_files, version, target_url);
-            Ok(())
+// Compares the FilesMap a local location would have once uploaded with the
+// FilesMap of a FilesContainer.
+fn verify_files_maps(local: &FilesMap, remote: &FilesMap) -> VerifyReport {
+    let mut report = VerifyReport::default();
+    for (path, remote_details) in remote.iter() {
+        match local.get(path) {
+            None => report.missing.push(path.to_string()),
+            Some(local_details) => {
+                if !is_same_entry(local_details, remote_details) {
+                    report.different.push(path.to_string());
+                }
+            }
         }
-        FilesSubCommands::Ls { target } => {
-            let target_url =
-                get_from_arg_or_stdin(target, Some("...awaiting target URl from STDIN"))?;
+    }
 
-            debug!("Getting files in container {:?}", target_url);
-            let mut resolution_chain = safe.inspect(&target_url).await?;
-            let resolved_content = resolution_chain
-                .pop()
-                .context("Unexpectedly failed to obtain the resolved content")?;
+    report.extra = local
+        .keys()
+        .filter(|path| !remote.contains_key(*path))
+        .cloned()
+        .collect();
 
-            let (version, files_map, total) = match resolved_content {
-                SafeData::FilesContainer {
-                    version, files_map, ..
-                } => {
-                    let (total, filtered_filesmap) = filter_files_map(&files_map, &target_url)?;
-                    (version, filtered_filesmap, total)
-                }
-                SafeData::PublicBlob { metadata, .. } => {
-                    if let Some(file_item) = metadata {
-                        let mut files_map = FilesMap::new();
-                        let name = match file_item.get("name") {
-                            Some(name) => name,
-                            None => UNKNOWN_FILE_NAME,
-                        };
-                        files_map.insert(name.to_string(), file_item);
+    report
+}
 
-                        let container_version = match resolution_chain.pop() {
-                            Some(SafeData::FilesContainer { version, .. }) => version,
-                            _ => bail!("Unexpectedly failed to obtain the container's version"),
-                        };
+// Compares two FilesMaps, returning the entries added ("+"), removed ("-"),
+// and modified ("*") in `to`, along with their link in `to`, or in `from`
+// for those removed. Entries are compared the same way `files verify` does.
+fn diff_files_maps(from: &FilesMap, to: &FilesMap) -> ProcessedFiles {
+    let mut changes = ProcessedFiles::new();
+    let link = |details: &FileDetails| details.get("link").cloned().unwrap_or_default();
 
-                        (container_version, files_map, 1)
-                    } else {
-                        bail!(
-                            "You can target files only by providing a FilesContainer with the file's path"
-                        );
-                    }
+    for (path, to_details) in to.iter() {
+        match from.get(path) {
+            None => {
+                changes.insert(path.to_string(), ("+".to_string(), link(to_details)));
+            }
+            Some(from_details) => {
+                if !is_same_entry(from_details, to_details) {
+                    changes.insert(path.to_string(), ("*".to_string(), link(to_details)));
                 }
-                _other_type => bail!("Make sure the URL targets a FilesContainer"),
-            };
-
-            if OutputFmt::Pretty == output_fmt {
-                print_files_map(&files_map, total, version, &target_url);
-            } else {
-                println!("{}", serialise_output(&(target_url, files_map), output_fmt));
             }
-
-            Ok(())
         }
-        FilesSubCommands::Tree { target, details } => {
-            process_tree_command(safe, target, details, output_fmt).await
+    }
+
+    for (path, from_details) in from.iter() {
+        if !to.contains_key(path) {
+            changes.insert(path.to_string(), ("-".to_string(), link(from_details)));
         }
-        FilesSubCommands::Get {
-            source,
-            dest,
-            exists,
-            progress,
-            preserve,
-        } => process_get_command(safe, source, dest, exists, progress, preserve, output_fmt).await,
     }
+
+    changes
 }
 
-// processes the `safe files tree` command.
-async fn process_tree_command(
-    safe: &mut Safe,
-    target: Option<XorUrl>,
-    details: bool,
-    output_fmt: OutputFmt,
-) -> Result<()> {
-    let target_url = get_from_arg_or_stdin(target, Some("...awaiting target URl from STDIN"))?;
+// Prints a tree of changed entries, in a format similar to unix `tree` command,
+// with the change sign of each entry to the left. prints a summary row below it.
+fn print_file_system_node_changes(dir: &FileTreeNode, changes: &ProcessedFiles) {
+    let mut siblings = HashMap::new();
+    print_file_system_node_changes_body(dir, 0, &mut siblings);
 
-    debug!("Getting files in container {:?}", target_url);
-    let files_map = match safe.fetch(&target_url, None).await? {
-        SafeData::FilesContainer { files_map, .. } => files_map,
-        _other_type => bail!("Make sure the URL targets a FilesContainer"),
+    println!("\n{}", changes_summary(changes));
+}
+
+// Summarises a set of changes, e.g. "1 added, 0 removed, 2 modified"
+fn changes_summary(changes: &ProcessedFiles) -> String {
+    let count = |sign: &str| {
+        changes
+            .values()
+            .filter(|(change, _)| change == sign)
+            .count()
     };
+    format!(
+        "{} added, {} removed, {} modified",
+        count("+"),
+        count("-"),
+        count("*")
+    )
+}
 
-    // Create a top/root node representing `target_url`.
-    let mut top = FileTreeNode::new(
-        &target_url,
-        FileTreeNodeType::Directory,
-        FileDetails::default(),
+// generates tree body for print_file_system_node_changes()
+// operates recursively on `dir`
+fn print_file_system_node_changes_body(
+    dir: &FileTreeNode,
+    depth: u32,
+    siblings: &mut HashMap<u32, bool>,
+) {
+    let change = dir.details.get("change").map_or(" ", String::as_str);
+    println!(
+        "{}  {}",
+        change,
+        format_file_system_node_line(dir, depth, siblings)
     );
-    // Transform flat list in `files_map` to a hierarchy in `top`
-    let mut files: u64 = 0;
-    let mut dirs: u64 = 0;
-    for (name, file_details) in files_map.iter() {
-        let path_parts: Vec<String> = name
-            .to_string()
-            .trim_matches('/')
-            .split('/')
-            .map(|s| s.to_string())
-            .collect();
-        let (d, f) = build_tree(&mut top, &path_parts, file_details, 0);
-        files += f;
-        dirs += d;
-    }
-    // Display.  with or without details.
-    if OutputFmt::Pretty == output_fmt {
-        if details {
-            print_file_system_node_details(&top, dirs, files);
-        } else {
-            print_file_system_node(&top, dirs, files);
-        }
-    } else {
-        println!("{}", serialise_output(&top, output_fmt));
-    }
 
-    Ok(())
+    // And now, for some recursion...
+    for (idx, child) in dir.sub.iter().enumerate() {
+        let is_last = idx == dir.sub.len() - 1;
+        siblings.insert(depth, !is_last);
+        print_file_system_node_changes_body(child, depth + 1, siblings);
+    }
 }
 
 fn print_serialized_output(
@@ -527,37 +2707,24 @@ fn output_processed_files_list(
 
 // Builds a file-system tree (hierarchy) from a single file path, split into its parts.
 // May be called multiple times to expand the tree.
-fn build_tree(
-    node: &mut FileTreeNode,
-    path_parts: &[String],
-    details: &FileDetails,
-    depth: usize,
-) -> (u64, u64) {
-    let mut dirs: u64 = 0;
-    let mut files: u64 = 0;
+fn build_tree(node: &mut FileTreeNode, path_parts: &[String], details: &FileDetails, depth: usize) {
     if depth < path_parts.len() {
         let item = &path_parts[depth];
 
         if item.is_empty() {
-            return (dirs, files);
+            return;
         }
 
         let mut node = match node.find_child(&item) {
             Some(n) => n,
             None => {
-                let (fs_type, d, di, fi) = match details["type"].as_str() {
-                    "inode/directory" => (FileTreeNodeType::Directory, details, 1, 0),
-                    "inode/symlink" => {
-                        let target_type = details["symlink_target_type"].as_str();
-                        let (dir, fil) = if target_type == "dir" { (1, 0) } else { (0, 1) };
-                        (FileTreeNodeType::Symlink, details, dir, fil)
-                    }
-                    _ => (FileTreeNodeType::File, details, 0, 1),
+                let fs_type = match details["type"].as_str() {
+                    "inode/directory" => FileTreeNodeType::Directory,
+                    "inode/symlink" => FileTreeNodeType::Symlink,
+                    _ => FileTreeNodeType::File,
                 };
 
-                dirs += di;
-                files += fi;
-                let n = FileTreeNode::new(&item, fs_type, d.clone());
+                let n = FileTreeNode::new(&item, fs_type, details.clone());
                 node.add_child(n);
                 // Very gross, but it works.
                 // if this can be done in a better way,
@@ -572,11 +2739,81 @@ fn build_tree(
                 }
             }
         };
-        let (di, fi) = build_tree(&mut node, path_parts, details, depth + 1);
-        dirs += di;
-        files += fi;
+        build_tree(&mut node, path_parts, details, depth + 1);
+    }
+}
+
+// Returns true if the node is a directory, or a symlink to a directory.
+fn is_dir_node(node: &FileTreeNode) -> bool {
+    match node.fs_type {
+        FileTreeNodeType::Directory => true,
+        FileTreeNodeType::Symlink => node
+            .details
+            .get("symlink_target_type")
+            .map_or(false, |t| t == "dir"),
+        FileTreeNodeType::File => false,
+    }
+}
+
+// Counts the directories and files found beneath `dir`, symlinks
+// being counted as their target's type.
+fn count_tree_nodes(dir: &FileTreeNode) -> (u64, u64) {
+    dir.sub.iter().fold((0, 0), |(dirs, files), child| {
+        let (d, f) = count_tree_nodes(child);
+        if is_dir_node(child) {
+            (dirs + d + 1, files + f)
+        } else {
+            (dirs + d, files + f + 1)
+        }
+    })
+}
+
+// Removes the files, and symlinks to files, which path relative to the
+// root of the tree doesn't match the pattern of `filter`.
+fn filter_tree(dir: &mut FileTreeNode, dir_path: &str, filter: &FilesFilter) {
+    dir.sub.retain(|child| {
+        let path = format!("{}{}", dir_path, child.name);
+        is_dir_node(child) || !filter.is_excluded(Path::new(&path), false)
+    });
+    for child in dir.sub.iter_mut() {
+        let child_path = format!("{}{}/", dir_path, child.name);
+        filter_tree(child, &child_path, filter);
+    }
+}
+
+// Sets the size of each node, that of a directory being the total size
+// of its content, returning the size of `dir`.
+fn compute_tree_du(dir: &mut FileTreeNode) -> u64 {
+    let own_size = dir
+        .details
+        .get("size")
+        .and_then(|size| size.parse::<u64>().ok())
+        .unwrap_or(0);
+    let du = dir
+        .sub
+        .iter_mut()
+        .fold(own_size, |du, child| du + compute_tree_du(child));
+    dir.du = Some(du);
+    du
+}
+
+// Removes all the nodes which are not directories, or symlinks to directories.
+fn retain_tree_dirs(dir: &mut FileTreeNode) {
+    dir.sub.retain(is_dir_node);
+    for child in dir.sub.iter_mut() {
+        retain_tree_dirs(child);
+    }
+}
+
+// Removes the nodes found deeper than `level` beneath `dir`.
+fn truncate_tree(dir: &mut FileTreeNode, level: usize) {
+    if level == 0 {
+        dir.sub.clear();
+        return;
+    }
+    for child in dir.sub.iter_mut() {
+        truncate_tree(child, level - 1);
     }
-    (dirs, files)
 }
 
 // A function to print a FileTreeNode in format similar to unix `tree` command.
@@ -586,8 +2823,17 @@ fn print_file_system_node(dir: &FileTreeNode, dirs: u64, files: u64) {
     print_file_system_node_body(dir, 0, &mut siblings);
 
     // print summary row
+    print_file_system_node_summary(dir, dirs, files);
+}
+
+// prints the summary row of a tree, including its total size if computed.
+fn print_file_system_node_summary(dir: &FileTreeNode, dirs: u64, files: u64) {
+    let used = dir
+        .du
+        .map_or_else(String::new, |du| format!("{} bytes used in ", du));
     println!(
-        "\n{} {}, {} {}",
+        "\n{}{} {}, {} {}",
+        used,
         dirs,
         pluralize("directory", "directories", dirs),
         files,
@@ -598,7 +2844,16 @@ fn print_file_system_node(dir: &FileTreeNode, dirs: u64, files: u64) {
 // generates tree body for print_file_system_node()
 // operates recursively on `dir`
 fn print_file_system_node_body(dir: &FileTreeNode, depth: u32, siblings: &mut HashMap<u32, bool>) {
-    println!("{}", format_file_system_node_line(dir, depth, siblings));
+    let line = format_file_system_node_line(dir, depth, siblings);
+    match dir.du {
+        // the size is displayed between the tree lines and the name, as `tree --du` does.
+        Some(du) if depth > 0 => {
+            let name_start = line.find("── ").map_or(0, |idx| idx + "── ".len());
+            let (lines, name) = line.split_at(name_start);
+            println!("{}[{:>10}]  {}", lines, du, name);
+        }
+        _ => println!("{}", line),
+    }
 
     // And now, for some recursion...
     for (idx, child) in dir.sub.iter().enumerate() {
@@ -626,13 +2881,7 @@ fn print_file_system_node_details(dir: &FileTreeNode, dirs: u64, files: u64) {
     table.printstd();
 
     // print summary row
-    println!(
-        "\n{} {}, {} {}",
-        dirs,
-        pluralize("directory", "directories", dirs),
-        files,
-        pluralize("file", "files", files),
-    );
+    print_file_system_node_summary(dir, dirs, files);
 }
 
 // generates table body for print_file_system_node_details()
@@ -646,8 +2895,12 @@ fn print_file_system_node_details_body(
     let name = format_file_system_node_line(dir, depth, siblings);
 
     let d = &dir.details;
+    let size = match dir.du {
+        Some(du) if depth > 0 => du.to_string(),
+        _ => d.get("size").cloned().unwrap_or_default(),
+    };
     table.add_row(row![
-        d.get("size").unwrap_or(&String::default()),
+        size,
         d.get("created").unwrap_or(&String::default()),
         d.get("modified").unwrap_or(&String::default()),
         name
@@ -712,12 +2965,115 @@ fn format_symlink(name: &str, fd: &FileDetails) -> String {
     }
 }
 
+/// # The order of the entries listed by `safe files ls`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LsSort {
+    Name,
+    Size,
+    Modified,
+    Created,
+}
+
+// implement FromStr for parsing "--sort" arg.
+impl std::str::FromStr for LsSort {
+    type Err = String;
+    fn from_str(str: &str) -> std::result::Result<Self, String> {
+        match str {
+            "name" => Ok(Self::Name),
+            "size" => Ok(Self::Size),
+            "modified" => Ok(Self::Modified),
+            "created" => Ok(Self::Created),
+            other => Err(format!(
+                "'{}' not supported. Supported values are name, size, modified, and created",
+                other
+            )),
+        }
+    }
+}
+
+// Entries of a FilesMap, serialised as a map keeping the order they are listed in.
+struct ListedEntries<'a>(&'a [(&'a String, &'a FileDetails)]);
+
+impl Serialize for ListedEntries<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(name, details)| (name, details)))
+    }
+}
+
+// Where `files ls` is to list the next entries from, when they don't all fit
+// in the --limit, i.e. the URL of the version listed and the offset within it.
+#[derive(Debug, Serialize)]
+struct NextPage {
+    url: String,
+    offset: usize,
+}
+
+// Returns the URL of a version of the FilesContainer a target URL resolved to,
+// with the same path, so an NRS URL is not resolved to a later version.
+fn version_url(container_xorurl: &str, target_url: &str, version: u64) -> Result<String> {
+    let mut url = SafeUrl::from_url(container_xorurl)?;
+    url.set_path(&SafeUrl::from_url(target_url)?.path_decoded()?);
+    url.set_content_version(Some(version));
+    Ok(url.to_string())
+}
+
+// Sorts the entries of the FilesMap, returning those found in the page given
+// by `offset` and `limit`, and the offset of the next page if there's any.
+// Entries which are equal by the sort key are ordered by name, and the
+// timestamps are RFC 3339 in UTC, thus ordered as strings are.
+fn sort_files_map(
+    files_map: &FilesMap,
+    sort: LsSort,
+    reverse: bool,
+    offset: usize,
+    limit: Option<usize>,
+) -> (Vec<(&String, &FileDetails)>, Option<usize>) {
+    let mut entries: Vec<(&String, &FileDetails)> = files_map.iter().collect();
+    let detail = |details: &FileDetails, key: &str| details.get(key).cloned().unwrap_or_default();
+    match sort {
+        // FilesMap is a BTreeMap, thus already ordered by name.
+        LsSort::Name => {}
+        LsSort::Size => entries.sort_by_key(|(_, details)| {
+            details
+                .get("size")
+                .and_then(|size| size.parse::<u64>().ok())
+                .unwrap_or(0)
+        }),
+        LsSort::Modified => entries.sort_by_key(|(_, details)| detail(details, "modified")),
+        LsSort::Created => entries.sort_by_key(|(_, details)| detail(details, "created")),
+    }
+    if reverse {
+        entries.reverse();
+    }
+
+    let total = entries.len();
+    let end = limit.map_or(total, |limit| offset.saturating_add(limit).min(total));
+    let page = entries
+        .into_iter()
+        .skip(offset)
+        .take(end.saturating_sub(offset))
+        .collect();
+    let next_offset = if end < total { Some(end) } else { None };
+    (page, next_offset)
+}
+
 // A function to print a FilesMap in human-friendly table format.
-fn print_files_map(files_map: &FilesMap, total_files: u64, version: u64, target_url: &str) {
+fn print_files_map(
+    files_map: &FilesMap,
+    entries: &[(&String, &FileDetails)],
+    total_files: u64,
+    version: u64,
+    target_url: &str,
+    long: bool,
+) {
     println!(
         "Files of FilesContainer (version {}) at \"{}\":",
         version, target_url
     );
+    if long {
+        print_files_map_long(files_map, entries, total_files);
+        return;
+    }
     let mut table = Table::new();
     let format = FormatBuilder::new()
         .column_separator(' ')
@@ -736,6 +3092,12 @@ fn print_files_map(files_map: &FilesMap, total_files: u64, version: u64, target_
     table.add_row(row!["SIZE", "CREATED", "MODIFIED", "NAME"]);
     files_map.iter().for_each(|(name, file_item)| {
         total_bytes += file_item["size"].parse().unwrap_or(0);
+        if !name.ends_with('/') && None == name.trim_matches('/').find('/') {
+            cwd_size += file_item["size"].parse().unwrap_or(0);
+            cwd_files += 1;
+        }
+    });
+    entries.iter().for_each(|(name, file_item)| {
         if name.ends_with('/') {
             table.add_row(row![
                 &file_item["size"],
@@ -744,10 +3106,6 @@ fn print_files_map(files_map: &FilesMap, total_files: u64, version: u64, target_
                 Fbb->name
             ]);
         } else {
-            if None == name.trim_matches('/').find('/') {
-                cwd_size += file_item["size"].parse().unwrap_or(0);
-                cwd_files += 1;
-            }
             let name_field = if file_item["type"] == "inode/symlink" {
                 format_symlink(&name, &file_item)
             } else {
@@ -769,10 +3127,44 @@ fn print_files_map(files_map: &FilesMap, total_files: u64, version: u64, target_
     table.printstd();
 }
 
+// Prints the entries with the same columns `safe cat` uses for a FilesContainer.
+fn print_files_map_long(
+    files_map: &FilesMap,
+    entries: &[(&String, &FileDetails)],
+    total_files: u64,
+) {
+    let total_bytes: u64 = files_map
+        .values()
+        .map(|file_item| file_item["size"].parse::<u64>().unwrap_or(0))
+        .sum();
+    println!("Total Files: {}   Total Size: {}", total_files, total_bytes);
+    let mut table = Table::new();
+    table.add_row(
+        row![bFg->"Name", bFg->"Type", bFg->"Size", bFg->"Created", bFg->"Modified", bFg->"Link"],
+    );
+    entries.iter().for_each(|(name, file_item)| {
+        let name_field = if file_item["type"] == "inode/symlink" {
+            format_symlink(name, file_item)
+        } else {
+            name.to_string()
+        };
+        table.add_row(row![
+            name_field,
+            file_item["type"],
+            file_item["size"],
+            file_item["created"],
+            file_item["modified"],
+            file_item.get("link").unwrap_or(&String::default()),
+        ]);
+    });
+    table.printstd();
+}
+
 fn filter_files_map(files_map: &FilesMap, target_url: &str) -> Result<(u64, FilesMap)> {
     let mut filtered_filesmap = FilesMap::default();
     let mut safeurl = Safe::parse_url(target_url)?;
     let mut total = 0;
+    let mut dir_entries = Vec::<(&String, &FileDetails)>::new();
 
     for (filepath, fileitem) in files_map.iter() {
         // TODO:
@@ -781,6 +3173,7 @@ fn filter_files_map(files_map: &FilesMap, target_url: &str) -> Result<(u64, File
         // refactor to display the mtime and ctime fields
         // from these directory FileItem(s).
         if fileitem["type"] == "inode/directory" {
+            dir_entries.push((filepath, fileitem));
             continue;
         }
 
@@ -855,5 +3248,30 @@ fn filter_files_map(files_map: &FilesMap, target_url: &str) -> Result<(u64, File
         }
     }
 
+    // Directories which don't contain any file are listed from their own entry,
+    // otherwise empty directories wouldn't be listed at all.
+    for (dirpath, fileitem) in dir_entries {
+        let subdir = match Path::new(dirpath).components().find_map(|p| match p {
+            Component::Normal(comp) => Some(comp),
+            _ => None,
+        }) {
+            Some(comp) => match comp.to_str() {
+                Some(c) => c,
+                None => bail!("Encountered invalid unicode sequence in path".to_string()),
+            },
+            None => continue,
+        };
+
+        let base_path = format!("{}/", subdir);
+        if !filtered_filesmap.contains_key(&base_path) {
+            let mut fileitem = fileitem.clone();
+            safeurl.set_path(subdir);
+            fileitem.insert("link".to_string(), safeurl.to_string());
+            fileitem.insert("type".to_string(), "".to_string());
+            fileitem.insert("size".to_string(), "0".to_string());
+            filtered_filesmap.insert(base_path, fileitem);
+        }
+    }
+
     Ok((total, filtered_filesmap))
 }
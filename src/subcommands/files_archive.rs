@@ -0,0 +1,834 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    files_filter::StagedLocation,
+    files_get::{
+        fetch_files_map, files_get_blob, FileExistsAction, FilesGetOptions, ProgressIndicator,
+        DOWNLOAD_RANGE_SIZE,
+    },
+    files_symlinks::apply_symlink_policies,
+    helpers::{pluralize, serialise_output},
+    OutputFmt,
+};
+use crate::operations::cache::BlobCache;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Datelike, Timelike};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use futures::stream::{self, StreamExt};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use log::{debug, trace, warn};
+use sn_api::{
+    files::{FilesMap, ProcessedFiles},
+    Safe, SafeUrl,
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Read, Write},
+    path::{Component, Path, PathBuf},
+};
+
+type FileDetails = BTreeMap<String, String>;
+
+// PAX extended header record keeping the type of a symlink's target, as
+// recorded in the FilesMap, since tar headers have no field for it.
+const PAX_SYMLINK_TARGET_TYPE: &str = "SAFE.symlink_target_type";
+
+// Unix file type bits of the mode of an entry
+const MODE_TYPE_MASK: u32 = 0o170_000;
+const MODE_SYMLINK: u32 = 0o120_000;
+
+// Permissions of the entries which have no mode bits recorded
+const DEFAULT_FILE_MODE: u32 = 0o644;
+const DEFAULT_DIR_MODE: u32 = 0o755;
+const DEFAULT_SYMLINK_MODE: u32 = 0o777;
+
+/// # Format of an archive, as determined by the extension of its file name.
+#[derive(Debug, Clone, Copy)]
+pub enum ArchiveFormat {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub fn from_path(path: &str) -> Result<Self> {
+        let name = path.to_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Ok(Self::TarGz)
+        } else if name.ends_with(".tar") {
+            Ok(Self::Tar)
+        } else if name.ends_with(".zip") {
+            Ok(Self::Zip)
+        } else {
+            bail!(
+                "Unsupported archive format of \"{}\", the supported extensions are .tar, .tar.gz, .tgz and .zip",
+                path
+            )
+        }
+    }
+}
+
+// Output of a tar archive, which is compressed for .tar.gz archives.
+enum TarOutput {
+    Plain(fs::File),
+    Gz(GzEncoder<fs::File>),
+}
+
+impl Write for TarOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(file) => file.write(buf),
+            Self::Gz(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(file) => file.flush(),
+            Self::Gz(encoder) => encoder.flush(),
+        }
+    }
+}
+
+impl TarOutput {
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Self::Plain(file) => file.sync_all(),
+            Self::Gz(encoder) => encoder.finish()?.sync_all(),
+        }
+    }
+}
+
+// Writes the entries of an archive, one after the other. The content of a
+// file is written in chunks as it's retrieved, so it's never held in memory.
+enum ArchiveWriter {
+    Tar(tar::Builder<TarOutput>),
+    Zip(zip::ZipWriter<fs::File>),
+}
+
+impl ArchiveWriter {
+    fn create(path: &Path, format: ArchiveFormat) -> Result<Self> {
+        let file = fs::File::create(path)
+            .with_context(|| format!("Couldn't create archive: \"{}\"", path.display()))?;
+        let writer = match format {
+            ArchiveFormat::Tar => Self::Tar(tar::Builder::new(TarOutput::Plain(file))),
+            ArchiveFormat::TarGz => Self::Tar(tar::Builder::new(TarOutput::Gz(GzEncoder::new(
+                file,
+                Compression::default(),
+            )))),
+            ArchiveFormat::Zip => Self::Zip(zip::ZipWriter::new(file)),
+        };
+        Ok(writer)
+    }
+
+    fn add_dir(&mut self, name: &str, details: &FileDetails) -> Result<()> {
+        match self {
+            Self::Tar(builder) => {
+                let mut header =
+                    tar_header(tar::EntryType::Directory, 0, details, DEFAULT_DIR_MODE);
+                builder.append_data(&mut header, name, io::empty())?;
+            }
+            Self::Zip(zip) => {
+                zip.add_directory(name, zip_options(details, DEFAULT_DIR_MODE))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn add_symlink(
+        &mut self,
+        name: &str,
+        target: &str,
+        target_type: &str,
+        details: &FileDetails,
+    ) -> Result<()> {
+        match self {
+            Self::Tar(builder) => {
+                // Targets are assumed to be files when extracted, thus only
+                // the type of other targets is recorded.
+                if target_type != "file" {
+                    let records = pax_record(PAX_SYMLINK_TARGET_TYPE, target_type);
+                    let mut pax_header = tar::Header::new_ustar();
+                    pax_header.set_entry_type(tar::EntryType::XHeader);
+                    pax_header.set_size(records.len() as u64);
+                    builder.append_data(&mut pax_header, "././@PaxHeader", records.as_slice())?;
+                }
+
+                // Targets too long for the header are written as a GNU long link record.
+                let mut header =
+                    tar_header(tar::EntryType::Symlink, 0, details, DEFAULT_SYMLINK_MODE);
+                builder
+                    .append_link(&mut header, name, target)
+                    .with_context(|| format!("Invalid symlink target: \"{}\"", target))?;
+            }
+            Self::Zip(zip) => {
+                // Symlinks are flagged by the Unix mode bits of the entry,
+                // the type of their target is found out when extracted.
+                zip.add_symlink(name, target, zip_options(details, DEFAULT_SYMLINK_MODE))?;
+            }
+        }
+        Ok(())
+    }
+
+    // Starts a file entry, whose content is then written with write_data.
+    fn start_file(&mut self, name: &str, size: u64, details: &FileDetails) -> Result<()> {
+        match self {
+            Self::Tar(builder) => {
+                // The header is written upfront, with no content, so the
+                // content can then be written as it's retrieved.
+                let mut header =
+                    tar_header(tar::EntryType::Regular, size, details, DEFAULT_FILE_MODE);
+                builder.append_data(&mut header, name, io::empty())?;
+            }
+            Self::Zip(zip) => {
+                zip.start_file(name, zip_options(details, DEFAULT_FILE_MODE))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_data(&mut self, data: &[u8]) -> Result<()> {
+        match self {
+            Self::Tar(builder) => builder.get_mut().write_all(data)?,
+            Self::Zip(zip) => zip.write_all(data)?,
+        }
+        Ok(())
+    }
+
+    // Ends a file entry once all its content has been written.
+    fn end_file(&mut self, size: u64) -> Result<()> {
+        if let Self::Tar(builder) = self {
+            // tar entries are padded to a multiple of the block size.
+            let padding = (512 - size % 512) % 512;
+            builder.get_mut().write_all(&[0; 512][..padding as usize])?;
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            Self::Tar(builder) => builder.into_inner()?.finish()?,
+            Self::Zip(mut zip) => zip.finish()?.sync_all()?,
+        }
+        Ok(())
+    }
+}
+
+/// # Retrieves the files of a FilesContainer into a tar or zip archive.
+///
+/// The files are written into the archive as they are retrieved, without
+/// writing them to the file system, with up to `opts.jobs` ranges of them
+/// being retrieved at a time. Directories and symlinks, including the type
+/// of their targets in tar archives, are kept in the archive.
+pub async fn process_get_archive_command(
+    safe: &mut Safe,
+    source: &str,
+    archive: &str,
+    opts: &FilesGetOptions,
+    output_fmt: OutputFmt,
+) -> Result<()> {
+    if opts.jobs == 0 {
+        bail!("The number of jobs must be greater than zero");
+    }
+    let format = ArchiveFormat::from_path(archive)?;
+    let path = Path::new(archive);
+    if path.exists() && !matches!(opts.exists, FileExistsAction::Overwrite) {
+        bail!(
+            "Archive \"{}\" already exists, use --exists=overwrite to replace it",
+            archive
+        );
+    }
+
+    let cache = if opts.use_cache {
+        BlobCache::open_or_warn()
+    } else {
+        None
+    };
+    let (version, files_map) = fetch_files_map(safe, source, cache.as_ref()).await?;
+    let (files_map, skipped_symlinks) =
+        apply_symlink_policies(files_map, opts.symlinks, opts.external_symlinks)?;
+
+    let mut writer = ArchiveWriter::create(path, format)?;
+    let result = write_archive(
+        safe,
+        cache.as_ref(),
+        source,
+        &files_map,
+        &mut writer,
+        opts.jobs,
+        &opts.progress,
+        output_fmt,
+    )
+    .await;
+    let mut processed_files = match result.and_then(|processed_files| {
+        writer.finish()?;
+        Ok(processed_files)
+    }) {
+        Ok(processed_files) => processed_files,
+        Err(err) => {
+            // An incomplete archive is of no use, thus it's not kept.
+            if let Err(remove_err) = fs::remove_file(path) {
+                warn!(
+                    "Failed to remove incomplete archive \"{}\": {}",
+                    archive, remove_err
+                );
+            }
+            return Err(err);
+        }
+    };
+
+    let skipped = skipped_symlinks.len() as u64;
+    for (path, change) in skipped_symlinks {
+        processed_files.insert(archive_entry_name(source, &path)?, change);
+    }
+
+    if OutputFmt::Pretty == output_fmt {
+        let count = processed_files.len() as u64 - skipped;
+        println!(
+            "Done. Archived {} {} to {}",
+            count,
+            pluralize("entry", "entries", count),
+            path.display()
+        );
+        if skipped > 0 {
+            println!("  skipped symlinks: {}", skipped);
+        }
+    } else {
+        let url = match SafeUrl::from_url(source) {
+            Ok(mut safeurl) => {
+                safeurl.set_content_version(Some(version));
+                safeurl.to_string()
+            }
+            Err(_) => source.to_string(),
+        };
+        println!("{}", serialise_output(&(url, processed_files), output_fmt));
+    }
+
+    Ok(())
+}
+
+// Kind of a FilesMap entry, with the attributes needed to archive it.
+#[derive(Clone, Copy)]
+enum EntryKind<'a> {
+    Dir,
+    Symlink {
+        target: &'a str,
+        target_type: &'a str,
+    },
+    File {
+        link: &'a str,
+        size: u64,
+    },
+}
+
+// Part of an entry to be added to the archive: a whole dir or symlink, or a
+// range of a file's content, which is retrieved before being written.
+struct ArchivePart<'a> {
+    // position of the entry in the FilesMap, to report progress.
+    idx: usize,
+    name: String,
+    details: &'a FileDetails,
+    kind: EntryKind<'a>,
+    // range of a file's content, None for other entries and empty files.
+    range: Option<(u64, u64)>,
+}
+
+// Adds each entry of the FilesMap to the archive, retrieving the files'
+// content. The parts of the entries are retrieved concurrently, up to `jobs`
+// at a time, but written in order as soon as each of them is retrieved.
+#[allow(clippy::too_many_arguments)]
+async fn write_archive(
+    safe: &Safe,
+    cache: Option<&BlobCache>,
+    source: &str,
+    files_map: &FilesMap,
+    writer: &mut ArchiveWriter,
+    jobs: usize,
+    progress: &ProgressIndicator,
+    output_fmt: OutputFmt,
+) -> Result<ProcessedFiles> {
+    let parts = archive_parts(source, files_map)?;
+    let total_bytes: u64 = parts
+        .iter()
+        .filter_map(|part| part.range.map(|(start, end)| end - start))
+        .sum();
+    let bar = match progress {
+        ProgressIndicator::Bars => {
+            // stdout is left for the report with a non-pretty output format.
+            let draw_target = if OutputFmt::Pretty == output_fmt {
+                ProgressDrawTarget::stdout()
+            } else {
+                ProgressDrawTarget::stderr()
+            };
+            let bar = ProgressBar::with_draw_target(total_bytes, draw_target);
+            bar.set_style(ProgressStyle::default_bar()
+                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})  Transfer")
+                .progress_chars("##-"));
+            Some(bar)
+        }
+        _ => None,
+    };
+
+    let mut retrieved_parts = stream::iter(parts.into_iter().map(|part| async move {
+        let data = match (part.kind, part.range) {
+            (EntryKind::File { link, size }, Some((start, end))) => {
+                retrieve_range(safe, cache, link, size, start, end).await?
+            }
+            _ => vec![],
+        };
+        Ok::<_, anyhow::Error>((part, data))
+    }))
+    .buffered(jobs);
+
+    let mut processed_files = ProcessedFiles::new();
+    let total_files = files_map.len();
+    while let Some(retrieved) = retrieved_parts.next().await {
+        let (part, data) = retrieved?;
+        let name = part.name.as_str();
+        let (is_first, is_last) = match (&part.kind, part.range) {
+            (EntryKind::File { size, .. }, Some((start, end))) => (start == 0, end == *size),
+            _ => (true, true),
+        };
+
+        if is_first {
+            trace!("Adding entry #{} to archive as \"{}\"", part.idx, name);
+            match progress {
+                ProgressIndicator::Text if OutputFmt::Pretty == output_fmt => {
+                    println!("{} - entries: {} of {}", name, part.idx + 1, total_files);
+                }
+                ProgressIndicator::Text => {
+                    let event = serde_json::json!({
+                        "path": name,
+                        "current_entry": part.idx + 1,
+                        "total_entries": total_files,
+                    });
+                    eprintln!("{}", event);
+                }
+                _ => {}
+            }
+
+            match &part.kind {
+                EntryKind::Dir => writer.add_dir(name, part.details)?,
+                EntryKind::Symlink {
+                    target,
+                    target_type,
+                } => writer.add_symlink(name, target, target_type, part.details)?,
+                EntryKind::File { size, .. } => writer.start_file(name, *size, part.details)?,
+            }
+        }
+
+        if !data.is_empty() {
+            writer.write_data(&data)?;
+            if let Some(bar) = &bar {
+                bar.inc(data.len() as u64);
+            }
+        }
+
+        if is_last {
+            let link = match part.kind {
+                EntryKind::File { link, size } => {
+                    writer.end_file(size)?;
+                    link
+                }
+                _ => "",
+            };
+            processed_files.insert(part.name, ("+".to_string(), link.to_string()));
+        }
+    }
+
+    if let Some(bar) = bar {
+        bar.finish_and_clear();
+    }
+    Ok(processed_files)
+}
+
+// Splits the entries of the FilesMap into the parts to be added to the
+// archive, the content of the files in ranges of DOWNLOAD_RANGE_SIZE.
+fn archive_parts<'a>(source: &str, files_map: &'a FilesMap) -> Result<Vec<ArchivePart<'a>>> {
+    let mut parts = vec![];
+    for (idx, (path, details)) in files_map.iter().enumerate() {
+        let attr = |name: &str| {
+            details
+                .get(name)
+                .map(String::as_str)
+                .ok_or_else(|| anyhow!("Missing {} of \"{}\" in FilesMap", name, path))
+        };
+        let kind = match attr("type")? {
+            "inode/directory" => EntryKind::Dir,
+            "inode/symlink" => EntryKind::Symlink {
+                target: attr("symlink_target")?,
+                target_type: details
+                    .get("symlink_target_type")
+                    .map_or("file", String::as_str),
+            },
+            _ => EntryKind::File {
+                link: details.get("link").map_or("", String::as_str),
+                size: attr("size")?
+                    .parse()
+                    .with_context(|| format!("Invalid file size of \"{}\"", path))?,
+            },
+        };
+
+        let mut ranges = vec![];
+        if let EntryKind::File { size, .. } = kind {
+            let mut offset = 0;
+            while offset < size {
+                let end = std::cmp::min(offset + DOWNLOAD_RANGE_SIZE, size);
+                ranges.push(Some((offset, end)));
+                offset = end;
+            }
+        }
+        if ranges.is_empty() {
+            ranges.push(None);
+        }
+
+        let name = archive_entry_name(source, path)?;
+        for range in ranges {
+            parts.push(ArchivePart {
+                idx,
+                name: name.clone(),
+                details,
+                kind,
+                range,
+            });
+        }
+    }
+    Ok(parts)
+}
+
+// Retrieves a range of the content of a file.
+async fn retrieve_range(
+    safe: &Safe,
+    cache: Option<&BlobCache>,
+    link: &str,
+    size: u64,
+    start: u64,
+    end: u64,
+) -> Result<Vec<u8>> {
+    // Small files are retrieved in full so they are stored in the cache.
+    let range = if size <= DOWNLOAD_RANGE_SIZE {
+        None
+    } else {
+        Some((Some(start), Some(end)))
+    };
+    let data = files_get_blob(safe, cache, link, range).await?;
+    if data.len() as u64 != end - start {
+        bail!(
+            "Unexpected size of content retrieved from {}, expected {} bytes but got {}",
+            link,
+            end - start,
+            data.len()
+        );
+    }
+    Ok(data)
+}
+
+// Returns the name of a FilesMap entry within the archive, i.e. its path
+// without the leading slash. A single file targeted by the URL has no path
+// in the FilesMap, thus it's named after the last component of the URL path.
+fn archive_entry_name(source: &str, path: &str) -> Result<String> {
+    let name = path.trim_matches('/');
+    if !name.is_empty() {
+        return Ok(name.to_string());
+    }
+
+    let urlpath = SafeUrl::from_url(source)?.path_decoded()?;
+    match Path::new(&urlpath).file_name() {
+        Some(file_name) => Ok(file_name.to_string_lossy().to_string()),
+        None => bail!("Cannot determine the name of the file at: {}", source),
+    }
+}
+
+fn tar_header(
+    entry_type: tar::EntryType,
+    size: u64,
+    details: &FileDetails,
+    default_mode: u32,
+) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(entry_type);
+    header.set_size(size);
+    header.set_mode(entry_mode(details, default_mode));
+    if let Some(modified) = entry_modified(details) {
+        header.set_mtime(modified.timestamp().max(0) as u64);
+    }
+    header
+}
+
+fn zip_options(details: &FileDetails, default_mode: u32) -> zip::write::FileOptions {
+    let mut options =
+        zip::write::FileOptions::default().unix_permissions(entry_mode(details, default_mode));
+    if let Some(modified) = entry_modified(details) {
+        // zip timestamps can only represent the years 1980 to 2107.
+        let datetime = zip::DateTime::from_date_and_time(
+            modified.year() as u16,
+            modified.month() as u8,
+            modified.day() as u8,
+            modified.hour() as u8,
+            modified.minute() as u8,
+            modified.second() as u8,
+        );
+        if let Ok(datetime) = datetime {
+            options = options.last_modified_time(datetime);
+        }
+    }
+    options
+}
+
+// Returns the permission bits recorded for an entry, e.g. "33261" (0o100755).
+fn entry_mode(details: &FileDetails, default_mode: u32) -> u32 {
+    details
+        .get("mode_bits")
+        .and_then(|mode_bits| mode_bits.parse::<u32>().ok())
+        .map_or(default_mode, |mode| mode & 0o7777)
+}
+
+// Returns the modification time of the original file if it was recorded,
+// or the time the entry was last modified in the FilesContainer otherwise.
+fn entry_modified(details: &FileDetails) -> Option<DateTime<chrono::FixedOffset>> {
+    details
+        .get("o_modified")
+        .or_else(|| details.get("modified"))
+        .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+}
+
+// Encodes a PAX extended header record, which is prefixed with its own length.
+fn pax_record(key: &str, value: &str) -> Vec<u8> {
+    let rest = format!(" {}={}\n", key, value);
+    let mut len = rest.len();
+    while len != rest.len() + len.to_string().len() {
+        len = rest.len() + len.to_string().len();
+    }
+    format!("{}{}", len, rest).into_bytes()
+}
+
+/// # Extracts an archive into a staging dir, to be uploaded in its place.
+///
+/// Entries whose paths could escape the staging dir, e.g. absolute paths or
+/// paths with '..' components, are skipped. Symlinks are recreated as such.
+pub fn stage_archive(archive: &str) -> Result<StagedLocation> {
+    let format = ArchiveFormat::from_path(archive)?;
+    let file = fs::File::open(archive)
+        .with_context(|| format!("Couldn't open archive: \"{}\"", archive))?;
+
+    let staged = StagedLocation::new_empty(archive)?;
+    let root = PathBuf::from(&staged.location);
+    debug!("Extracting archive \"{}\" into {}", archive, root.display());
+    match format {
+        ArchiveFormat::Tar => extract_tar(file, &root),
+        ArchiveFormat::TarGz => extract_tar(GzDecoder::new(file), &root),
+        ArchiveFormat::Zip => extract_zip(file, &root),
+    }
+    .with_context(|| format!("Failed to extract archive \"{}\"", archive))?;
+
+    Ok(staged)
+}
+
+fn extract_tar<R: Read>(reader: R, root: &Path) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let dest = match extraction_path(root, &path)? {
+            Some(dest) => dest,
+            None => continue,
+        };
+
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => create_dir_all(&dest)?,
+            tar::EntryType::Regular | tar::EntryType::Continuous => {
+                let mode = entry.header().mode().ok();
+                let mut file = create_file(&dest)?;
+                io::copy(&mut entry, &mut file)
+                    .with_context(|| format!("Failed to extract \"{}\"", path.display()))?;
+                set_file_mode(&dest, mode);
+            }
+            tar::EntryType::Symlink => {
+                let target = entry
+                    .link_name()?
+                    .ok_or_else(|| anyhow!("Missing target of symlink \"{}\"", path.display()))?
+                    .into_owned();
+                let target_type = tar_symlink_target_type(&mut entry)?;
+                create_symlink(&target, &dest, &target_type)?;
+            }
+            other => warn!(
+                "Skipping archive entry \"{}\" of unsupported type: {:?}",
+                path.display(),
+                other
+            ),
+        }
+    }
+    Ok(())
+}
+
+fn extract_zip(file: fs::File, root: &Path) -> Result<()> {
+    let mut archive = zip::ZipArchive::new(file)?;
+    let mut symlinks = vec![];
+    for idx in 0..archive.len() {
+        let mut entry = archive.by_index(idx)?;
+        let path = PathBuf::from(entry.name());
+        let dest = match extraction_path(root, &path)? {
+            Some(dest) => dest,
+            None => continue,
+        };
+
+        // Symlinks are flagged by their mode, their content being the target
+        let mode = entry.unix_mode();
+        if entry.is_dir() {
+            create_dir_all(&dest)?;
+        } else if mode.map_or(false, |mode| mode & MODE_TYPE_MASK == MODE_SYMLINK) {
+            let mut target = String::new();
+            entry
+                .read_to_string(&mut target)
+                .with_context(|| format!("Invalid target of symlink \"{}\"", path.display()))?;
+            symlinks.push((path, PathBuf::from(target)));
+        } else {
+            let mut file = create_file(&dest)?;
+            io::copy(&mut entry, &mut file)
+                .with_context(|| format!("Failed to extract \"{}\"", path.display()))?;
+            set_file_mode(&dest, mode);
+        }
+    }
+
+    // zip entries don't record the type of a symlink's target, thus symlinks
+    // are created once the rest of the entries were extracted, a dir target
+    // being found among them.
+    for (path, target) in symlinks {
+        if let Some(dest) = extraction_path(root, &path)? {
+            let is_dir = dest
+                .parent()
+                .map_or(false, |parent| parent.join(&target).is_dir());
+            let target_type = if is_dir { "dir" } else { "file" };
+            create_symlink(&target, &dest, target_type)?;
+        }
+    }
+    Ok(())
+}
+
+// Returns the path an archive entry is to be extracted to, or None if the
+// entry is to be skipped since it could be written outside the staging dir.
+fn extraction_path(root: &Path, path: &Path) -> Result<Option<PathBuf>> {
+    let mut names = vec![];
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => names.push(name),
+            Component::CurDir => {}
+            _ => {
+                warn!(
+                    "Skipping archive entry with unsafe path: {}",
+                    path.display()
+                );
+                return Ok(None);
+            }
+        }
+    }
+    if names.is_empty() {
+        return Ok(None);
+    }
+
+    let is_symlink = |path: &Path| {
+        fs::symlink_metadata(path)
+            .map(|metadata| metadata.file_type().is_symlink())
+            .unwrap_or(false)
+    };
+    let mut dest = root.to_path_buf();
+    for (idx, name) in names.iter().enumerate() {
+        dest.push(name);
+        // An entry is never extracted through a symlink extracted earlier.
+        if idx < names.len() - 1 && is_symlink(&dest) {
+            warn!(
+                "Skipping archive entry within a symlink: {}",
+                path.display()
+            );
+            return Ok(None);
+        }
+    }
+
+    // A symlink extracted earlier with the same path is replaced, not followed.
+    if is_symlink(&dest) {
+        fs::remove_file(&dest)
+            .with_context(|| format!("Failed to replace symlink \"{}\"", dest.display()))?;
+    }
+    Ok(Some(dest))
+}
+
+fn tar_symlink_target_type<R: Read>(entry: &mut tar::Entry<R>) -> Result<String> {
+    if let Some(extensions) = entry.pax_extensions()? {
+        for extension in extensions {
+            let extension = extension?;
+            if extension.key() == Ok(PAX_SYMLINK_TARGET_TYPE) {
+                if let Ok(value) = extension.value() {
+                    return Ok(value.to_string());
+                }
+            }
+        }
+    }
+    Ok("file".to_string())
+}
+
+fn create_dir_all(path: &Path) -> Result<()> {
+    fs::create_dir_all(path)
+        .with_context(|| format!("Couldn't create path: \"{}\"", path.display()))
+}
+
+fn create_file(path: &Path) -> Result<fs::File> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+    fs::File::create(path).with_context(|| format!("Couldn't create file: \"{}\"", path.display()))
+}
+
+fn create_symlink(target: &Path, link: &Path, target_type: &str) -> Result<()> {
+    if let Some(parent) = link.parent() {
+        create_dir_all(parent)?;
+    }
+    symlink(target, link, target_type).with_context(|| {
+        format!(
+            "Could not create symlink: {} --> {}",
+            link.display(),
+            target.display()
+        )
+    })
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path, _target_type: &str) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path, target_type: &str) -> io::Result<()> {
+    if target_type == "dir" {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+// Sets the permissions of an extracted file, so they are recorded when uploaded.
+#[cfg(unix)]
+fn set_file_mode(path: &Path, mode: Option<u32>) {
+    use std::os::unix::fs::PermissionsExt;
+    if let Some(mode) = mode {
+        let permissions = fs::Permissions::from_mode(mode & 0o7777);
+        if let Err(err) = fs::set_permissions(path, permissions) {
+            warn!(
+                "Couldn't set permissions of \"{}\": {}",
+                path.display(),
+                err
+            );
+        }
+    }
+}
+
+// mode bits are not meaningful on non-unix platforms.
+#[cfg(not(unix))]
+fn set_file_mode(_path: &Path, _mode: Option<u32>) {}
@@ -0,0 +1,192 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    helpers::{pluralize, serialise_output},
+    OutputFmt,
+};
+use anyhow::Result;
+use prettytable::{format::FormatBuilder, Table};
+use serde::Serialize;
+use sn_api::{files::FilesMap, SafeUrl, XorName};
+use std::{cmp::Reverse, collections::BTreeMap};
+
+/// # Disk usage of a directory, and of all its content.
+///
+/// The apparent size is the total size of the files found in the directory,
+/// while the unique size only counts once each blob shared by several of
+/// them, since blobs are content-addressed and thus only stored once.
+#[derive(Debug, Default, Serialize)]
+pub struct DirUsage {
+    pub files: u64,
+    pub apparent_size: u64,
+    pub unique_size: u64,
+}
+
+/// # A blob linked to by several files of a FilesContainer, by the link of the first one.
+#[derive(Debug, Serialize)]
+pub struct DuplicatedBlob {
+    pub link: String,
+    pub size: u64,
+    pub paths: Vec<String>,
+}
+
+impl DuplicatedBlob {
+    // Size which would be stored if each file had its own blob.
+    fn saved_size(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+/// # Disk usage report of a FilesContainer, as reported by `safe files du`
+#[derive(Debug, Serialize)]
+pub struct DuReport {
+    pub target: String,
+    pub total: DirUsage,
+    pub dirs: BTreeMap<String, DirUsage>,
+    pub duplicated_blobs: Vec<DuplicatedBlob>,
+}
+
+/// Generates the disk usage report of a FilesMap, which can be that of a
+/// FilesContainer or the one a local location would have once uploaded.
+///
+/// Only files are accounted for, since directories and symlinks have no
+/// blob of their own. The usage of each directory includes that of its
+/// subdirectories, and the total is that of the root directory. Files share
+/// a blob if their links have the same XorName, even if the media type in
+/// their links differ.
+pub fn du_files_map(target: &str, files_map: &FilesMap) -> Result<DuReport> {
+    // blobs found beneath each directory, with their size
+    let mut dir_blobs = BTreeMap::<String, BTreeMap<XorName, u64>>::new();
+    let mut dirs = BTreeMap::<String, DirUsage>::new();
+    let mut blobs = BTreeMap::<XorName, (&str, u64, Vec<String>)>::new();
+
+    for (path, details) in files_map.iter() {
+        let link = details.get("link").map_or("", String::as_str);
+        let is_file = match details.get("type") {
+            Some(t) => t != "inode/directory" && t != "inode/symlink",
+            None => false,
+        };
+        if !is_file || link.is_empty() {
+            continue;
+        }
+        let size = details
+            .get("size")
+            .and_then(|size| size.parse::<u64>().ok())
+            .unwrap_or(0);
+
+        let xorname = SafeUrl::from_url(link)?.xorname();
+        let (_, _, paths) = blobs.entry(xorname).or_insert((link, size, vec![]));
+        paths.push(path.to_string());
+
+        for dir in parent_dirs(path) {
+            let usage = dirs.entry(dir.clone()).or_default();
+            usage.files += 1;
+            usage.apparent_size += size;
+            dir_blobs.entry(dir).or_default().insert(xorname, size);
+        }
+    }
+
+    for (dir, dir_blobs) in dir_blobs.iter() {
+        if let Some(usage) = dirs.get_mut(dir) {
+            usage.unique_size = dir_blobs.values().sum();
+        }
+    }
+
+    let mut duplicated_blobs: Vec<DuplicatedBlob> = blobs
+        .into_iter()
+        .filter(|(_, (_, _, paths))| paths.len() > 1)
+        .map(|(_, (link, size, paths))| DuplicatedBlob {
+            link: link.to_string(),
+            size,
+            paths,
+        })
+        .collect();
+    // the blobs saving the most storage first
+    duplicated_blobs.sort_by_key(|blob| Reverse(blob.saved_size()));
+
+    let total = dirs.remove("/").unwrap_or_default();
+    Ok(DuReport {
+        target: target.to_string(),
+        total,
+        dirs,
+        duplicated_blobs,
+    })
+}
+
+// Prints the disk usage report of `safe files du`, similar to that of unix `du`.
+pub fn print_du_report(report: &DuReport, output_fmt: OutputFmt) {
+    if OutputFmt::Pretty != output_fmt {
+        println!("{}", serialise_output(report, output_fmt));
+        return;
+    }
+
+    println!("Disk usage of \"{}\":", report.target);
+    let mut table = Table::new();
+    let format = FormatBuilder::new()
+        .column_separator(' ')
+        .padding(0, 1)
+        .build();
+    table.set_format(format);
+    table.add_row(row!["FILES", "APPARENT", "UNIQUE", "DIRECTORY"]);
+    for (dir, usage) in report.dirs.iter() {
+        table.add_row(row![
+            usage.files,
+            usage.apparent_size,
+            usage.unique_size,
+            dir
+        ]);
+    }
+    let total = &report.total;
+    table.add_row(row![
+        total.files,
+        total.apparent_size,
+        total.unique_size,
+        "/"
+    ]);
+    table.printstd();
+
+    if !report.duplicated_blobs.is_empty() {
+        let count = report.duplicated_blobs.len() as u64;
+        println!(
+            "\n{} {} linked to by several files:",
+            count,
+            pluralize("blob is", "blobs are", count)
+        );
+        for blob in report.duplicated_blobs.iter() {
+            println!(
+                "{} ({} bytes, {} files, {} bytes saved)",
+                blob.link,
+                blob.size,
+                blob.paths.len(),
+                blob.saved_size()
+            );
+            for path in blob.paths.iter() {
+                println!("  {}", path);
+            }
+        }
+    }
+
+    println!(
+        "\nApparent size: {} bytes   Unique size: {} bytes   Saved by deduplication: {} bytes",
+        total.apparent_size,
+        total.unique_size,
+        total.apparent_size - total.unique_size
+    );
+}
+
+// Returns the root dir, "/", and each of the parent dirs of a FilesMap path.
+fn parent_dirs(path: &str) -> Vec<String> {
+    let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+    let mut dirs = vec!["/".to_string()];
+    for idx in 1..components.len() {
+        dirs.push(format!("/{}", components[..idx].join("/")));
+    }
+    dirs
+}
@@ -0,0 +1,543 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::files_get::JOURNAL_FILE_NAME;
+use crate::operations::config::get_cli_config_path;
+use anyhow::{anyhow, bail, Context, Result};
+use filetime::FileTime;
+use ignore::{
+    gitignore::{Gitignore, GitignoreBuilder},
+    overrides::{Override, OverrideBuilder},
+};
+use log::{debug, trace, warn};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use sn_api::files::ProcessedFiles;
+use std::{
+    collections::BTreeSet,
+    env, fs,
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+// Name of the file, in the root of a local location, listing the paths to
+// be ignored using gitignore syntax.
+pub const SAFEIGNORE_FILE_NAME: &str = ".safeignore";
+
+// Change marker set in ProcessedFiles for those files filtered out,
+// and for the symlinks skipped when retrieving files.
+pub const SKIPPED_MARKER: &str = "S";
+
+// Prefix of the name of the dirs the files of a location are staged in.
+const STAGING_DIR_PREFIX: &str = ".safe-staging-";
+
+// Dir within the CLI's config dir to stage the files of a location in.
+const STAGING_DIRNAME: &str = "staging";
+
+// Patterns of the local files which are never uploaded, as they're left by
+// the CLI itself, e.g. the journal of an interrupted `files get`, or the
+// staging dirs left by an upload which was killed.
+const UPLOAD_EXCLUSIONS: &[&str] = &[JOURNAL_FILE_NAME, ".safe-staging-*"];
+
+/// # Include/exclude filter for the files of a put, sync or get.
+///
+/// A path is excluded if it, or any of its parent directories, matches a
+/// pattern of the `.safeignore` file or an `--exclude` glob. When there are
+/// `--include` globs, a file not matching any of them is also excluded.
+/// The filters of the local files to upload also exclude those the CLI
+/// leaves in the location, like the journal of an interrupted `files get`.
+#[derive(Debug)]
+pub struct FilesFilter {
+    overrides: Override,
+    safeignore: Gitignore,
+    upload_exclusions: Gitignore,
+    // paths excluded regardless of the globs, e.g. the files uploaded apart.
+    excluded_paths: BTreeSet<PathBuf>,
+}
+
+impl FilesFilter {
+    // Creates a filter for paths relative to root, honouring the
+    // .safeignore file found in root if there is one.
+    pub fn new(root: &Path, include: &[String], exclude: &[String]) -> Result<Self> {
+        let overrides = build_overrides(root, include, exclude)?;
+
+        let safeignore_path = root.join(SAFEIGNORE_FILE_NAME);
+        let safeignore = if safeignore_path.is_file() {
+            debug!("Honouring ignore file {}", safeignore_path.display());
+            let (safeignore, err) = Gitignore::new(&safeignore_path);
+            if let Some(err) = err {
+                bail!("Invalid \"{}\" file: {}", safeignore_path.display(), err);
+            }
+            safeignore
+        } else {
+            Gitignore::empty()
+        };
+
+        Ok(Self {
+            overrides,
+            safeignore,
+            upload_exclusions: Gitignore::empty(),
+            excluded_paths: BTreeSet::new(),
+        })
+    }
+
+    // Creates a filter for the files of a local location to be uploaded,
+    // which also excludes the files left in it by the CLI.
+    pub fn for_upload(root: &Path, include: &[String], exclude: &[String]) -> Result<Self> {
+        let mut filter = Self::new(root, include, exclude)?;
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in UPLOAD_EXCLUSIONS {
+            builder
+                .add_line(None, pattern)
+                .with_context(|| format!("Invalid exclusion pattern: {}", pattern))?;
+        }
+        filter.upload_exclusions = builder
+            .build()
+            .context("Failed to build the upload exclusions")?;
+        Ok(filter)
+    }
+
+    // Creates a filter for relative paths which only applies the include and
+    // exclude globs, e.g. to the paths of a FilesContainer.
+    pub fn from_globs(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            overrides: build_overrides(Path::new(""), include, exclude)?,
+            safeignore: Gitignore::empty(),
+            upload_exclusions: Gitignore::empty(),
+            excluded_paths: BTreeSet::new(),
+        })
+    }
+
+    // Excludes the given paths, relative to the filter's root.
+    pub fn exclude_paths(&mut self, paths: impl IntoIterator<Item = PathBuf>) {
+        self.excluded_paths.extend(paths);
+    }
+
+    // Returns true if no path can be excluded by this filter, other than
+    // the files the CLI leaves in the locations uploaded.
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty() && self.safeignore.is_empty() && self.excluded_paths.is_empty()
+    }
+
+    // Returns true if any entry of a local location, walked the same way
+    // it's uploaded, is excluded by this filter. Entries which can't be read
+    // are assumed to be excluded, so they're reported when staging them.
+    pub fn excludes_any(&self, location: &str, recursive: bool, follow_links: bool) -> bool {
+        let src = Path::new(location);
+        if !src.is_dir() {
+            let rel_path = src
+                .strip_prefix(location_filter_root(location))
+                .unwrap_or(src);
+            return self.is_excluded(rel_path, false);
+        }
+
+        let max_depth = if recursive { usize::MAX } else { 1 };
+        WalkDir::new(src)
+            .min_depth(1)
+            .max_depth(max_depth)
+            .follow_links(follow_links)
+            .into_iter()
+            .any(|entry| match entry {
+                Ok(entry) => entry.path().strip_prefix(src).map_or(true, |rel_path| {
+                    self.is_excluded(rel_path, entry.file_type().is_dir())
+                }),
+                Err(_) => true,
+            })
+    }
+
+    // Returns true if the path, relative to the filter's root, is excluded.
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        let parent_excluded = path
+            .ancestors()
+            .skip(1)
+            .filter(|p| !p.as_os_str().is_empty())
+            .any(|p| self.matches_exclusion(p, true));
+
+        parent_excluded || self.matches_exclusion(path, is_dir)
+    }
+
+    // Returns true if the path itself, rather than any of its parent
+    // directories, matches one of the include globs.
+    pub fn is_included(&self, path: &Path, is_dir: bool) -> bool {
+        self.overrides.matched(path, is_dir).is_whitelist()
+    }
+
+    fn matches_exclusion(&self, path: &Path, is_dir: bool) -> bool {
+        self.safeignore.matched(path, is_dir).is_ignore()
+            || self.upload_exclusions.matched(path, is_dir).is_ignore()
+            || self.overrides.matched(path, is_dir).is_ignore()
+            || self.excluded_paths.contains(path)
+    }
+}
+
+fn build_overrides(root: &Path, include: &[String], exclude: &[String]) -> Result<Override> {
+    let mut builder = OverrideBuilder::new(root);
+    for glob in include {
+        builder
+            .add(glob)
+            .with_context(|| format!("Invalid include pattern: {}", glob))?;
+    }
+    // overrides are whitelists by default, a '!' makes them ignore globs.
+    for glob in exclude {
+        builder
+            .add(&format!("!{}", glob))
+            .with_context(|| format!("Invalid exclude pattern: {}", glob))?;
+    }
+    builder
+        .build()
+        .context("Failed to build the include/exclude filter")
+}
+
+/// # A filtered copy of a local location, to be uploaded in its place.
+///
+/// Files are hard linked into a staging dir on the same device as the
+/// location, so their content is not copied and their metadata is the same
+/// as that of the original files. They are only copied if they can't be
+/// hard linked. The staging dir is removed when this is dropped.
+#[derive(Debug)]
+pub struct StagedLocation {
+    dir: PathBuf,
+    // location of the original files, as provided by the user.
+    original: String,
+    // location of the filtered copy, to be provided to sn_api instead.
+    pub location: String,
+    // local paths of the files which were filtered out.
+    pub skipped: Vec<String>,
+}
+
+impl StagedLocation {
+    // Creates an empty staging dir, to be filled with the content of the
+    // original location, e.g. the entries extracted from an archive.
+    pub fn new_empty(original: &str) -> Result<Self> {
+        let dir = staging_dir();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Couldn't create path: \"{}\"", dir.display()))?;
+
+        Ok(Self {
+            location: format!("{}/", dir.display()),
+            dir,
+            original: format!("{}/", original),
+            skipped: vec![],
+        })
+    }
+
+    // Translates a local path reported by sn_api for the staged location
+    // back to the corresponding path in the original location.
+    pub fn original_path(&self, path: &str) -> String {
+        let staged_base = self.location.trim_end_matches('/');
+        let original_base = self.original.trim_end_matches('/');
+        match path.strip_prefix(staged_base) {
+            Some(rest) => format!("{}{}", original_base, rest),
+            None => path.to_string(),
+        }
+    }
+
+    // Translates the ProcessedFiles of the staged location to the original
+    // location, adding the files which were filtered out as skipped.
+    pub fn restore_processed_files(&self, processed_files: ProcessedFiles) -> ProcessedFiles {
+        let mut restored: ProcessedFiles = processed_files
+            .into_iter()
+            .map(|(path, change)| (self.original_path(&path), change))
+            .collect();
+        for path in self.skipped.iter() {
+            restored.insert(
+                path.to_string(),
+                (SKIPPED_MARKER.to_string(), "".to_string()),
+            );
+        }
+        restored
+    }
+}
+
+impl Drop for StagedLocation {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_dir_all(&self.dir) {
+            warn!(
+                "Failed to remove staging dir \"{}\": {}",
+                self.dir.display(),
+                err
+            );
+        }
+    }
+}
+
+// Returns the dir whose .safeignore applies to a local location, which
+// is also the root the include/exclude globs are relative to.
+pub fn location_filter_root(location: &str) -> PathBuf {
+    let path = Path::new(location);
+    if path.is_dir() {
+        path.to_path_buf()
+    } else {
+        match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    }
+}
+
+// Creates a filtered copy of a local location in a temporary dir, so it can
+// be provided to sn_api which doesn't support filtering the files it uploads.
+// The copy keeps the location's name and trailing slash, so the paths of the
+// files within the FilesContainer are the same as for the original location.
+pub fn stage_location(
+    location: &str,
+    recursive: bool,
+    follow_links: bool,
+    filter: &FilesFilter,
+) -> Result<StagedLocation> {
+    let src = Path::new(location);
+    if !src.exists() {
+        bail!("No such file or directory: \"{}\"", location);
+    }
+    let name = src
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve path: \"{}\"", location))?
+        .file_name()
+        .map(|name| name.to_os_string())
+        .ok_or_else(|| anyhow!("Cannot filter the files of location: \"{}\"", location))?;
+
+    let dir = staging_dir_near(src);
+    let staged_root = dir.join(&name);
+
+    let mut staged = StagedLocation {
+        dir,
+        original: location.to_string(),
+        location: staged_root.display().to_string(),
+        skipped: vec![],
+    };
+    if location.ends_with('/') {
+        staged.location.push('/');
+    }
+
+    let filter_root = location_filter_root(location);
+    if src.is_dir() {
+        fs::create_dir_all(&staged_root)
+            .with_context(|| format!("Couldn't create path: \"{}\"", staged_root.display()))?;
+
+        // sn_api only uploads the files at the top level if not recursive.
+        let max_depth = if recursive { usize::MAX } else { 1 };
+        let mut walker = WalkDir::new(src)
+            .min_depth(1)
+            .max_depth(max_depth)
+            .follow_links(follow_links)
+            .into_iter();
+        while let Some(entry) = walker.next() {
+            let entry = entry.with_context(|| format!("Failed to read \"{}\"", location))?;
+            let rel_path = entry
+                .path()
+                .strip_prefix(src)
+                .context("Unexpectedly failed to get relative path")?;
+            let is_dir = entry.file_type().is_dir();
+
+            if filter.is_excluded(rel_path, is_dir) {
+                trace!("Skipping filtered path: {}", entry.path().display());
+                if is_dir {
+                    walker.skip_current_dir();
+                }
+                staged.skipped.push(entry.path().display().to_string());
+                continue;
+            }
+            stage_entry(
+                entry.path(),
+                &staged_root.join(rel_path),
+                is_dir,
+                follow_links,
+            )?;
+        }
+    } else {
+        let rel_path = src.strip_prefix(&filter_root).unwrap_or(src);
+        if filter.is_excluded(rel_path, false) {
+            staged.skipped.push(location.to_string());
+        } else {
+            fs::create_dir_all(&staged.dir)
+                .with_context(|| format!("Couldn't create path: \"{}\"", staged.dir.display()))?;
+            stage_entry(src, &staged_root, false, follow_links)?;
+        }
+    }
+
+    Ok(staged)
+}
+
+// Creates an empty dir, and any missing parents, in a temporary dir, so it can
+// be synced up with a FilesContainer to create the same dir on it. The paths
+// reported by sn_api are then translated to paths within the FilesContainer.
+pub fn stage_empty_dir(dir_path: &str) -> Result<StagedLocation> {
+    stage_dirs_and_symlinks(&[dir_path], &[])
+}
+
+// Creates empty dirs, and symlinks with the given target and whether it's a
+// dir, at their path within a FilesContainer in a temporary dir, the same way
+// stage_empty_dir does, so they can all be created with a single sync.
+pub fn stage_dirs_and_symlinks(
+    dir_paths: &[&str],
+    symlinks: &[(&str, &str, bool)],
+) -> Result<StagedLocation> {
+    let dir = staging_dir();
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Couldn't create path: \"{}\"", dir.display()))?;
+    let staged = StagedLocation {
+        location: format!("{}/", dir.display()),
+        dir,
+        original: "".to_string(),
+        skipped: vec![],
+    };
+
+    for dir_path in dir_paths {
+        let staged_dir = staged.dir.join(dir_path.trim_start_matches('/'));
+        fs::create_dir_all(&staged_dir)
+            .with_context(|| format!("Couldn't create path: \"{}\"", staged_dir.display()))?;
+    }
+    for (link_path, target, is_dir) in symlinks {
+        let staged_link = staged.dir.join(link_path.trim_start_matches('/'));
+        if let Some(parent) = staged_link.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Couldn't create path: \"{}\"", parent.display()))?;
+        }
+        create_symlink(Path::new(target), &staged_link, *is_dir)
+            .with_context(|| format!("Couldn't create symlink: \"{}\"", staged_link.display()))?;
+    }
+
+    Ok(staged)
+}
+
+// Returns a new random path in the temporary dir to stage files in.
+fn staging_dir() -> PathBuf {
+    staging_dir_in(&env::temp_dir())
+}
+
+fn staging_dir_in(parent: &Path) -> PathBuf {
+    let random: String = thread_rng().sample_iter(&Alphanumeric).take(15).collect();
+    parent.join(format!("{}{}", STAGING_DIR_PREFIX, random))
+}
+
+// Returns a new random path to stage the files of a location in, on the same
+// device as the location so they can be hard linked. The CLI's own staging
+// dir, or else the temporary dir, is used if it's on that device, otherwise
+// the dir next to the location is used as long as it's writable. It's never
+// within the location, so the staging dir isn't seen by a watcher of the
+// location, and any staging dir left behind is never uploaded.
+fn staging_dir_near(location: &Path) -> PathBuf {
+    let temp_dir = env::temp_dir();
+    let parent = match location.canonicalize() {
+        Ok(path) => match path.parent() {
+            Some(parent) => parent.to_path_buf(),
+            None => return staging_dir_in(&temp_dir),
+        },
+        Err(_) => return staging_dir_in(&temp_dir),
+    };
+    if let Some(cli_staging_dir) = cli_staging_dir() {
+        if is_same_device(&parent, &cli_staging_dir) {
+            return staging_dir_in(&cli_staging_dir);
+        }
+    }
+    if is_same_device(&parent, &temp_dir) {
+        return staging_dir_in(&temp_dir);
+    }
+
+    let dir = staging_dir_in(&parent);
+    match fs::create_dir(&dir) {
+        Ok(()) => dir,
+        Err(err) => {
+            debug!(
+                "Cannot stage files next to \"{}\", they'll be copied instead: {}",
+                location.display(),
+                err
+            );
+            staging_dir_in(&temp_dir)
+        }
+    }
+}
+
+// Returns the dir within the CLI's config dir to stage files in, creating
+// it if it doesn't exist, unless it can't be created.
+fn cli_staging_dir() -> Option<PathBuf> {
+    let dir = match get_cli_config_path() {
+        Ok(path) => path.join(STAGING_DIRNAME),
+        Err(err) => {
+            debug!("Cannot stage files in the CLI's config dir: {}", err);
+            return None;
+        }
+    };
+    match fs::create_dir_all(&dir) {
+        Ok(()) => Some(dir),
+        Err(err) => {
+            debug!("Couldn't create staging dir \"{}\": {}", dir.display(), err);
+            None
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_same_device(path: &Path, other: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(path), fs::metadata(other)) {
+        (Ok(meta), Ok(other_meta)) => meta.dev() == other_meta.dev(),
+        _ => false,
+    }
+}
+
+// devices can't be compared with std on other platforms, the temporary
+// dir is always used there.
+#[cfg(not(unix))]
+fn is_same_device(_path: &Path, _other: &Path) -> bool {
+    true
+}
+
+// Places a file, dir or symlink found in the location into the staging dir.
+// Symlinks are recreated as such, unless they are to be followed, in which
+// case their target is staged instead.
+fn stage_entry(src: &Path, dest: &Path, is_dir: bool, follow_links: bool) -> Result<()> {
+    let is_symlink = fs::symlink_metadata(src)
+        .with_context(|| format!("Failed to read metadata of \"{}\"", src.display()))?
+        .file_type()
+        .is_symlink();
+
+    let result = if is_dir {
+        fs::create_dir_all(dest)
+    } else if is_symlink && !follow_links {
+        stage_symlink(src, dest)
+    } else if is_symlink {
+        // a hard link to a symlink would be a symlink itself.
+        fs::canonicalize(src).and_then(|target| stage_file(&target, dest))
+    } else {
+        stage_file(src, dest)
+    };
+    result.with_context(|| format!("Failed to stage \"{}\" for upload", src.display()))
+}
+
+// Hard links a file into the staging dir, or copies it if it's on another
+// device, keeping its modification time which is recorded when uploaded.
+fn stage_file(src: &Path, dest: &Path) -> std::io::Result<()> {
+    if fs::hard_link(src, dest).is_ok() {
+        return Ok(());
+    }
+    trace!("Copying \"{}\" as it can't be hard linked", src.display());
+    fs::copy(src, dest)?;
+    let modified = FileTime::from_last_modification_time(&fs::metadata(src)?);
+    filetime::set_file_mtime(dest, modified)
+}
+
+// Recreates a symlink in the staging dir with the same target.
+fn stage_symlink(src: &Path, dest: &Path) -> std::io::Result<()> {
+    let target = fs::read_link(src)?;
+    create_symlink(&target, dest, src.is_dir())
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, link: &Path, _is_dir: bool) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn create_symlink(target: &Path, link: &Path, is_dir: bool) -> std::io::Result<()> {
+    if is_dir {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
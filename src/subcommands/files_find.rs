@@ -0,0 +1,225 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    files_filter::FilesFilter,
+    helpers::{get_from_arg_or_stdin, pluralize, serialise_output},
+    OutputFmt,
+};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use log::{debug, warn};
+use sn_api::{fetch::SafeData, Safe, SafeUrl, XorUrl};
+use std::{collections::BTreeMap, path::Path, process::Command};
+
+type FileDetails = BTreeMap<String, String>;
+
+// Placeholder replaced by the URL of each entry found in the `--exec` command.
+const EXEC_URL_PLACEHOLDER: &str = "{}";
+
+/// # Criteria an entry of a FilesContainer must meet to be found.
+///
+/// An entry is found if it meets all the criteria provided.
+#[derive(Debug)]
+pub struct FindOptions {
+    pub name: Option<String>,
+    pub media_type: Option<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub newer: Option<DateTime<Utc>>,
+    pub older: Option<DateTime<Utc>>,
+    pub exec: Vec<String>,
+}
+
+// processes the `safe files find` command.
+pub async fn process_find_command(
+    safe: &mut Safe,
+    target: Option<XorUrl>,
+    opts: FindOptions,
+    output_fmt: OutputFmt,
+) -> Result<()> {
+    let name_filter = match &opts.name {
+        Some(name) => Some(FilesFilter::from_globs(&[name.clone()], &[])?),
+        None => None,
+    };
+    let target_url = get_from_arg_or_stdin(target, Some("...awaiting target URl from STDIN"))?;
+
+    // The URL of each entry found is pinned to the version searched, so it
+    // keeps targeting the same file once the FilesContainer is updated. For
+    // an NRS URL that's the version of the NRS map, which links to a version
+    // of the FilesContainer.
+    let mut safeurl = SafeUrl::from_url(&target_url)?;
+    if safeurl.content_version().is_none() && safeurl.is_nrsurl() {
+        let (nrs_version, _nrs_map) = safe.nrs_map_container_get(&target_url).await?;
+        safeurl.set_content_version(Some(nrs_version));
+    }
+
+    debug!("Finding files in container {}", safeurl);
+    let files_map = match safe.fetch(&safeurl.to_string(), None).await? {
+        SafeData::FilesContainer {
+            version, files_map, ..
+        } => {
+            if safeurl.content_version().is_none() {
+                safeurl.set_content_version(Some(version));
+            }
+            files_map
+        }
+        _other_type => bail!("Make sure the URL targets a FilesContainer"),
+    };
+
+    // The FilesMap paths are relative to the target URL's path, thus the URL
+    // of each entry found is the target URL with the entry's path appended.
+    let base_path = safeurl.path_decoded()?.trim_end_matches('/').to_string();
+
+    let mut found = BTreeMap::<String, FileDetails>::new();
+    for (path, details) in files_map.iter() {
+        let rel_path = path.trim_matches('/');
+        if rel_path.is_empty() || !matches(rel_path, details, &opts, name_filter.as_ref()) {
+            continue;
+        }
+        safeurl.set_path(&format!("{}/{}", base_path, rel_path));
+        found.insert(safeurl.to_string(), details.clone());
+    }
+
+    if !opts.exec.is_empty() {
+        return exec_for_each(&opts.exec, found.keys());
+    }
+
+    if OutputFmt::Pretty == output_fmt {
+        // one URL per line, so the output can be piped into other commands.
+        for url in found.keys() {
+            println!("{}", url);
+        }
+    } else {
+        println!("{}", serialise_output(&(target_url, found), output_fmt));
+    }
+
+    Ok(())
+}
+
+/// Parses the date of the `--newer` and `--older` args, either an RFC 3339
+/// timestamp, e.g. 2020-01-28T20:26:05Z, or a date, e.g. 2020-01-28, which is
+/// taken as midnight UTC.
+pub fn parse_date(src: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(src) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+    match NaiveDate::parse_from_str(src, "%Y-%m-%d") {
+        Ok(date) => Ok(DateTime::from_utc(date.and_hms(0, 0, 0), Utc)),
+        Err(_) => Err(format!(
+            "'{}' is not a valid date. Use a date like 2020-01-28, or a timestamp like 2020-01-28T20:26:05Z",
+            src
+        )),
+    }
+}
+
+// Returns true if the entry found at `path` meets all the criteria.
+fn matches(
+    path: &str,
+    details: &FileDetails,
+    opts: &FindOptions,
+    name_filter: Option<&FilesFilter>,
+) -> bool {
+    let media_type = details.get("type").map_or("", String::as_str);
+    if let Some(filter) = name_filter {
+        let is_dir = media_type == "inode/directory";
+        if !filter.is_included(Path::new(path), is_dir) {
+            return false;
+        }
+    }
+
+    if let Some(wanted) = &opts.media_type {
+        // e.g. 'text/*' matches any text media type.
+        let is_match = match wanted.strip_suffix("/*") {
+            Some(top_level) => media_type.split('/').next() == Some(top_level),
+            None => media_type == wanted,
+        };
+        if !is_match {
+            return false;
+        }
+    }
+
+    if opts.min_size.is_some() || opts.max_size.is_some() {
+        let size = match details
+            .get("size")
+            .and_then(|size| size.parse::<u64>().ok())
+        {
+            Some(size) => size,
+            None => return false,
+        };
+        if opts.min_size.map_or(false, |min_size| size < min_size)
+            || opts.max_size.map_or(false, |max_size| size > max_size)
+        {
+            return false;
+        }
+    }
+
+    if opts.newer.is_some() || opts.older.is_some() {
+        let modified = match details
+            .get("modified")
+            .and_then(|modified| DateTime::parse_from_rfc3339(modified).ok())
+        {
+            Some(modified) => modified.with_timezone(&Utc),
+            None => return false,
+        };
+        if opts.newer.map_or(false, |newer| modified <= newer)
+            || opts.older.map_or(false, |older| modified >= older)
+        {
+            return false;
+        }
+    }
+
+    true
+}
+
+// Runs the `--exec` command once for each URL found, the same way `find -exec`
+// does, replacing any '{}' arg with the URL, or appending the URL otherwise.
+fn exec_for_each<'a>(exec: &[String], urls: impl Iterator<Item = &'a String>) -> Result<()> {
+    let program = &exec[0];
+    let has_placeholder = exec[1..].iter().any(|arg| arg == EXEC_URL_PLACEHOLDER);
+    let mut total: u64 = 0;
+    let mut failed = 0;
+    for url in urls {
+        let mut args: Vec<&str> = exec[1..]
+            .iter()
+            .map(|arg| {
+                if arg == EXEC_URL_PLACEHOLDER {
+                    url.as_str()
+                } else {
+                    arg.as_str()
+                }
+            })
+            .collect();
+        if !has_placeholder {
+            args.push(url);
+        }
+
+        debug!("Executing {} {:?}", program, args);
+        total += 1;
+        let status = Command::new(program)
+            .args(&args)
+            .status()
+            .with_context(|| format!("Failed to execute '{}'", program))?;
+        if !status.success() {
+            warn!("'{}' failed for {}: {}", program, url, status);
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        bail!(
+            "'{}' failed for {} of the {} {} found",
+            program,
+            failed,
+            total,
+            pluralize("entry", "entries", total)
+        );
+    }
+    Ok(())
+}
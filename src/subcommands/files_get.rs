@@ -8,13 +8,20 @@
 // Software.
 
 use super::{
-    helpers::{div_or, pluralize, prompt_user},
+    files_filter::{FilesFilter, SKIPPED_MARKER},
+    files_symlinks::{apply_symlink_policies, ExternalSymlinkPolicy, SymlinkPolicy},
+    helpers::{div_or, pluralize, prompt_user, serialise_output},
     OutputFmt,
 };
+use crate::operations::cache::{blob_key, BlobCache};
 use anyhow::{anyhow, bail, Context, Result};
+use chrono::DateTime;
 use console::Term;
+use filetime::FileTime;
+use futures::stream::{self, StreamExt};
 use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle, TickTimeLimit};
 use log::{debug, info, trace, warn};
+use serde::{Deserialize, Serialize};
 use sn_api::{
     fetch::Range,
     fetch::SafeData,
@@ -22,12 +29,31 @@ use sn_api::{
     Result as ApiResult, Safe, SafeDataType, SafeUrl, XorUrl,
 };
 use std::{
+    collections::{BTreeMap, BTreeSet},
     fs,
-    io::{BufWriter, Write},
-    path::Path,
-    time::Duration,
+    io::{BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
+// Size of the byte ranges requested from the network when downloading a file.
+// This matches the max chunk size used by self-encryption, so a range request
+// does not need to fetch more chunks than necessary.
+pub const DOWNLOAD_RANGE_SIZE: u64 = 1024 * 1024;
+
+// Name of the transfer journal written to the destination during a transfer.
+pub const JOURNAL_FILE_NAME: &str = ".safe-get-state";
+
+// Min time between two writes of the transfer journal while downloading.
+// The ranges completed since the last write are downloaded again if the
+// transfer is interrupted and resumed.
+const JOURNAL_SAVE_INTERVAL: Duration = Duration::from_secs(2);
+
+// FileItem attributes of the original file, as recorded by put/sync,
+// which are restored when using --preserve.
+const ATTR_MODE_BITS: &str = "mode_bits";
+const ATTR_ORIGINAL_MODIFIED: &str = "o_modified";
+
 /// # Retrieval/write status for current file and overall transfer.
 #[derive(Debug, Clone)]
 pub struct FilesGetStatus<'a, 'b> {
@@ -104,124 +130,370 @@ impl Default for ProgressIndicator {
     }
 }
 
+/// # Options for the `safe files get` command.
+#[derive(Debug)]
+pub struct FilesGetOptions {
+    /// How to handle pre-existing files
+    pub exists: FileExistsAction,
+    /// What type of progress indicator to display
+    pub progress: ProgressIndicator,
+    /// Preserve file attributes recorded in the FilesMap
+    pub preserve: bool,
+    /// Max number of ranges/files to be downloaded concurrently
+    pub jobs: usize,
+    /// Resume an interrupted transfer using the journal found at destination
+    pub resume: bool,
+    /// Glob patterns of the files to be retrieved, all if empty
+    pub include: Vec<String>,
+    /// Glob patterns of the files not to be retrieved
+    pub exclude: Vec<String>,
+    /// Read and store the files' content in the local cache
+    pub use_cache: bool,
+    /// Verify the content of the files retrieved against their XOR-URLs
+    pub verify: bool,
+    /// How to retrieve symlinks
+    pub symlinks: SymlinkPolicy,
+    /// What to do with absolute symlinks, and those escaping the retrieved tree
+    pub external_symlinks: ExternalSymlinkPolicy,
+}
+
+/// # Report of a `safe files get`, printed with a non-pretty output format.
+#[derive(Debug, Serialize)]
+struct FilesGetReport<'a> {
+    url: String,
+    dest: String,
+    processed_files: &'a ProcessedFiles,
+    // pre-existing files which were overwritten
+    overwrites: u64,
+    // pre-existing files which were preserved
+    preserves: u64,
+}
+
+// Progress of a transfer, printed to stderr as a JSON line
+// with --progress=text and a non-pretty output format.
+#[derive(Debug, Serialize)]
+struct FilesGetProgressEvent {
+    path: String,
+    local_path: String,
+    current_file: u64,
+    total_files: u64,
+    file_bytes_written: u64,
+    file_size: u64,
+    transfer_bytes_written: u64,
+    total_transfer_bytes: u64,
+}
+
+// Download progress of a single file, as recorded in the transfer journal.
+#[derive(Debug, Serialize, Deserialize)]
+struct JournalFile {
+    link: String,
+    size: u64,
+    complete: bool,
+    // offsets of the ranges already written to disk, cleared once complete.
+    ranges: BTreeSet<u64>,
+}
+
+impl JournalFile {
+    // Number of bytes already written to disk for this file.
+    fn bytes_written(&self) -> u64 {
+        if self.complete {
+            return self.size;
+        }
+        self.ranges
+            .iter()
+            .map(|offset| DOWNLOAD_RANGE_SIZE.min(self.size.saturating_sub(*offset)))
+            .sum()
+    }
+}
+
+// Journal of a `safe files get` transfer. It is kept up to date in the
+// destination while files are being downloaded, and removed once all of
+// them were retrieved, so an interrupted transfer can be resumed.
+#[derive(Debug, Serialize, Deserialize)]
+struct GetJournal {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(skip)]
+    last_saved: Option<Instant>,
+    source: String,
+    version: u64,
+    files: BTreeMap<String, JournalFile>,
+}
+
+impl GetJournal {
+    fn new(path: PathBuf, source: &str, version: u64) -> Self {
+        Self {
+            path,
+            last_saved: None,
+            source: source.to_string(),
+            version,
+            files: BTreeMap::new(),
+        }
+    }
+
+    // Loads the journal at the given path, if there is one.
+    fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let serialised = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read transfer journal: \"{}\"", path.display()))?;
+        let mut journal: Self = serde_json::from_str(&serialised)
+            .with_context(|| format!("Invalid transfer journal: \"{}\"", path.display()))?;
+        journal.path = path.to_path_buf();
+        Ok(Some(journal))
+    }
+
+    // Writes the journal to disk. A temporary file is renamed over the
+    // journal so it's never left half written if we are interrupted.
+    fn save(&self) -> Result<()> {
+        let serialised = serde_json::to_string(&self)?;
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, serialised).with_context(|| {
+            format!(
+                "Couldn't write transfer journal: \"{}\"",
+                tmp_path.display()
+            )
+        })?;
+        fs::rename(&tmp_path, &self.path).with_context(|| {
+            format!(
+                "Couldn't write transfer journal: \"{}\"",
+                self.path.display()
+            )
+        })
+    }
+
+    fn remove(&self) -> Result<()> {
+        if self.path.exists() {
+            fs::remove_file(&self.path).with_context(|| {
+                format!(
+                    "Couldn't remove transfer journal: \"{}\"",
+                    self.path.display()
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    // Returns the journal entry of a file which can be resumed, ie. it
+    // still has the same link and size, and was already created on disk.
+    fn resumable(&self, path: &str, link: &str, size: u64, abspath: &Path) -> Option<&JournalFile> {
+        self.files
+            .get(path)
+            .filter(|file| file.link == link && file.size == size && abspath.is_file())
+    }
+
+    // Saves the journal, only warning the user upon failure since the
+    // transfer itself can still complete without it.
+    fn save_or_warn(&mut self) {
+        self.last_saved = Some(Instant::now());
+        if let Err(err) = self.save() {
+            warn!("{:#}", err);
+            if isatty::stderr_isatty() {
+                eprintln!("Warning: {:#}", err);
+            }
+        }
+    }
+
+    // Saves the journal unless it was saved recently, so it's not rewritten
+    // after each of the many ranges completed when retrieving large containers.
+    fn save_if_due(&mut self) {
+        let is_due = self.last_saved.map_or(true, |last_saved| {
+            last_saved.elapsed() >= JOURNAL_SAVE_INTERVAL
+        });
+        if is_due {
+            self.save_or_warn();
+        }
+    }
+}
+
 // processes the `safe files get` command.  called by files.rs
 //
 // dest is a local path.  defaults to "."
 //   Path will be created if not existing, else error.
 //
-// TODO: _preserve file attributes is not yet implemented, we need them
-//   stored in metadata first.
+// opts.jobs is the max number of file ranges being downloaded
+//   concurrently, which may belong to one or several files.
 //
-// TBD: how should we handle OutputFmt?  Presently, we are displaying
-// progress bars, and also [possibly] prompting user about overwrites.
-// We have a list of processed files that we could present as json
-// or in table form.  But if stdout format is json and we prompt user,
-// then any process parsing output as json will break.  So possibly
-// --exists=ask and --json should conflict and not be allowed.
+// opts.resume continues an interrupted transfer from the journal left in
+//   dest, skipping the files and ranges which were already written.
 //
-// This command is really similar to cp or scp, and people are fine
-// using those without a report.  So it doesn't seem especially urgent.
+// opts.preserve restores the mode and modification time of the original
+//   files, when they have been recorded in the FilesMap.
+//
+// With a non-pretty output_fmt a FilesGetReport is printed to stdout, thus
+//   the progress is reported on stderr, with opts.progress=text printing one
+//   JSON object per line. Prompting the user about overwrites would break the
+//   output, so --exists=ask is not allowed with such a format.
 pub async fn process_get_command(
     safe: &mut Safe,
     source: XorUrl,
     dest: Option<String>,
-    exists: FileExistsAction,
-    progress: ProgressIndicator,
-    _preserve: bool,
-    _output_fmt: OutputFmt,
+    opts: FilesGetOptions,
+    output_fmt: OutputFmt,
 ) -> Result<()> {
+    if opts.jobs == 0 {
+        bail!("The number of jobs must be greater than zero");
+    }
+    check_exists_action(&opts.exists, output_fmt)?;
+
     let str_path = dest.unwrap_or_else(|| ".".to_string());
     let path = Path::new(&str_path);
 
-    let (mp, bars) = create_progress_bars();
+    let (mp, bars) = create_progress_bars(output_fmt);
 
     let mut overwrites: u64 = 0;
     let mut preserves: u64 = 0;
 
-    let (_version, processed_files) =
-        files_container_get_files(safe, &source, &str_path, |status| {
-            let mut overwrite = true;
-            let mut mystatus = status.clone();
-
-            if status.file_bytes_written == 0 {
-                // It is an error/warning if the dest path attempts to use
-                // an existing file as a directory. But other files should
-                // still be written.  eg:
-                // $ mkdir -p /tmp/a/b/c && touch /tmp/a/file.txt
-                // $ mkdir /tmp/target && touch /tmp/target/b   (b is a file)
-                // $ cp -r /tmp/a/* /tmp/target
-                //    cp: cannot overwrite non-directory '/tmp/target/b' with directory '/tmp/a/b'
-                // $ ls -l /tmp/target/
-                //      total 0
-                //      -rw-rw-r-- 1 user user 0 Mar 31 14:38 b         (b still a file)
-                //      -rw-rw-r-- 1 user user 0 Mar 31 14:38 file.txt  (other file written)
-                //
-                // TBD: Should FileExistsAction apply to this case?
-                //      unix cp does not provide any flag/option/prompt to permit this
-                //      and it always emits a warning.  So I am satisfied with this
-                //      working the same way, at least for now.
-                let dirpath = if status.file_type == "inode/directory" {
-                    Some(status.path_local)
-                } else {
-                    status.path_local.parent()
-                };
-                if let Some(parent) = dirpath {
-                    if let Some(filepath) = path_contains_file(&parent) {
-                        let msg = format!(
-                            "cannot overwrite non-directory '{}' with directory in '{}'",
-                            filepath.display(),
-                            status.path_local.display()
-                        );
-
-                        warn!("Skipping file \"{}\". {}", status.path_local.display(), msg);
-                        if isatty::stderr_isatty() {
-                            eprintln!("Warning: {}", msg);
-                        }
-                        overwrite = false;
+    let (version, processed_files) = files_container_get_files(
+        safe,
+        &source,
+        &str_path,
+        &opts,
+        get_status_callback(
+            &opts,
+            output_fmt,
+            &mp,
+            &bars,
+            &mut overwrites,
+            &mut preserves,
+        ),
+    )
+    .await?;
+
+    if processed_files.is_empty() && preserves == 0 {
+        bail!("Path '{}' not found", path.display());
+    }
+
+    if OutputFmt::Pretty == output_fmt {
+        print_results(&processed_files, path, overwrites, preserves);
+    } else {
+        let url = match SafeUrl::from_url(&source) {
+            Ok(mut safeurl) => {
+                safeurl.set_content_version(Some(version));
+                safeurl.to_string()
+            }
+            Err(_) => source.clone(),
+        };
+        let report = FilesGetReport {
+            url,
+            dest: str_path.clone(),
+            processed_files: &processed_files,
+            overwrites,
+            preserves,
+        };
+        println!("{}", serialise_output(&report, output_fmt));
+    }
+
+    let failed = processed_files
+        .values()
+        .filter(|(change, _)| change == "E")
+        .count() as u64;
+    if opts.verify && failed > 0 {
+        bail!(
+            "{} {} could not be retrieved or failed verification",
+            failed,
+            pluralize("file", "files", failed)
+        );
+    }
+
+    Ok(())
+}
+
+// Prompting the user about overwrites would break a non-pretty output.
+pub fn check_exists_action(exists: &FileExistsAction, output_fmt: OutputFmt) -> Result<()> {
+    if OutputFmt::Pretty != output_fmt && matches!(exists, FileExistsAction::Ask) {
+        bail!("--exists=ask cannot be used along with a non-pretty output format, please use --exists=preserve or --exists=overwrite instead");
+    }
+    Ok(())
+}
+
+// Returns the callback to be invoked with the status of a transfer, which
+// handles the pre-existing files as per `opts.exists` and displays progress.
+// It counts the pre-existing files which were overwritten and preserved.
+fn get_status_callback<'a>(
+    opts: &'a FilesGetOptions,
+    output_fmt: OutputFmt,
+    mp: &'a MultiProgress,
+    bars: &'a [ProgressBar],
+    overwrites: &'a mut u64,
+    preserves: &'a mut u64,
+) -> impl FnMut(&FilesGetStatus) -> bool + 'a {
+    move |status| {
+        let mut overwrite = true;
+        let mut mystatus = status.clone();
+
+        if status.file_bytes_written == 0 {
+            // It is an error/warning if the dest path attempts to use
+            // an existing file as a directory. But other files should
+            // still be written.  eg:
+            // $ mkdir -p /tmp/a/b/c && touch /tmp/a/file.txt
+            // $ mkdir /tmp/target && touch /tmp/target/b   (b is a file)
+            // $ cp -r /tmp/a/* /tmp/target
+            //    cp: cannot overwrite non-directory '/tmp/target/b' with directory '/tmp/a/b'
+            // $ ls -l /tmp/target/
+            //      total 0
+            //      -rw-rw-r-- 1 user user 0 Mar 31 14:38 b         (b still a file)
+            //      -rw-rw-r-- 1 user user 0 Mar 31 14:38 file.txt  (other file written)
+            //
+            // TBD: Should FileExistsAction apply to this case?
+            //      unix cp does not provide any flag/option/prompt to permit this
+            //      and it always emits a warning.  So I am satisfied with this
+            //      working the same way, at least for now.
+            let dirpath = if status.file_type == "inode/directory" {
+                Some(status.path_local)
+            } else {
+                status.path_local.parent()
+            };
+            if let Some(parent) = dirpath {
+                if let Some(filepath) = path_contains_file(&parent) {
+                    let msg = format!(
+                        "cannot overwrite non-directory '{}' with directory in '{}'",
+                        filepath.display(),
+                        status.path_local.display()
+                    );
+
+                    warn!("Skipping file \"{}\". {}", status.path_local.display(), msg);
+                    if isatty::stderr_isatty() {
+                        eprintln!("Warning: {}", msg);
                     }
+                    overwrite = false;
                 }
-                if status.path_local.exists() && overwrite {
-                    overwrite = match exists {
-                        FileExistsAction::Overwrite => true,
-                        FileExistsAction::Preserve => false,
-                        FileExistsAction::Ask => {
-                            let prompt = format!(
-                                "overwrite '{}'? ",
-                                status.path_local.display().to_string()
-                            );
-                            prompt_yes_no(&prompt, "Y")
-                        }
-                    };
-                    if overwrite {
-                        overwrites += 1;
-                    } else {
-                        preserves += 1;
-                        mystatus.total_transfer_bytes -= mystatus.file_size;
+            }
+            if status.path_local.exists() && overwrite {
+                overwrite = match opts.exists {
+                    FileExistsAction::Overwrite => true,
+                    FileExistsAction::Preserve => false,
+                    FileExistsAction::Ask => {
+                        let prompt =
+                            format!("overwrite '{}'? ", status.path_local.display().to_string());
+                        prompt_yes_no(&prompt, "Y")
                     }
+                };
+                if overwrite {
+                    *overwrites += 1;
+                } else {
+                    *preserves += 1;
+                    mystatus.total_transfer_bytes -= mystatus.file_size;
                 }
             }
-            if overwrite {
-                match progress {
-                    ProgressIndicator::Bars => {
-                        update_progress_bars(&mp, &bars, &mystatus);
-                    }
-                    ProgressIndicator::Text => {
-                        print_status(status);
-                    }
-                    ProgressIndicator::None => {}
+        }
+        if overwrite {
+            match opts.progress {
+                ProgressIndicator::Bars => {
+                    update_progress_bars(mp, bars, &mystatus);
                 }
+                ProgressIndicator::Text if OutputFmt::Pretty == output_fmt => {
+                    print_status(status);
+                }
+                ProgressIndicator::Text => {
+                    print_status_json(status);
+                }
+                ProgressIndicator::None => {}
             }
-            overwrite
-        })
-        .await?;
-
-    if processed_files.is_empty() && preserves == 0 {
-        bail!("Path '{}' not found", path.display());
+        }
+        overwrite
     }
-
-    print_results(&processed_files, path, overwrites, preserves);
-
-    Ok(())
 }
 
 // detects if a path contains a file at any level.
@@ -246,22 +518,52 @@ fn path_contains_file(path: &Path) -> Option<&Path> {
 
 // prints results/summary of GET transfer
 fn print_results(processed_files: &ProcessedFiles, path: &Path, overwrites: u64, preserves: u64) {
+    let skipped = processed_files
+        .values()
+        .filter(|(change, _)| change == SKIPPED_MARKER)
+        .count() as u64;
+    // symlinks skipped are reported along with the reason why.
+    let skipped_symlinks: Vec<(&String, &String)> = processed_files
+        .iter()
+        .filter(|(_, (change, msg))| change == SKIPPED_MARKER && !msg.is_empty())
+        .map(|(path, (_, msg))| (path, msg))
+        .collect();
+    let filtered = skipped - skipped_symlinks.len() as u64;
+    let failed: Vec<(&String, &String)> = processed_files
+        .iter()
+        .filter(|(_, (change, _))| change == "E")
+        .map(|(path, (_, msg))| (path, msg))
+        .collect();
+    // files which couldn't be retrieved or failed verification are not counted
+    let retrieved = processed_files.len() as u64 - skipped - failed.len() as u64;
+
     if overwrites > 0 || preserves > 0 {
         println!(
             "Done. Retrieved {} {} to {}.\n  pre-existing: {}   (overwritten: {}  preserved: {})",
-            processed_files.len(),
-            pluralize("file", "files", processed_files.len() as u64),
+            retrieved,
+            pluralize("file", "files", retrieved),
             path.display(),
             overwrites + preserves,
             overwrites,
             preserves
         );
     } else {
-        println!(
-            "Done. Retrieved {} files to {}",
-            processed_files.len(),
-            path.display()
-        );
+        println!("Done. Retrieved {} files to {}", retrieved, path.display());
+    }
+    if filtered > 0 {
+        println!("  filtered out: {}", filtered);
+    }
+    if !skipped_symlinks.is_empty() {
+        println!("  skipped symlinks: {}", skipped_symlinks.len());
+        for (path, msg) in skipped_symlinks {
+            println!("    {} {}", path, msg);
+        }
+    }
+    if !failed.is_empty() {
+        println!("  failed: {}", failed.len());
+        for (path, msg) in failed {
+            println!("    {} {}", path, msg);
+        }
     }
 }
 
@@ -291,9 +593,30 @@ fn print_status(status: &FilesGetStatus) {
     );
 }
 
+// Prints the status of a transfer to stderr as a single line of JSON, so
+// it can be parsed while the report is printed to stdout.
+fn print_status_json(status: &FilesGetStatus) {
+    let event = FilesGetProgressEvent {
+        path: status.path_remote.display().to_string(),
+        local_path: status.path_local.display().to_string(),
+        current_file: status.current_file,
+        total_files: status.total_files,
+        file_bytes_written: status.file_bytes_written,
+        file_size: status.file_size,
+        transfer_bytes_written: status.transfer_bytes_written,
+        total_transfer_bytes: status.total_transfer_bytes,
+    };
+    match serde_json::to_string(&event) {
+        Ok(line) => eprintln!("{}", line),
+        Err(err) => warn!("Failed to serialise progress event: {}", err),
+    }
+}
+
 // Update the progress bars.
 // Called once before each file starts downloading,
-// and again after each chunk until it finishes.
+// and again after each range until it finishes.
+// Ranges of several files may be downloaded concurrently, thus
+// consecutive calls are not necessarily reporting the same file.
 // Current state can be determined by
 //    checking transfer_bvtes_written and file_bytes_written
 fn update_progress_bars(m: &MultiProgress, bars: &[ProgressBar], status: &FilesGetStatus) {
@@ -307,20 +630,23 @@ fn update_progress_bars(m: &MultiProgress, bars: &[ProgressBar], status: &FilesG
         }
     }
 
-    // set bar 1 length and reset elapsed time when starting a new file.
+    // reset elapsed time when starting a new file.
     if status.file_bytes_written == 0 {
-        bars[0].set_length(status.file_size);
         bars[0].reset();
-
-        // We re-set this for each file because it can change if a pre-existing file is preserved.
-        bars[2].set_length(status.total_transfer_bytes);
-
         if !b_onefile {
-            bars[1].set_length(status.file_size);
             bars[1].reset();
         }
     }
 
+    // We re-set these lengths each time since the file being reported can change
+    // from one call to the next, and the transfer length can change if a
+    // pre-existing file is preserved or a file fails to download.
+    bars[0].set_length(status.file_size);
+    bars[2].set_length(status.total_transfer_bytes);
+    if !b_onefile {
+        bars[1].set_length(status.file_size);
+    }
+
     let msg = format!(
         "File [{} of {}]: {}",
         status.current_file,
@@ -334,12 +660,6 @@ fn update_progress_bars(m: &MultiProgress, bars: &[ProgressBar], status: &FilesG
     bars[1].set_position(status.file_bytes_written);
     bars[2].set_position(status.transfer_bytes_written);
 
-    // finish bars 0 and 1 when current file has been downloaded.
-    if status.file_size == status.file_bytes_written {
-        bars[0].finish_at_current_pos();
-        bars[1].finish_at_current_pos();
-    }
-
     // Hide bars 0 and 1 when transfer is done.
     if status.total_transfer_bytes == status.transfer_bytes_written {
         bars[0].finish_and_clear();
@@ -354,8 +674,14 @@ fn update_progress_bars(m: &MultiProgress, bars: &[ProgressBar], status: &FilesG
 // Creates and inits the progress bars.
 // We use 3. The first just reports the path.
 // The 2nd and 3rd represent File progress and Transfer progress respectively.
-fn create_progress_bars() -> (MultiProgress, Vec<ProgressBar>) {
-    let m = MultiProgress::with_draw_target(ProgressDrawTarget::stdout_nohz());
+// They are drawn on stderr when a non-pretty output is printed to stdout.
+fn create_progress_bars(output_fmt: OutputFmt) -> (MultiProgress, Vec<ProgressBar>) {
+    let draw_target = if OutputFmt::Pretty == output_fmt {
+        ProgressDrawTarget::stdout_nohz()
+    } else {
+        ProgressDrawTarget::stderr_nohz()
+    };
+    let m = MultiProgress::with_draw_target(draw_target);
     let sty_file = ProgressStyle::default_bar()
         .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})  File")
         .progress_chars("##-");
@@ -406,26 +732,36 @@ fn prompt_yes_no(prompt_msg: &str, default: &str) -> bool {
     }
 }
 
-/// # Downloads all files within a FilesContainer and writes them to disk, preserving paths.
+/// # Fetches the FilesMap of the FilesContainer, or of the single file, a URL targets.
 ///
-/// TODO: In the future, this will have options for preserving symlinks and
-/// file attributes.
-async fn files_container_get_files(
-    safe: &mut Safe,
+/// A single file has no path in the FilesMap returned, and no version.
+pub async fn fetch_files_map(
+    safe: &Safe,
     url: &str,
-    dirpath: &str,
-    callback: impl FnMut(&FilesGetStatus) -> bool,
-) -> Result<(u64, ProcessedFiles)> {
-    debug!("Getting files in container {:?}", url);
-    let (version, files_map) = match safe.fetch(url, None).await? {
+    cache: Option<&BlobCache>,
+) -> Result<(u64, FilesMap)> {
+    match safe.fetch(url, None).await? {
         SafeData::FilesContainer {
             version, files_map, ..
-        } => (version, files_map),
-        SafeData::PublicBlob { metadata, .. } => {
+        } => Ok((version, files_map)),
+        SafeData::PublicBlob {
+            xorurl,
+            data,
+            metadata,
+            ..
+        } => {
+            // The content was already fetched, so it's not fetched again
+            // when its ranges are read from the cache.
+            if let Some(cache) = cache {
+                if let Err(err) = cache.insert(&xorurl, &data) {
+                    warn!("Failed to store {} in local cache: {}", xorurl, err);
+                }
+            }
+
             if let Some(file_item) = metadata {
                 let mut files_map = FilesMap::new();
                 files_map.insert("".to_string(), file_item);
-                (0, files_map)
+                Ok((0, files_map))
             } else {
                 // TODO: support it even if no stats are shown of the file being downloaded
                 bail!(
@@ -434,11 +770,36 @@ async fn files_container_get_files(
             }
         }
         _other_type => bail!("Make sure the URL targets a FilesContainer"),
+    }
+}
+
+/// # Downloads all files within a FilesContainer and writes them to disk, preserving paths.
+///
+/// Symlinks are recreated, followed or skipped as per `opts.symlinks`, and
+/// those which are absolute or escape the retrieved tree as per
+/// `opts.external_symlinks`. The symlinks skipped are reported as such.
+async fn files_container_get_files(
+    safe: &mut Safe,
+    url: &str,
+    dirpath: &str,
+    opts: &FilesGetOptions,
+    callback: impl FnMut(&FilesGetStatus) -> bool,
+) -> Result<(u64, ProcessedFiles)> {
+    debug!("Getting files in container {:?}", url);
+    let cache = if opts.use_cache {
+        BlobCache::open_or_warn()
+    } else {
+        None
     };
 
+    let (version, files_map) = fetch_files_map(safe, url, cache.as_ref()).await?;
+
     // Todo: This test will need to be modified once we support empty directories.
     let is_single_file = files_map.len() == 1;
 
+    let (files_map, skipped_symlinks) =
+        apply_symlink_policies(files_map, opts.symlinks, opts.external_symlinks)?;
+
     let safeurl = SafeUrl::from_url(&url)?;
     let urlpath = safeurl.path_decoded()?;
 
@@ -451,10 +812,230 @@ async fn files_container_get_files(
     // surprising users.
     ensure_parent_dir_exists(&root)?;
 
-    let processed_files = files_map_get_files(safe, &files_map, &root, callback).await?;
+    // The journal and .safeignore file are in the destination dir, or
+    // next to the destination file when retrieving a single file.
+    let local_dir = if is_single_file {
+        match Path::new(&root).parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    } else {
+        PathBuf::from(&root)
+    };
+    let journal_path = local_dir.join(JOURNAL_FILE_NAME);
+
+    // Files filtered out are not retrieved, but reported as skipped.
+    let filter = FilesFilter::new(&local_dir, &opts.include, &opts.exclude)?;
+    let (files_map, skipped): (FilesMap, FilesMap) =
+        files_map.into_iter().partition(|(path, details)| {
+            // a single file targeted by the URL has no path in the FilesMap.
+            let rel_path = match path.trim_matches('/') {
+                "" => Path::new(&urlpath)
+                    .file_name()
+                    .map_or_else(PathBuf::new, PathBuf::from),
+                rel_path => PathBuf::from(rel_path),
+            };
+            let is_dir = details
+                .get("type")
+                .map_or(false, |t| t == "inode/directory");
+            !filter.is_excluded(&rel_path, is_dir)
+        });
+
+    let previous_journal = if opts.resume {
+        GetJournal::load(&journal_path)?
+    } else {
+        None
+    };
+
+    let journal = match previous_journal {
+        Some(journal) => {
+            if journal.source != url {
+                bail!(
+                    "The transfer journal at \"{}\" belongs to a transfer from a different source: {}",
+                    journal_path.display(),
+                    journal.source
+                );
+            }
+            if journal.version != version {
+                bail!(
+                    "The FilesContainer has changed since the interrupted transfer (version {} was being retrieved, current version is {}). Run the command without --resume to start over",
+                    journal.version,
+                    version
+                );
+            }
+            debug!("Resuming transfer from journal {}", journal_path.display());
+            journal
+        }
+        None => {
+            if opts.resume {
+                info!(
+                    "No transfer journal found at \"{}\", starting from scratch",
+                    journal_path.display()
+                );
+            }
+            GetJournal::new(journal_path, url, version)
+        }
+    };
+
+    let mut processed_files = files_map_get_files(
+        safe,
+        cache.as_ref(),
+        &files_map,
+        &root,
+        opts,
+        journal,
+        callback,
+    )
+    .await?;
+    if opts.verify {
+        verify_files(safe, cache.as_ref(), &root, &mut processed_files).await;
+    }
+    for (path, _details) in skipped {
+        processed_files.insert(path, (SKIPPED_MARKER.to_string(), "".to_string()));
+    }
+    processed_files.extend(skipped_symlinks);
     Ok((version, processed_files))
 }
 
+/// # Retrieves the given entries of a FilesContainer into a local dir.
+///
+/// Used by `safe files sync --pull` to retrieve the entries which changed
+/// since the local dir was last synced down. Pre-existing files are handled
+/// as per `opts.exists`, and those to be overwritten are removed first, so
+/// the target of a pre-existing symlink is never written to. Returns the
+/// files processed, and how many pre-existing ones were overwritten and preserved.
+pub async fn pull_files(
+    safe: &Safe,
+    url: &str,
+    version: u64,
+    files_map: &FilesMap,
+    dirpath: &str,
+    opts: &FilesGetOptions,
+    output_fmt: OutputFmt,
+) -> Result<(ProcessedFiles, u64, u64)> {
+    let cache = if opts.use_cache {
+        BlobCache::open_or_warn()
+    } else {
+        None
+    };
+    let journal = GetJournal::new(Path::new(dirpath).join(JOURNAL_FILE_NAME), url, version);
+
+    let (mp, bars) = create_progress_bars(output_fmt);
+    let mut overwrites: u64 = 0;
+    let mut preserves: u64 = 0;
+    let mut status_callback = get_status_callback(
+        opts,
+        output_fmt,
+        &mp,
+        &bars,
+        &mut overwrites,
+        &mut preserves,
+    );
+
+    let processed_files = files_map_get_files(
+        safe,
+        cache.as_ref(),
+        files_map,
+        dirpath,
+        opts,
+        journal,
+        |status| {
+            let write = status_callback(status);
+            if write && status.file_bytes_written == 0 {
+                remove_replaced_entry(status.path_local);
+            }
+            write
+        },
+    )
+    .await?;
+    drop(status_callback);
+
+    Ok((processed_files, overwrites, preserves))
+}
+
+// Removes a pre-existing file or symlink which is to be replaced. Dirs are
+// kept, since their content is pulled, or removed, entry by entry.
+fn remove_replaced_entry(path: &Path) {
+    let is_dir = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata.is_dir(),
+        Err(_) => return,
+    };
+    if !is_dir {
+        if let Err(err) = fs::remove_file(path) {
+            warn!("Couldn't remove \"{}\": {}", path.display(), err);
+        }
+    }
+}
+
+// Recalculates the XOR-URL of each file retrieved from its local content,
+// with a dry-run the same way `safe xorurl` does, and compares its XorName
+// with the one of the link it was retrieved from. As the XOR-URLs may differ
+// in the media type guessed for the file, only their XorNames are compared.
+// Files which fail verification are reported as errors. The files retrieved
+// are only stored in the cache once verified, and any content cached for
+// those which fail verification is evicted, so it's fetched again next time.
+async fn verify_files(
+    safe: &mut Safe,
+    cache: Option<&BlobCache>,
+    dirpath: &str,
+    processed_files: &mut ProcessedFiles,
+) {
+    let dpath = Path::new(dirpath);
+    for (path, (change, link)) in processed_files.iter_mut() {
+        // Only files with content are reported as retrieved, not dirs or symlinks
+        if change != "+" {
+            continue;
+        }
+
+        let abspath = local_path(dpath, path);
+        let location = abspath.display().to_string();
+        let local_link = match safe
+            .files_container_create(Some(&location), None, false, false, true)
+            .await
+        {
+            Ok((_, local_files, _)) => local_files.into_iter().next().map(|(_, (_, l))| l),
+            Err(err) => {
+                warn!("Failed to verify \"{}\": {}", location, err);
+                None
+            }
+        };
+
+        let xorname = |url: &str| SafeUrl::from_url(url).map(|u| u.xorname()).ok();
+        let verified = match &local_link {
+            Some(local_link) => {
+                xorname(local_link).is_some() && xorname(local_link) == xorname(link)
+            }
+            None => false,
+        };
+        if verified {
+            trace!("Verified \"{}\" matches {}", location, link);
+            if let Some(cache) = cache {
+                if let Err(err) = cache.insert_file(link, &abspath) {
+                    warn!("Failed to store {} in local cache: {}", link, err);
+                }
+            }
+        } else {
+            warn!("Content of \"{}\" doesn't match {}", location, link);
+            if let Some(cache) = cache {
+                if let Err(err) = blob_key(link).and_then(|key| cache.remove(&key)) {
+                    warn!("Failed to evict {} from local cache: {}", link, err);
+                }
+            }
+            *link = format!("<verification failed, content doesn't match {}>", link);
+            *change = "E".to_string();
+        }
+    }
+}
+
+// Returns the local path a FilesMap entry is written to.
+fn local_path(dpath: &Path, path: &str) -> PathBuf {
+    if !path.is_empty() {
+        dpath.join(path.trim_matches('/'))
+    } else {
+        dpath.to_path_buf()
+    }
+}
+
 // Determines the root (translated) path to download files to.
 // The root path is determined as per the follow matrix:
 /*
@@ -534,13 +1115,36 @@ fn ensure_parent_dir_exists(path: &str) -> Result<()> {
     }
 }
 
+// A file from a FilesMap which content is to be downloaded from the network.
+struct FileDownload<'a> {
+    path: &'a str,
+    abspath: PathBuf,
+    xorurl: &'a str,
+    size: u64,
+    file_type: String,
+    current_file: u64,
+    details: &'a BTreeMap<String, String>,
+    // offsets of ranges already written by an interrupted transfer.
+    done_ranges: BTreeSet<u64>,
+}
+
 /// # Downloads files within a FilesMap and writes them to disk, preserving paths.
 ///
-/// TODO: In the future, this will have options for preserving file attributes.
+/// Files are split into ranges which are downloaded concurrently, with at most
+/// `jobs` ranges (of one or several files) being pulled from the network at a time.
+///
+/// The progress is recorded in the given transfer journal, and any files or ranges
+/// it reports as already written are not downloaded again.
+///
+/// When `opts.preserve` is set, the original file attributes are restored
+/// on each file once it's been completely written.
 async fn files_map_get_files(
-    safe: &mut Safe,
+    safe: &Safe,
+    cache: Option<&BlobCache>,
     files_map: &FilesMap,
     dirpath: &str,
+    opts: &FilesGetOptions,
+    mut journal: GetJournal,
     mut callback: impl FnMut(&FilesGetStatus) -> bool,
 ) -> Result<ProcessedFiles> {
     trace!("Fetching files from FilesMap");
@@ -549,6 +1153,7 @@ async fn files_map_get_files(
 
     let mut processed_files = ProcessedFiles::new();
     let mut transfer_bytes_written = 0;
+    let total_files = files_map.len() as u64;
 
     // We need to calc total_transfer_bytes in advance for status callback
     let mut total_transfer_bytes = files_map
@@ -556,14 +1161,12 @@ async fn files_map_get_files(
         .map(|(_path, details)| &details["size"]) // todo: use FileItem::getattr()
         .fold(0, |tot, size| tot + size.parse().unwrap_or(0));
 
-    // Loop through files map and download each file.
-    // caller may cancel individual files, but not entire transfer.
+    // Loop through files map creating dirs and symlinks, and preparing the
+    // list of files to be downloaded. Caller may cancel individual files,
+    // but not entire transfer.
+    let mut downloads = Vec::<FileDownload>::new();
     for (idx, (path, details)) in files_map.iter().enumerate() {
-        let abspath = if !path.is_empty() {
-            dpath.join(path.trim_matches('/'))
-        } else {
-            dpath.to_path_buf()
-        };
+        let abspath = local_path(dpath, path);
         trace!("target path: {}", abspath.display());
 
         // determine the file size from metadata.  string must be parsed.
@@ -572,11 +1175,39 @@ async fn files_map_get_files(
             .parse()
             .context(format!("Invalid file size: {} for {}", size_str, path))?;
 
+        // A file (partially) written by an interrupted transfer is neither
+        // announced to our caller again, nor re-created.
+        let resumed = details
+            .get("link")
+            .and_then(|link| journal.resumable(path, link, size, &abspath));
+        if let Some(file) = resumed {
+            let done_bytes = file.bytes_written();
+            total_transfer_bytes -= done_bytes;
+            if file.complete {
+                if opts.preserve {
+                    restore_file_attributes(&abspath, details);
+                }
+                processed_files.insert(path.to_string(), ("+".to_string(), file.link.clone()));
+                continue;
+            }
+            downloads.push(FileDownload {
+                path,
+                abspath,
+                xorurl: details.getattr("link")?,
+                size,
+                file_type: details.getattr("type")?.to_string(),
+                current_file: idx as u64 + 1,
+                details,
+                done_ranges: file.ranges.clone(),
+            });
+            continue;
+        }
+
         // Setup status to notify our caller of progress in callback.
-        let mut status = FilesGetStatus {
+        let status = FilesGetStatus {
             path_remote: &Path::new(path),
             path_local: &abspath.as_path(),
-            total_files: files_map.len() as u64,
+            total_files,
             current_file: idx as u64 + 1,
             total_transfer_bytes,
             transfer_bytes_written,
@@ -623,26 +1254,176 @@ async fn files_map_get_files(
         }
 
         // Note: must never get here if a directory/symlink.
-        let xorurl = &details.getattr("link")?;
+        let xorurl = details.getattr("link")?;
+
+        // The file is created upfront, its content is then written in
+        // ranges which may complete in any order.
+        if let Err(err) = file_create(&abspath) {
+            processed_files.insert(path.to_string(), ("E".to_string(), format!("<{}>", err)));
+            info!("Skipping file \"{}\". {}", path, err);
+            total_transfer_bytes -= size;
+            continue;
+        }
 
-        // Download file
-        match download_file_from_net(safe, xorurl, abspath.as_path(), size).await {
-            Ok(file_bytes_written) => {
-                processed_files.insert(path.to_string(), ("+".to_string(), (*xorurl).to_string()));
-                transfer_bytes_written += file_bytes_written;
-                status.transfer_bytes_written = transfer_bytes_written;
-                status.file_bytes_written = file_bytes_written;
+        // There is nothing to download for an empty file.
+        let complete = size == 0;
+        journal.files.insert(
+            path.to_string(),
+            JournalFile {
+                link: xorurl.to_string(),
+                size,
+                complete,
+                ranges: BTreeSet::new(),
+            },
+        );
+        if complete {
+            if opts.preserve {
+                restore_file_attributes(&abspath, details);
+            }
+            processed_files.insert(path.to_string(), ("+".to_string(), xorurl.to_string()));
+            continue;
+        }
+
+        downloads.push(FileDownload {
+            path,
+            abspath,
+            xorurl,
+            size,
+            file_type: details.getattr("type")?.to_string(),
+            current_file: idx as u64 + 1,
+            details,
+            done_ranges: BTreeSet::new(),
+        });
+    }
+
+    if !downloads.is_empty() {
+        journal.save_or_warn();
+    }
+
+    // Split each file into ranges, keeping the order of the FilesMap so
+    // files tend to complete in the same order they are listed.
+    let ranges = downloads
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, file)| {
+            (0..file.size)
+                .step_by(DOWNLOAD_RANGE_SIZE as usize)
+                .filter(move |offset| !file.done_ranges.contains(offset))
+                .map(move |offset| (idx, offset, DOWNLOAD_RANGE_SIZE.min(file.size - offset)))
+        })
+        .collect::<Vec<_>>();
 
-                // status callback for this file which has been downloaded.
+    // Ranges written by an interrupted transfer are not part of this transfer,
+    // but they still count towards each file's progress.
+    let mut file_bytes_written = downloads
+        .iter()
+        .map(|file| {
+            file.done_ranges
+                .iter()
+                .map(|offset| DOWNLOAD_RANGE_SIZE.min(file.size.saturating_sub(*offset)))
+                .sum::<u64>()
+        })
+        .collect::<Vec<_>>();
+    let mut file_failed = vec![false; downloads.len()];
+
+    let mut downloaded_ranges = stream::iter(ranges)
+        .map(|(idx, offset, len)| {
+            let file = &downloads[idx];
+            async move {
+                let result =
+                    download_range_from_net(safe, cache, file.xorurl, &file.abspath, offset, len)
+                        .await;
+                (idx, offset, result)
+            }
+        })
+        .buffer_unordered(opts.jobs);
+
+    // Ranges are reported here one at a time as they complete, so the
+    // caller's callback is never invoked concurrently.
+    while let Some((idx, offset, result)) = downloaded_ranges.next().await {
+        if file_failed[idx] {
+            // We already reported this file as failed, its remaining
+            // ranges were already deducted from the transfer.
+            continue;
+        }
+        let file = &downloads[idx];
+
+        let result = result.and_then(|bytes_written| {
+            file_bytes_written[idx] += bytes_written;
+            transfer_bytes_written += bytes_written;
+            if file_bytes_written[idx] == file.size {
+                // Close may generate an error, so we do a sync first to detect such.
+                // see https://github.com/rust-lang/rust/pull/63410#issuecomment-519965351
+                let fh = file_open_for_write(&file.abspath)?;
+                file_sync_all(&fh, &file.abspath)?;
+                if opts.preserve {
+                    restore_file_attributes(&file.abspath, file.details);
+                }
+                // when verifying, files are only cached once verified.
+                if let (Some(cache), false) = (cache, opts.verify) {
+                    if let Err(err) = cache.insert_file(file.xorurl, &file.abspath) {
+                        warn!("Failed to store {} in local cache: {}", file.xorurl, err);
+                    }
+                }
+            }
+            Ok(())
+        });
+
+        match result {
+            Ok(()) => {
+                let complete = file_bytes_written[idx] == file.size;
+                if complete {
+                    processed_files.insert(
+                        file.path.to_string(),
+                        ("+".to_string(), file.xorurl.to_string()),
+                    );
+                }
+
+                if let Some(entry) = journal.files.get_mut(file.path) {
+                    if complete {
+                        entry.complete = true;
+                        entry.ranges.clear();
+                    } else {
+                        entry.ranges.insert(offset);
+                    }
+                    journal.save_if_due();
+                }
+
+                let status = FilesGetStatus {
+                    path_remote: &Path::new(file.path),
+                    path_local: &file.abspath.as_path(),
+                    total_files,
+                    current_file: file.current_file,
+                    total_transfer_bytes,
+                    transfer_bytes_written,
+                    file_size: file.size,
+                    file_bytes_written: file_bytes_written[idx],
+                    file_type: file.file_type.clone(),
+                };
+
+                // status callback for this file which has been (partially) downloaded.
                 callback(&status);
             }
             Err(err) => {
-                processed_files.insert(path.to_string(), ("E".to_string(), format!("<{}>", err)));
-                info!("Skipping file \"{}\". {}", path, err);
+                file_failed[idx] = true;
+                total_transfer_bytes -= file.size - file_bytes_written[idx];
+                processed_files.insert(
+                    file.path.to_string(),
+                    ("E".to_string(), format!("<{}>", err)),
+                );
+                info!("Skipping file \"{}\". {}", file.path, err);
             }
         };
     }
 
+    // The journal is only needed if we'll have to resume this transfer.
+    let all_retrieved = processed_files.values().all(|(change, _)| change == "+");
+    if all_retrieved {
+        journal.remove()?;
+    } else if !journal.files.is_empty() {
+        journal.save_or_warn();
+    }
+
     Ok(processed_files)
 }
 
@@ -697,7 +1478,7 @@ async fn create_symlink(target: &Path, link: &Path, target_type: &str) -> ApiRes
         Err((msg, os_err)) => {
             warn!("{}", msg);
             warn!("{}", os_err);
-            println!("{}", msg);
+            eprintln!("Warning: {}", msg);
         }
     }
 
@@ -708,39 +1489,96 @@ fn denormalize_slashes(p: &str) -> String {
     p.replace('/', &std::path::MAIN_SEPARATOR.to_string())
 }
 
-// Downloads a file from the network to a given file path
-// xorurl must point to a Blob
-// size (in bytes) must be provided
-async fn download_file_from_net(
-    safe: &mut Safe,
+// Downloads a range of a file from the network and writes it
+// at the same offset of a given file path.
+// xorurl must point to a Blob, and the file must already exist.
+async fn download_range_from_net(
+    safe: &Safe,
+    cache: Option<&BlobCache>,
     xorurl: &str,
     path: &Path,
-    size: u64,
+    offset: u64,
+    len: u64,
 ) -> Result<u64> {
-    debug!("downloading file {} to {}", xorurl, path.display());
+    debug!(
+        "downloading range [{}..{}) of {} to {}",
+        offset,
+        offset + len,
+        xorurl,
+        path.display()
+    );
 
-    // TODO: download the file by concurrently (spawning tasks/threads) pulling chunks.
-    // The chunk_size can be based on https://stackoverflow.com/questions/8803515/optimal-buffer-size-for-write2
-    // which can be 4096 to match common disk block size, but that seems a bit small for the
-    // network, so I multiplied by 16. Perhaps should make it a param so caller can decide.
-    let mut rcvd: u64 = 0;
-    let mut bytes_written: u64 = 0;
+    // gets public or private, based on xorurl type
+    let range = Some((Some(offset), Some(offset + len)));
+    let filedata = files_get_blob(safe, cache, &xorurl, range).await?;
+    trace!("received {} bytes of {}", filedata.len(), len);
 
-    let fh = file_create(path)?;
+    let fh = file_open_for_write(path)?;
     let mut stream = BufWriter::new(fh);
+    stream
+        .seek(SeekFrom::Start(offset))
+        .with_context(|| format!("Error seeking in file: \"{}\"", path.display()))?;
+    let bytes_written = stream_write(&mut stream, &filedata, &path)? as u64;
+    bufwriter_into_inner(stream, &path)?;
 
-    // gets public or private, based on xorurl type
-    let filedata = files_get_blob(safe, &xorurl, None).await?;
-    bytes_written += stream_write(&mut stream, &filedata, &path)? as u64;
-    rcvd += filedata.len() as u64;
-    trace!("received {} bytes of {}", rcvd, size,);
+    Ok(bytes_written)
+}
+
+// Restores the attributes of the original file on a local file which has
+// been completely written. Attributes which were not recorded in the
+// FilesMap are left untouched, and failures are only reported as
+// warnings, the same way `cp -p` does.
+fn restore_file_attributes(path: &Path, details: &BTreeMap<String, String>) {
+    let attr = |name: &str| details.get(name).map(String::as_str);
+
+    if let Some(mode_bits) = attr(ATTR_MODE_BITS) {
+        if let Err(err) = set_file_mode(path, mode_bits) {
+            warn_attribute_not_restored(path, &err);
+        }
+    }
+
+    if let Some(modified) = attr(ATTR_ORIGINAL_MODIFIED) {
+        if let Err(err) = set_file_mtime(path, modified) {
+            warn_attribute_not_restored(path, &err);
+        }
+    }
+}
 
-    // Close may generate an error, so we do a flush/sync first to detect such.
-    // see https://github.com/rust-lang/rust/pull/63410#issuecomment-519965351
-    let fh = bufwriter_into_inner(stream, &path)?;
-    file_sync_all(&fh, &path)?;
+fn warn_attribute_not_restored(path: &Path, err: &anyhow::Error) {
+    let msg = format!(
+        "could not preserve attributes of \"{}\": {}",
+        path.display(),
+        err
+    );
+    warn!("{}", msg);
+    if isatty::stderr_isatty() {
+        eprintln!("Warning: {}", msg);
+    }
+}
+
+// sets permission bits of a file, eg. "33261" (0o100755)
+#[cfg(unix)]
+fn set_file_mode(path: &Path, mode_bits: &str) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode: u32 = mode_bits
+        .parse()
+        .with_context(|| format!("Invalid mode bits: {}", mode_bits))?;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .context("Couldn't set permissions".to_string())
+}
+
+// mode bits are not meaningful on non-unix platforms.
+#[cfg(not(unix))]
+fn set_file_mode(_path: &Path, _mode_bits: &str) -> Result<()> {
+    Ok(())
+}
 
-    Ok(bytes_written as u64)
+// sets the modification time of a file from an RFC3339 timestamp.
+fn set_file_mtime(path: &Path, modified: &str) -> Result<()> {
+    let t = DateTime::parse_from_rfc3339(modified)
+        .with_context(|| format!("Invalid timestamp: {}", modified))?;
+    let mtime = FileTime::from_unix_time(t.timestamp(), t.timestamp_subsec_nanos());
+    filetime::set_file_mtime(path, mtime).context("Couldn't set modification time".to_string())
 }
 
 // syncs file to filesystem.
@@ -761,11 +1599,12 @@ fn bufwriter_into_inner<W: Write>(w: BufWriter<W>, path: &Path) -> Result<W> {
     }
 }
 
-// Writes data to a file/stream.
+// Writes all data to a file/stream.
 fn stream_write(writer: &mut dyn Write, data: &[u8], path: &Path) -> Result<usize> {
     writer
-        .write(&data)
-        .with_context(|| format!("Error writing to file: \"{}\"", path.display(),))
+        .write_all(&data)
+        .with_context(|| format!("Error writing to file: \"{}\"", path.display(),))?;
+    Ok(data.len())
 }
 
 // Creates a file, ready for writing.
@@ -773,6 +1612,14 @@ fn file_create(path: &Path) -> Result<fs::File> {
     fs::File::create(path).with_context(|| format!("Couldn't create file: \"{}\"", path.display(),))
 }
 
+// Opens an existing file for writing, without truncating it.
+fn file_open_for_write(path: &Path) -> Result<fs::File> {
+    fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Couldn't open file: \"{}\"", path.display(),))
+}
+
 // create all directories in path if possible.
 fn create_dir_all(dir_path: &Path) -> Result<()> {
     if dir_path.is_file() {
@@ -787,19 +1634,40 @@ fn create_dir_all(dir_path: &Path) -> Result<()> {
 
 /// # Get Private Blob
 /// Get private immutable data blobs from the network.
-async fn files_get_private_blob(_safe: &Safe, _url: &str, _range: Range) -> Result<Vec<u8>> {
-    unimplemented!();
+///
+/// TODO: sn_api doesn't expose an API to retrieve private blobs yet, so for
+/// now we report it as an error for the file rather than aborting the transfer.
+async fn files_get_private_blob(_safe: &Safe, url: &str, _range: Range) -> Result<Vec<u8>> {
+    bail!(
+        "Retrieving private files is not supported yet, cannot fetch: {}",
+        url
+    )
 }
 
 /// # Get Public or Private Blob
 /// Get immutable data blobs from the network.
-pub async fn files_get_blob(safe: &mut Safe, url: &str, range: Range) -> Result<Vec<u8>> {
+pub async fn files_get_blob(
+    safe: &Safe,
+    cache: Option<&BlobCache>,
+    url: &str,
+    range: Range,
+) -> Result<Vec<u8>> {
     match SafeUrl::from_url(&url)?.data_type() {
         SafeDataType::PublicBlob => {
+            if let Some(data) = cache.and_then(|cache| cache.get(url, range)) {
+                return Ok(data);
+            }
             let pub_blob = safe.files_get_public_blob(&url, range).await?;
+            // Only complete blobs are stored, ranges are stored once
+            // the file they were written to is complete.
+            if let (Some(cache), None) = (cache, range) {
+                if let Err(err) = cache.insert(url, &pub_blob) {
+                    warn!("Failed to store {} in local cache: {}", url, err);
+                }
+            }
             Ok(pub_blob)
         }
-        SafeDataType::PrivateBlob => files_get_private_blob(&safe, &url, range).await,
+        SafeDataType::PrivateBlob => files_get_private_blob(safe, &url, range).await,
         _ => Err(anyhow!("URL target is not immutable data")),
     }
 }
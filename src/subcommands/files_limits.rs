@@ -0,0 +1,179 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::helpers::pluralize;
+use crate::operations::config::{read_upload_limits, UploadLimits};
+use anyhow::{bail, Result};
+use log::debug;
+use sn_api::files::ProcessedFiles;
+use std::path::Path;
+use walkdir::WalkDir;
+
+// Returns the upload limits to apply, those passed as arguments
+// taking precedence over the ones set in the config settings.
+pub fn resolve_upload_limits(
+    max_file_size: Option<u64>,
+    max_total_size: Option<u64>,
+) -> Result<UploadLimits> {
+    let settings = read_upload_limits()?;
+    let limits = UploadLimits {
+        max_file_size: max_file_size.or(settings.max_file_size),
+        max_total_size: max_total_size.or(settings.max_total_size),
+    };
+    debug!("Upload limits: {:?}", limits);
+    Ok(limits)
+}
+
+/// A local file to be uploaded, along with its path relative to the location
+/// it's uploaded from.
+#[derive(Debug, Clone)]
+pub struct LocalFile {
+    pub path: String,
+    pub rel_path: String,
+    pub size: u64,
+}
+
+/// # Lists the local files which an upload of a location would store.
+///
+/// The location is walked the same way it's uploaded, i.e. recursing into its
+/// dirs only if `recursive` is set, and following symlinks only if
+/// `follow_links` is set, and the files found are stat'ed, without reading
+/// their content.
+pub fn local_files(location: &str, recursive: bool, follow_links: bool) -> Vec<LocalFile> {
+    let root = Path::new(location);
+    let walker = WalkDir::new(root)
+        .follow_links(follow_links)
+        .max_depth(if recursive { usize::MAX } else { 1 });
+
+    let mut files = vec![];
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                // sn_api reports it when uploading the location
+                debug!("Failed to read entry of \"{}\": {}", location, err);
+                continue;
+            }
+        };
+        let size = match entry.metadata() {
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            _ => continue,
+        };
+        let rel_path = entry
+            .path()
+            .strip_prefix(root)
+            .map_or_else(|_| String::new(), |p| p.to_string_lossy().to_string());
+        files.push(LocalFile {
+            path: entry.path().display().to_string(),
+            rel_path,
+            size,
+        });
+    }
+    files
+}
+
+/// # Checks the files to be uploaded against the upload limits.
+///
+/// The files are expected to be those listed by `local_files`, left out
+/// those which wouldn't be uploaded again, so the check happens before any
+/// data is written to the network. The files are reported by the path
+/// `original_path` returns for them, i.e. the path the user provided rather
+/// than that of a staged copy.
+///
+/// When the processed files of a dry run are provided, keyed by original
+/// path, the files larger than the max file size are reported in them with
+/// an "E" change and the reason why, and exceeding the max total size is
+/// only warned about. Otherwise this fails if any limit is broken, listing
+/// the files which broke it.
+pub fn enforce_upload_limits(
+    limits: &UploadLimits,
+    files: &[LocalFile],
+    original_path: &dyn Fn(&str) -> String,
+    dry_run_files: Option<&mut ProcessedFiles>,
+) -> Result<()> {
+    if limits.is_empty() {
+        return Ok(());
+    }
+
+    let total_size: u64 = files.iter().map(|file| file.size).sum();
+    let too_large: Vec<&LocalFile> = match limits.max_file_size {
+        Some(max_file_size) => files
+            .iter()
+            .filter(|file| file.size > max_file_size)
+            .collect(),
+        None => vec![],
+    };
+    let total_exceeded = limits
+        .max_total_size
+        .filter(|max_total_size| total_size > *max_total_size);
+
+    if let Some(processed_files) = dry_run_files {
+        for file in too_large {
+            let msg = format!(
+                "<{} bytes exceeds the max file size of {} bytes>",
+                file.size,
+                limits.max_file_size.unwrap_or_default()
+            );
+            processed_files.insert(original_path(&file.path), ("E".to_string(), msg));
+        }
+        if let Some(max_total_size) = total_exceeded {
+            eprintln!(
+                "Warning: the files to be uploaded add up to {} bytes, which exceeds the max total size of {} bytes",
+                total_size, max_total_size
+            );
+        }
+        return Ok(());
+    }
+
+    let mut msg = String::new();
+    if let Some(max_total_size) = total_exceeded {
+        msg.push_str(&format!(
+            "\nThe files to be uploaded add up to {} bytes, which exceeds the max total size of {} bytes",
+            total_size, max_total_size
+        ));
+    }
+    if !too_large.is_empty() {
+        let count = too_large.len() as u64;
+        msg.push_str(&format!(
+            "\n{} {} the max file size of {} bytes:",
+            count,
+            pluralize("file exceeds", "files exceed", count),
+            limits.max_file_size.unwrap_or_default()
+        ));
+        for file in too_large {
+            msg.push_str(&format!(
+                "\n  {} ({} bytes)",
+                original_path(&file.path),
+                file.size
+            ));
+        }
+    }
+    if !msg.is_empty() {
+        bail!("Nothing was uploaded since the upload limits were exceeded:{}\nUse --max-file-size and --max-total-size to allow larger uploads", msg);
+    }
+    Ok(())
+}
+
+// Checks the content read from stdin against the upload limits.
+pub fn enforce_upload_limits_on_content(limits: &UploadLimits, content: &[u8]) -> Result<()> {
+    let size = content.len() as u64;
+    let max_size = match (limits.max_file_size, limits.max_total_size) {
+        (Some(max_file_size), Some(max_total_size)) => max_file_size.min(max_total_size),
+        (Some(max_size), None) | (None, Some(max_size)) => max_size,
+        (None, None) => return Ok(()),
+    };
+    if size > max_size {
+        bail!(
+            "Nothing was uploaded since the content read from stdin, {} bytes, exceeds the upload limit of {} bytes",
+            size,
+            max_size
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,334 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    files_filter::{location_filter_root, FilesFilter},
+    files_limits::{local_files, LocalFile},
+};
+use crate::operations::config::read_media_types;
+use anyhow::{bail, Context, Result};
+use log::{debug, warn};
+use sn_api::{
+    files::{FilesMap, ProcessedFiles},
+    Safe,
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+// Number of bytes read from the start of a file to sniff its media type.
+const SNIFF_LEN: usize = 512;
+
+// Media type of the files found to be text when sniffing their content.
+const TEXT_MEDIA_TYPE: &str = "text/plain";
+
+/// # Rules to choose the media type of the files uploaded.
+///
+/// The media type passed with `--media-type` applies to the single file
+/// uploaded. Otherwise the media type set in the config settings for the
+/// file's extension is used, and the media type of a file without extension
+/// is detected by sniffing its content, unless `--no-sniff-media-type` is
+/// used. Files not matching any rule keep the media type guessed from their
+/// extension when uploaded.
+#[derive(Debug)]
+pub struct MediaTypeRules {
+    media_type: Option<String>,
+    by_extension: BTreeMap<String, String>,
+    sniff: bool,
+}
+
+impl MediaTypeRules {
+    // Creates the rules from the `--media-type` and `--no-sniff-media-type`
+    // args and the config settings.
+    pub fn new(media_type: Option<String>, sniff: bool) -> Result<Self> {
+        let by_extension = read_media_types()?;
+        debug!("Media types by extension: {:?}", by_extension);
+        Ok(Self {
+            media_type,
+            by_extension,
+            sniff,
+        })
+    }
+
+    // Returns true if no rule applies to any file.
+    pub fn is_empty(&self) -> bool {
+        self.media_type.is_none() && self.by_extension.is_empty() && !self.sniff
+    }
+
+    /// # Lists the files of a local location which a rule applies to.
+    ///
+    /// The location is walked the same way it's uploaded, leaving out the
+    /// files excluded by `filter`, and the media type chosen for each file is
+    /// returned along with it. Nothing is read unless a rule may apply.
+    pub fn typed_files(
+        &self,
+        location: &str,
+        recursive: bool,
+        follow_links: bool,
+        filter: &FilesFilter,
+    ) -> Result<Vec<TypedFile>> {
+        if self.is_empty() {
+            return Ok(vec![]);
+        }
+        let filter_root = location_filter_root(location);
+        let mut typed_files = vec![];
+        for file in local_files(location, recursive, follow_links) {
+            let filter_path = Path::new(&file.path)
+                .strip_prefix(&filter_root)
+                .map_or_else(|_| PathBuf::from(&file.path), Path::to_path_buf);
+            if filter.is_excluded(&filter_path, false) {
+                continue;
+            }
+            if let Some(media_type) = self.media_type_of(Path::new(&file.path))? {
+                typed_files.push(TypedFile {
+                    file,
+                    filter_path,
+                    media_type,
+                    explicit: self.media_type.is_some(),
+                });
+            }
+        }
+        debug!("Files with a media type chosen: {:?}", typed_files);
+        Ok(typed_files)
+    }
+
+    // Returns the media type to assign to a local file, if any rule applies to it.
+    fn media_type_of(&self, path: &Path) -> Result<Option<String>> {
+        if let Some(media_type) = &self.media_type {
+            return Ok(Some(media_type.clone()));
+        }
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(extension) => Ok(self
+                .by_extension
+                .get(&normalise_extension(extension))
+                .cloned()),
+            None if self.sniff => {
+                let mut head = Vec::with_capacity(SNIFF_LEN);
+                fs::File::open(path)
+                    .and_then(|file| file.take(SNIFF_LEN as u64).read_to_end(&mut head))
+                    .with_context(|| format!("Failed to read \"{}\"", path.display()))?;
+                Ok(sniff_media_type(&head).map(str::to_string))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// # A local file to be uploaded with the media type chosen for it.
+#[derive(Debug)]
+pub struct TypedFile {
+    pub file: LocalFile,
+    // path relative to the root of the location's filter, to leave the
+    // file out of the upload of the rest of the location.
+    pub filter_path: PathBuf,
+    pub media_type: String,
+    // whether the media type was passed with `--media-type`.
+    explicit: bool,
+}
+
+// Extensions are matched without their leading dot, and case insensitively.
+pub fn normalise_extension(extension: &str) -> String {
+    extension.trim_start_matches('.').to_lowercase()
+}
+
+// Fails if `--media-type` is used for anything other than a single file.
+pub fn check_single_file(media_type: &Option<String>, location: &str) -> Result<()> {
+    if media_type.is_some() && !Path::new(location).is_file() {
+        bail!("--media-type can only be used when uploading a single file");
+    }
+    Ok(())
+}
+
+/// # Stores the files with the media type chosen for them.
+///
+/// The media type of a file is part of the XOR-URL of its content, which is
+/// assigned when stored. The files are thus expected to be left out of the
+/// upload of the rest of their location, so each is stored only once, with
+/// its media type, to be added to the FilesContainer at `base_path` followed
+/// by its path within the location. A file is neither stored nor added if the
+/// FilesContainer, whose FilesMap is `files_map`, already has it with the same
+/// content and media type.
+///
+/// The processed files, keyed by local path, are updated with the files
+/// stored. Returns the entries to add to the FilesMap, so all the files are
+/// added with a single update of the FilesContainer.
+pub async fn store_typed_files(
+    safe: &mut Safe,
+    typed_files: &[TypedFile],
+    base_path: &str,
+    files_map: &FilesMap,
+    force: bool,
+    dry_run: bool,
+    processed_files: &mut ProcessedFiles,
+) -> Result<FilesMap> {
+    let mut entries = FilesMap::new();
+    for typed in typed_files {
+        let local_path = &typed.file.path;
+        let path = if typed.file.rel_path.is_empty() {
+            base_path.to_string()
+        } else {
+            format!(
+                "{}/{}",
+                base_path.trim_end_matches('/'),
+                typed.file.rel_path.replace('\\', "/")
+            )
+        };
+        let existing = files_map.get(&path);
+        if existing.is_some() && !force {
+            let msg = format!(
+                "<\"{}\" already exists on the FilesContainer, use the --force flag to overwrite it>",
+                path
+            );
+            processed_files.insert(local_path.clone(), ("E".to_string(), msg));
+            continue;
+        }
+
+        let data =
+            fs::read(local_path).with_context(|| format!("Failed to read \"{}\"", local_path))?;
+        if let Some(details) = existing {
+            let is_same_size = details.get("size") == Some(&data.len().to_string());
+            if is_same_size && details.get("type") == Some(&typed.media_type) {
+                // the link is computed without storing anything.
+                let link = safe
+                    .files_store_public_blob(&data, Some(&typed.media_type), true)
+                    .await?;
+                if details.get("link") == Some(&link) {
+                    debug!("\"{}\" is unchanged at \"{}\"", local_path, path);
+                    processed_files.remove(local_path);
+                    continue;
+                }
+            }
+        }
+
+        let (link, media_type) = store_typed_file(safe, typed, &data, dry_run).await?;
+        let mut details = local_file_details(safe, local_path).await?;
+        details.insert("link".to_string(), link.clone());
+        if let Some(media_type) = media_type {
+            details.insert("type".to_string(), media_type.to_string());
+        }
+        // a file replaced keeps the time it was first added at.
+        if let Some(created) = existing.and_then(|existing| existing.get("created")) {
+            details.insert("created".to_string(), created.clone());
+        }
+        entries.insert(path, details);
+
+        let change = if existing.is_some() { "*" } else { "+" };
+        processed_files.insert(local_path.clone(), (change.to_string(), link));
+    }
+
+    Ok(entries)
+}
+
+// Returns the details sn_api records in a FilesMap for a local file, e.g.
+// its size and timestamps, which are obtained with a dry run of its upload.
+async fn local_file_details(safe: &mut Safe, path: &str) -> Result<BTreeMap<String, String>> {
+    let (_, _, files_map) = safe
+        .files_container_create(Some(path), None, false, true, true)
+        .await?;
+    files_map
+        .into_iter()
+        .next()
+        .map(|(_, details)| details)
+        .with_context(|| format!("Failed to obtain the details of \"{}\"", path))
+}
+
+// Stores the content of a file with the media type chosen for it, returning
+// its link and the media type it was stored with. A media type set in the
+// config settings which sn_api doesn't support is only warned about, and the
+// content stored without it.
+async fn store_typed_file<'a>(
+    safe: &mut Safe,
+    typed: &'a TypedFile,
+    data: &[u8],
+    dry_run: bool,
+) -> Result<(String, Option<&'a str>)> {
+    let media_type = &typed.media_type;
+    match safe
+        .files_store_public_blob(data, Some(media_type), dry_run)
+        .await
+    {
+        Ok(link) => {
+            debug!(
+                "Stored \"{}\" with media type '{}': {}",
+                typed.file.path, media_type, link
+            );
+            Ok((link, Some(media_type)))
+        }
+        // a media type passed explicitly must be supported.
+        Err(err) if typed.explicit => bail!(
+            "Failed to store \"{}\" as '{}': {}",
+            typed.file.path,
+            media_type,
+            err
+        ),
+        Err(err) => {
+            warn!(
+                "Storing \"{}\" without media type, since it can't be stored as '{}': {}",
+                typed.file.path, media_type, err
+            );
+            let link = safe.files_store_public_blob(data, None, dry_run).await?;
+            Ok((link, None))
+        }
+    }
+}
+
+// Detects the media type of some content from its first bytes, the same
+// way `file --mime-type` does for the most common formats, or as text if
+// it's valid UTF-8 without control characters. Empty content is unknown.
+fn sniff_media_type(head: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\0asm", "application/wasm"),
+        (b"ID3", "audio/mpeg"),
+        (b"OggS", "audio/ogg"),
+    ];
+    if head.is_empty() {
+        return None;
+    }
+    if let Some((_, media_type)) = SIGNATURES
+        .iter()
+        .find(|(signature, _)| head.starts_with(signature))
+    {
+        return Some(*media_type);
+    }
+    if head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    if head.len() >= 8 && &head[4..8] == b"ftyp" {
+        return Some("video/mp4");
+    }
+
+    // the content read may end in the middle of a multi-byte character.
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        Err(err) if err.error_len().is_none() => {
+            std::str::from_utf8(&head[..err.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return None,
+    };
+    let is_text = text.chars().all(|c| !c.is_control() || c.is_whitespace());
+    if !is_text {
+        return None;
+    }
+    let start = text.trim_start().to_lowercase();
+    if start.starts_with("<!doctype html") || start.starts_with("<html") {
+        Some("text/html")
+    } else {
+        Some(TEXT_MEDIA_TYPE)
+    }
+}
@@ -0,0 +1,497 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::{
+    files_get::{fetch_files_map, files_get_blob, DOWNLOAD_RANGE_SIZE},
+    OutputFmt,
+};
+use crate::operations::cache::BlobCache;
+use anyhow::{bail, Context, Result};
+use chrono::DateTime;
+use fuser::{
+    FileAttr, FileType, Filesystem, MountOption, ReplyAttr, ReplyData, ReplyDirectory, ReplyEntry,
+    ReplyOpen, Request,
+};
+use futures::channel::oneshot;
+use log::{debug, info, warn};
+use nix::{
+    libc::{EIO, ENOENT, ENOTDIR, EROFS, O_ACCMODE, O_RDONLY},
+    unistd::{getgid, getuid},
+};
+use sn_api::{files::FilesMap, Safe, SafeUrl};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::OsStr,
+    path::Path,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::runtime::Handle;
+
+// Inode number the kernel expects for the root of the mount.
+const ROOT_INO: u64 = 1;
+
+// How long the kernel can cache the attributes and entries returned.
+const ATTR_TTL: Duration = Duration::from_secs(1);
+
+// When following the latest version of the FilesContainer, how long its
+// FilesMap is used before fetching it again.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+const BLOCK_SIZE: u32 = 512;
+
+// A file, directory or symlink of the mounted FilesContainer.
+#[derive(Debug)]
+struct MountNode {
+    kind: FileType,
+    size: u64,
+    perm: u16,
+    mtime: SystemTime,
+    // XOR-URL of the file's content.
+    link: Option<String>,
+    symlink_target: Option<String>,
+    parent: u64,
+    children: BTreeMap<String, u64>,
+}
+
+impl MountNode {
+    // Creates a directory node for a path which is not
+    // in the FilesMap, but some entries are within it.
+    fn implicit_dir(parent: u64, mtime: SystemTime) -> Self {
+        Self {
+            kind: FileType::Directory,
+            size: 0,
+            perm: 0o555,
+            mtime,
+            link: None,
+            symlink_target: None,
+            parent,
+            children: BTreeMap::new(),
+        }
+    }
+
+    fn from_details(parent: u64, details: &BTreeMap<String, String>, mtime: SystemTime) -> Self {
+        let (kind, default_perm) = match details.get("type").map(String::as_str) {
+            Some("inode/directory") => (FileType::Directory, 0o555),
+            Some("inode/symlink") => (FileType::Symlink, 0o777),
+            _ => (FileType::RegularFile, 0o444),
+        };
+        let perm = details
+            .get("mode_bits")
+            .and_then(|mode_bits| mode_bits.parse::<u32>().ok())
+            // the mount is read-only, so no write permission is reported.
+            .map_or(default_perm, |mode| (mode & 0o7777 & !0o222) as u16);
+        let symlink_target = details.get("symlink_target").cloned();
+        let size = match (&kind, &symlink_target) {
+            (FileType::Symlink, Some(target)) => target.len() as u64,
+            (FileType::RegularFile, _) => details
+                .get("size")
+                .and_then(|size| size.parse().ok())
+                .unwrap_or(0),
+            _ => 0,
+        };
+        let link = match kind {
+            FileType::RegularFile => details.get("link").cloned(),
+            _ => None,
+        };
+
+        Self {
+            kind,
+            size,
+            perm,
+            mtime: entry_mtime(details).unwrap_or(mtime),
+            link,
+            symlink_target,
+            parent,
+            children: BTreeMap::new(),
+        }
+    }
+}
+
+/// # Read-only FUSE filesystem exposing the content of a FilesContainer.
+///
+/// Directory listings are built from the FilesMap, while the content of the
+/// files is only fetched, by ranges, when it's read. Unless the URL targets a
+/// specific version, the FilesMap is fetched again every REFRESH_INTERVAL so
+/// the mount follows the latest version of the FilesContainer.
+struct FilesContainerFs {
+    safe: Safe,
+    // handle to the runtime the command runs on, the FUSE session's thread
+    // blocks on it to fetch the content it's asked for.
+    runtime: Handle,
+    cache: Option<BlobCache>,
+    // content of the last small file read, which is fetched in full, so it's
+    // not fetched again for each of its reads when there's no local cache.
+    last_small_file: Option<(String, Vec<u8>)>,
+    url: String,
+    follow_latest: bool,
+    version: u64,
+    fetched_at: Instant,
+    mounted_at: SystemTime,
+    uid: u32,
+    gid: u32,
+    nodes: HashMap<u64, MountNode>,
+    // inode numbers are kept for each path across refreshes of the FilesMap.
+    inodes: HashMap<String, u64>,
+    next_ino: u64,
+}
+
+impl FilesContainerFs {
+    fn inode_for(&mut self, path: &str) -> u64 {
+        if let Some(ino) = self.inodes.get(path) {
+            return *ino;
+        }
+        let ino = self.next_ino;
+        self.next_ino += 1;
+        self.inodes.insert(path.to_string(), ino);
+        ino
+    }
+
+    // Rebuilds the tree of nodes from the flat list of paths of a FilesMap.
+    fn build_tree(&mut self, files_map: &FilesMap) {
+        let mut nodes = HashMap::new();
+        nodes.insert(ROOT_INO, MountNode::implicit_dir(ROOT_INO, self.mounted_at));
+
+        for (path, details) in files_map.iter() {
+            let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+            let mut parent = ROOT_INO;
+            let mut node_path = String::new();
+            for (i, name) in parts.iter().enumerate() {
+                node_path.push('/');
+                node_path.push_str(name);
+                let ino = self.inode_for(&node_path);
+
+                if i + 1 == parts.len() {
+                    let node = MountNode::from_details(parent, details, self.mounted_at);
+                    match nodes.get_mut(&ino) {
+                        // keep the entries found within a directory before its own entry.
+                        Some(existing) if node.kind == FileType::Directory => {
+                            existing.perm = node.perm;
+                            existing.mtime = node.mtime;
+                        }
+                        _ => {
+                            nodes.insert(ino, node);
+                        }
+                    }
+                } else {
+                    let mtime = self.mounted_at;
+                    nodes
+                        .entry(ino)
+                        .or_insert_with(|| MountNode::implicit_dir(parent, mtime));
+                }
+
+                if let Some(parent_node) = nodes.get_mut(&parent) {
+                    parent_node.children.insert(name.to_string(), ino);
+                }
+                parent = ino;
+            }
+        }
+
+        self.nodes = nodes;
+    }
+
+    // Fetches the FilesMap again if following the latest version and it's stale.
+    // If it can't be fetched, the current tree keeps being exposed.
+    fn refresh(&mut self) {
+        if !self.follow_latest || self.fetched_at.elapsed() < REFRESH_INTERVAL {
+            return;
+        }
+        self.fetched_at = Instant::now();
+
+        let result =
+            self.runtime
+                .block_on(fetch_files_map(&self.safe, &self.url, self.cache.as_ref()));
+        match result {
+            Ok((version, files_map)) => {
+                if version != self.version {
+                    info!(
+                        "FilesContainer at \"{}\" updated to version {}",
+                        self.url, version
+                    );
+                    self.version = version;
+                }
+                self.build_tree(&files_map);
+            }
+            Err(err) => warn!("Failed to refresh FilesContainer content: {}", err),
+        }
+    }
+
+    fn attr(&self, ino: u64, node: &MountNode) -> FileAttr {
+        FileAttr {
+            ino,
+            size: node.size,
+            blocks: (node.size + BLOCK_SIZE as u64 - 1) / BLOCK_SIZE as u64,
+            atime: node.mtime,
+            mtime: node.mtime,
+            ctime: node.mtime,
+            crtime: node.mtime,
+            kind: node.kind,
+            perm: node.perm,
+            nlink: if node.kind == FileType::Directory {
+                2
+            } else {
+                1
+            },
+            uid: self.uid,
+            gid: self.gid,
+            rdev: 0,
+            blksize: BLOCK_SIZE,
+            padding: 0,
+            flags: 0,
+        }
+    }
+
+    // Reads a range of a file's content. Files no bigger than a download
+    // range are fetched in full, so they are kept in the local cache, and
+    // the last one read is also kept in memory.
+    fn read_range(&mut self, link: &str, size: u64, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let end = (offset + len).min(size);
+        if offset >= end {
+            return Ok(vec![]);
+        }
+
+        if size <= DOWNLOAD_RANGE_SIZE {
+            let data = match self.last_small_file.take() {
+                Some((last_link, data)) if last_link == link => data,
+                _ => self.runtime.block_on(files_get_blob(
+                    &self.safe,
+                    self.cache.as_ref(),
+                    link,
+                    None,
+                ))?,
+            };
+            let end = (end as usize).min(data.len());
+            let start = (offset as usize).min(end);
+            let range = data[start..end].to_vec();
+            self.last_small_file = Some((link.to_string(), data));
+            Ok(range)
+        } else {
+            self.runtime.block_on(files_get_blob(
+                &self.safe,
+                self.cache.as_ref(),
+                link,
+                Some((Some(offset), Some(end))),
+            ))
+        }
+    }
+}
+
+impl Filesystem for FilesContainerFs {
+    fn lookup(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        self.refresh();
+        let ino = self
+            .nodes
+            .get(&parent)
+            .and_then(|node| node.children.get(name.to_string_lossy().as_ref()));
+        match ino.and_then(|ino| self.nodes.get(ino).map(|node| (*ino, node))) {
+            Some((ino, node)) => reply.entry(&ATTR_TTL, &self.attr(ino, node), 0),
+            None => reply.error(ENOENT),
+        }
+    }
+
+    fn getattr(&mut self, _req: &Request<'_>, ino: u64, reply: ReplyAttr) {
+        match self.nodes.get(&ino) {
+            Some(node) => reply.attr(&ATTR_TTL, &self.attr(ino, node)),
+            None => reply.error(ENOENT),
+        }
+    }
+
+    fn readlink(&mut self, _req: &Request<'_>, ino: u64, reply: ReplyData) {
+        match self
+            .nodes
+            .get(&ino)
+            .and_then(|node| node.symlink_target.as_ref())
+        {
+            Some(target) => reply.data(target.as_bytes()),
+            None => reply.error(ENOENT),
+        }
+    }
+
+    fn open(&mut self, _req: &Request<'_>, ino: u64, flags: i32, reply: ReplyOpen) {
+        if !self.nodes.contains_key(&ino) {
+            reply.error(ENOENT);
+        } else if flags & O_ACCMODE != O_RDONLY {
+            reply.error(EROFS);
+        } else {
+            reply.opened(0, 0);
+        }
+    }
+
+    fn read(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        size: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyData,
+    ) {
+        let (link, file_size) = match self.nodes.get(&ino) {
+            Some(MountNode {
+                link: Some(link),
+                size,
+                ..
+            }) => (link.clone(), *size),
+            Some(_) => return reply.data(&[]),
+            None => return reply.error(ENOENT),
+        };
+
+        match self.read_range(&link, file_size, offset.max(0) as u64, size as u64) {
+            Ok(data) => reply.data(&data),
+            Err(err) => {
+                warn!("Failed to read {}: {}", link, err);
+                reply.error(EIO);
+            }
+        }
+    }
+
+    fn readdir(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        mut reply: ReplyDirectory,
+    ) {
+        self.refresh();
+        let node = match self.nodes.get(&ino) {
+            Some(node) if node.kind == FileType::Directory => node,
+            Some(_) => return reply.error(ENOTDIR),
+            None => return reply.error(ENOENT),
+        };
+
+        let mut entries = vec![
+            (ino, FileType::Directory, ".".to_string()),
+            (node.parent, FileType::Directory, "..".to_string()),
+        ];
+        for (name, child) in node.children.iter() {
+            if let Some(child_node) = self.nodes.get(child) {
+                entries.push((*child, child_node.kind, name.clone()));
+            }
+        }
+
+        // the offset of each entry is the one the next call resumes from.
+        for (i, (entry_ino, kind, name)) in entries.into_iter().enumerate().skip(offset as usize) {
+            if reply.add(entry_ino, (i + 1) as i64, kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+}
+
+// processes the `safe files mount` command.
+pub async fn process_mount_command(
+    safe: &Safe,
+    url: &str,
+    mountpoint: &str,
+    use_cache: bool,
+    output_fmt: OutputFmt,
+) -> Result<()> {
+    if !Path::new(mountpoint).is_dir() {
+        bail!(
+            "Mount point is not an existing directory: \"{}\"",
+            mountpoint
+        );
+    }
+
+    let cache = if use_cache {
+        BlobCache::open_or_warn()
+    } else {
+        None
+    };
+    let (version, files_map) = fetch_files_map(safe, url, cache.as_ref()).await?;
+    if files_map.contains_key("") {
+        bail!("Only FilesContainers can be mounted, the URL targets a file");
+    }
+    let follow_latest = SafeUrl::from_url(url)?.content_version().is_none();
+
+    let mut fs = FilesContainerFs {
+        safe: safe.clone(),
+        runtime: Handle::current(),
+        cache,
+        last_small_file: None,
+        url: url.to_string(),
+        follow_latest,
+        version,
+        fetched_at: Instant::now(),
+        mounted_at: SystemTime::now(),
+        uid: getuid().as_raw(),
+        gid: getgid().as_raw(),
+        nodes: HashMap::new(),
+        inodes: HashMap::new(),
+        next_ino: ROOT_INO + 1,
+    };
+    fs.build_tree(&files_map);
+
+    if OutputFmt::Pretty == output_fmt {
+        let following = if follow_latest {
+            ", following its latest version"
+        } else {
+            ""
+        };
+        println!(
+            "FilesContainer (version {}) at \"{}\" mounted read-only on \"{}\"{}",
+            version, url, mountpoint, following
+        );
+        println!(
+            "Press Ctrl-C, or run 'fusermount -u {}', to unmount it",
+            mountpoint
+        );
+    }
+
+    let options = [
+        MountOption::RO,
+        MountOption::FSName("safe".to_string()),
+        MountOption::Subtype("safe".to_string()),
+        MountOption::AutoUnmount,
+    ];
+    debug!(
+        "Mounting FilesContainer at \"{}\" on \"{}\"",
+        url, mountpoint
+    );
+    // The FUSE session runs on its own thread until the mount is unmounted,
+    // blocking on the fetch of the content it's asked for, while this task
+    // waits for it without blocking the runtime it's running on.
+    let (sender, receiver) = oneshot::channel();
+    let mountpoint_path = mountpoint.to_string();
+    thread::Builder::new()
+        .name("safe-mount".to_string())
+        .spawn(move || {
+            let result = fuser::mount2(fs, &mountpoint_path, &options);
+            let _ = sender.send(result);
+        })
+        .context("Failed to start the thread serving the mount")?;
+
+    receiver
+        .await
+        .context("The thread serving the mount stopped unexpectedly")?
+        .with_context(|| format!("Failed to mount FilesContainer on \"{}\"", mountpoint))
+}
+
+// Returns the modification time of the original file if it was recorded,
+// or the time the entry was last modified in the FilesContainer otherwise.
+fn entry_mtime(details: &BTreeMap<String, String>) -> Option<SystemTime> {
+    let modified = details
+        .get("o_modified")
+        .or_else(|| details.get("modified"))
+        .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())?;
+    if modified.timestamp() < 0 {
+        return None;
+    }
+    Some(
+        UNIX_EPOCH
+            + Duration::new(
+                modified.timestamp() as u64,
+                modified.timestamp_subsec_nanos(),
+            ),
+    )
+}
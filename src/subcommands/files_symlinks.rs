@@ -0,0 +1,371 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use super::files_filter::SKIPPED_MARKER;
+use anyhow::{bail, Result};
+use log::{debug, warn};
+use sn_api::files::{FilesMap, ProcessedFiles};
+use std::{collections::BTreeMap, path::Path};
+
+type FileDetails = BTreeMap<String, String>;
+
+// Max number of symlinks resolved to reach a target, the same as Linux
+// allows, after which the symlink is considered to be in a loop.
+const MAX_SYMLINK_HOPS: usize = 40;
+
+/// # How to retrieve the symlinks found in a FilesContainer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymlinkPolicy {
+    Preserve,
+    Follow,
+    Skip,
+}
+
+/// Default policy is Preserve
+impl Default for SymlinkPolicy {
+    fn default() -> Self {
+        SymlinkPolicy::Preserve
+    }
+}
+
+// implement FromStr for parsing "--symlinks" arg.
+impl std::str::FromStr for SymlinkPolicy {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, String> {
+        match str {
+            "preserve" => Ok(Self::Preserve),
+            "follow" => Ok(Self::Follow),
+            "skip" => Ok(Self::Skip),
+            other => Err(format!(
+                "'{}' not supported. Supported values are preserve, follow, and skip",
+                other
+            )),
+        }
+    }
+}
+
+/// # What to do with symlinks which are absolute, or escape the retrieved tree.
+///
+/// Such symlinks point outside of the destination once retrieved, thus
+/// to whatever happens to be found at their target on the local machine.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExternalSymlinkPolicy {
+    Keep,
+    Skip,
+    Error,
+}
+
+/// Default policy is Keep
+impl Default for ExternalSymlinkPolicy {
+    fn default() -> Self {
+        ExternalSymlinkPolicy::Keep
+    }
+}
+
+// implement FromStr for parsing "--external-symlinks" arg.
+impl std::str::FromStr for ExternalSymlinkPolicy {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, String> {
+        match str {
+            "keep" => Ok(Self::Keep),
+            "skip" => Ok(Self::Skip),
+            "error" => Ok(Self::Error),
+            other => Err(format!(
+                "'{}' not supported. Supported values are keep, skip, and error",
+                other
+            )),
+        }
+    }
+}
+
+// Outcome of resolving a symlink within a FilesMap.
+#[derive(Debug)]
+enum Resolved {
+    // path of the target in the FilesMap, and whether it's a dir.
+    Target(String, bool),
+    Absolute,
+    Escapes,
+    Missing,
+    Loop,
+}
+
+/// # Applies the symlink policies to the FilesMap of the tree being retrieved.
+///
+/// The paths of the FilesMap are expected to be relative to the root of the
+/// tree being retrieved, as returned by `fetch`. A symlink is external if it's
+/// absolute, or if its target can only be reached by leaving that tree.
+///
+/// With `SymlinkPolicy::Follow` each symlink is replaced by the entry of its
+/// target, or by a copy of the target dir's entries, so their content is
+/// retrieved in place of the symlink. Broken symlinks, and those to a dir
+/// containing them, can't be followed and are skipped.
+///
+/// Returns the FilesMap to be retrieved, and the symlinks skipped with an
+/// `S` change marker and the reason why. `ExternalSymlinkPolicy::Error`
+/// makes this fail if any external symlink is found.
+pub fn apply_symlink_policies(
+    files_map: FilesMap,
+    policy: SymlinkPolicy,
+    external: ExternalSymlinkPolicy,
+) -> Result<(FilesMap, ProcessedFiles)> {
+    let mut applied = FilesMap::new();
+    let mut skipped = ProcessedFiles::new();
+    for (path, details) in files_map.iter() {
+        if !is_symlink(details) {
+            applied.insert(path.to_string(), details.clone());
+            continue;
+        }
+        match policy {
+            SymlinkPolicy::Skip => {
+                let reason = format!("<symlink to {}>", symlink_target(details));
+                skip(&mut skipped, path, reason);
+            }
+            SymlinkPolicy::Preserve => match resolve_symlink(&files_map, path) {
+                resolved @ Resolved::Absolute | resolved @ Resolved::Escapes => {
+                    let keep = handle_external(&mut skipped, path, details, &resolved, external)?;
+                    if keep {
+                        applied.insert(path.to_string(), details.clone());
+                    }
+                }
+                _ => {
+                    applied.insert(path.to_string(), details.clone());
+                }
+            },
+            SymlinkPolicy::Follow => {
+                follow_symlink(
+                    &files_map,
+                    &mut applied,
+                    &mut skipped,
+                    path,
+                    path,
+                    &mut vec![],
+                    external,
+                )?;
+            }
+        }
+    }
+    Ok((applied, skipped))
+}
+
+// Replaces the symlink found at `path` with its target's entries, placed at
+// `vpath`, which differs from `path` when the symlink is within a dir being
+// followed. `followed` holds the target dirs being followed, so a symlink to
+// any of them is detected as a loop instead of being followed forever.
+fn follow_symlink(
+    files_map: &FilesMap,
+    applied: &mut FilesMap,
+    skipped: &mut ProcessedFiles,
+    path: &str,
+    vpath: &str,
+    followed: &mut Vec<String>,
+    external: ExternalSymlinkPolicy,
+) -> Result<()> {
+    let details = &files_map[path];
+    let target = symlink_target(details);
+    match resolve_symlink(files_map, path) {
+        Resolved::Target(target_path, false) => {
+            applied.insert(vpath.to_string(), files_map[&target_path].clone());
+        }
+        Resolved::Target(target_path, true) => {
+            if is_ancestor(&target_path, path) || followed.contains(&target_path) {
+                let reason = format!("<symlink to {} is in a loop>", target);
+                skip(skipped, vpath, reason);
+                return Ok(());
+            }
+            debug!("Following symlink \"{}\" to dir \"{}\"", vpath, target_path);
+            if let Some(target_details) = files_map.get(&target_path) {
+                applied.insert(vpath.to_string(), target_details.clone());
+            }
+            followed.push(target_path.clone());
+            let prefix = format!("{}/", target_path);
+            for (entry_path, entry_details) in files_map.iter() {
+                let rel_path = match entry_path.strip_prefix(&prefix) {
+                    Some(rel_path) => rel_path,
+                    None => continue,
+                };
+                let entry_vpath = format!("{}/{}", vpath, rel_path);
+                if is_symlink(entry_details) {
+                    follow_symlink(
+                        files_map,
+                        applied,
+                        skipped,
+                        entry_path,
+                        &entry_vpath,
+                        followed,
+                        external,
+                    )?;
+                } else {
+                    applied.insert(entry_vpath, entry_details.clone());
+                }
+            }
+            followed.pop();
+        }
+        resolved @ Resolved::Absolute | resolved @ Resolved::Escapes => {
+            if handle_external(skipped, vpath, details, &resolved, external)? {
+                applied.insert(vpath.to_string(), details.clone());
+            }
+        }
+        Resolved::Missing => {
+            let reason = format!("<broken symlink to {}>", target);
+            skip(skipped, vpath, reason);
+        }
+        Resolved::Loop => {
+            let reason = format!("<symlink to {} is in a loop>", target);
+            skip(skipped, vpath, reason);
+        }
+    }
+    Ok(())
+}
+
+// Applies the policy for external symlinks, returning true if it's to be kept.
+fn handle_external(
+    skipped: &mut ProcessedFiles,
+    path: &str,
+    details: &FileDetails,
+    resolved: &Resolved,
+    external: ExternalSymlinkPolicy,
+) -> Result<bool> {
+    let target = symlink_target(details);
+    let reason = match resolved {
+        Resolved::Absolute => format!("absolute symlink to {}", target),
+        _ => format!("symlink to {} escapes the retrieved tree", target),
+    };
+    match external {
+        ExternalSymlinkPolicy::Keep => Ok(true),
+        ExternalSymlinkPolicy::Skip => {
+            skip(skipped, path, format!("<{}>", reason));
+            Ok(false)
+        }
+        ExternalSymlinkPolicy::Error => bail!(
+            "Found external symlink \"{}\", {}. Use --external-symlinks=keep or --external-symlinks=skip to retrieve the files anyway",
+            path,
+            reason
+        ),
+    }
+}
+
+fn skip(skipped: &mut ProcessedFiles, path: &str, reason: String) {
+    warn!("Skipping \"{}\": {}", path, reason);
+    skipped.insert(path.to_string(), (SKIPPED_MARKER.to_string(), reason));
+}
+
+// Resolves the symlink found at `path` to the path of its final target in the
+// FilesMap, resolving any symlinks found along the way, as the OS would do
+// once the symlinks are retrieved.
+fn resolve_symlink(files_map: &FilesMap, path: &str) -> Resolved {
+    let mut current = components(path);
+    current.pop();
+    let mut hops = 0;
+    resolve_target(
+        files_map,
+        current,
+        symlink_target(&files_map[path]),
+        &mut hops,
+    )
+}
+
+fn resolve_target(
+    files_map: &FilesMap,
+    mut current: Vec<String>,
+    target: &str,
+    hops: &mut usize,
+) -> Resolved {
+    if target.starts_with('/') || Path::new(target).has_root() {
+        return Resolved::Absolute;
+    }
+    *hops += 1;
+    if *hops > MAX_SYMLINK_HOPS {
+        return Resolved::Loop;
+    }
+
+    let target_components = components(target);
+    let last = target_components.len();
+    for (idx, component) in target_components.into_iter().enumerate() {
+        match component.as_str() {
+            "." => continue,
+            ".." => {
+                // leaving the root of the tree being retrieved.
+                if current.pop().is_none() {
+                    return Resolved::Escapes;
+                }
+                continue;
+            }
+            _ => current.push(component),
+        }
+
+        let current_path = join(&current);
+        match files_map.get(&current_path) {
+            Some(details) if is_symlink(details) => {
+                current.pop();
+                match resolve_target(files_map, current, symlink_target(details), hops) {
+                    Resolved::Target(resolved, is_dir) => {
+                        if !is_dir && idx + 1 < last {
+                            return Resolved::Missing;
+                        }
+                        current = components(&resolved);
+                    }
+                    other => return other,
+                }
+            }
+            Some(details) => {
+                if !is_dir(details) && idx + 1 < last {
+                    return Resolved::Missing;
+                }
+            }
+            None if is_implicit_dir(files_map, &current_path) => {}
+            None => return Resolved::Missing,
+        }
+    }
+
+    let target_path = join(&current);
+    // a path not found in the FilesMap was already checked to be a dir.
+    let target_is_dir = files_map.get(&target_path).map_or(true, is_dir);
+    Resolved::Target(target_path, target_is_dir)
+}
+
+fn is_dir(details: &FileDetails) -> bool {
+    details
+        .get("type")
+        .map_or(false, |t| t == "inode/directory")
+}
+
+fn is_symlink(details: &FileDetails) -> bool {
+    details.get("type").map_or(false, |t| t == "inode/symlink")
+}
+
+fn symlink_target(details: &FileDetails) -> &str {
+    details.get("symlink_target").map_or("", String::as_str)
+}
+
+// A dir may have no entry of its own in the FilesMap, but only those of its content.
+fn is_implicit_dir(files_map: &FilesMap, path: &str) -> bool {
+    let prefix = format!("{}/", path);
+    path.is_empty() || files_map.keys().any(|p| p.starts_with(&prefix))
+}
+
+// Returns true if `dir` is `path`, or any of its parents.
+fn is_ancestor(dir: &str, path: &str) -> bool {
+    dir.is_empty() || path == dir || path.starts_with(&format!("{}/", dir))
+}
+
+fn components(path: &str) -> Vec<String> {
+    path.split(&['/', '\\'][..])
+        .filter(|c| !c.is_empty())
+        .map(|c| c.to_string())
+        .collect()
+}
+
+// Joins the components of a path in the FilesMap, which all start with a slash.
+fn join(components: &[String]) -> String {
+    if components.is_empty() {
+        "".to_string()
+    } else {
+        format!("/{}", components.join("/"))
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use anyhow::{anyhow, bail, Context, Result};
+use futures::{future, StreamExt};
+use inotify::{EventMask, EventOwned, EventStream, Inotify, WatchMask};
+use log::{debug, trace};
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::{
+    signal::unix::{signal, Signal, SignalKind},
+    time::{sleep_until, Instant},
+};
+use walkdir::WalkDir;
+
+// How long the location needs to be left unchanged before syncing it, so
+// a build writing many files results in a single new version.
+const DEBOUNCE_DELAY: Duration = Duration::from_secs(2);
+
+// Size of the buffer the inotify events are read into.
+const EVENTS_BUFFER_SIZE: usize = 4096;
+
+/// # Watches a local location for changes, using inotify.
+///
+/// Watches are added for the location's directory and, if recursive, for
+/// each of its subdirectories. When the location is a file, its parent
+/// directory is watched and the events for any other entry are ignored.
+/// Ctrl-C is handled once the watcher is created, so a sync in progress
+/// isn't interrupted by it.
+pub struct LocationWatcher {
+    inotify: Inotify,
+    events: EventStream<Vec<u8>>,
+    interrupt: Signal,
+    root: PathBuf,
+    file_name: Option<OsString>,
+    recursive: bool,
+    follow_links: bool,
+}
+
+impl LocationWatcher {
+    pub fn new(location: &str, recursive: bool, follow_links: bool) -> Result<Self> {
+        let path = Path::new(location);
+        if !path.exists() {
+            bail!("No such file or directory: \"{}\"", location);
+        }
+        let (root, file_name) = if path.is_dir() {
+            (path.to_path_buf(), None)
+        } else {
+            let root = match path.parent() {
+                Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+                _ => PathBuf::from("."),
+            };
+            (root, path.file_name().map(|name| name.to_os_string()))
+        };
+
+        let mut inotify = Inotify::init().context("Failed to initialise inotify")?;
+        let events = inotify
+            .event_stream(vec![0; EVENTS_BUFFER_SIZE])
+            .context("Failed to read inotify events")?;
+        let interrupt =
+            signal(SignalKind::interrupt()).context("Failed to listen for Ctrl-C signal")?;
+
+        let mut watcher = Self {
+            inotify,
+            events,
+            interrupt,
+            root,
+            file_name,
+            recursive,
+            follow_links,
+        };
+        watcher.watch_dirs()?;
+        Ok(watcher)
+    }
+
+    // Adds a watch for each dir of the location, including those created
+    // since the last call. Dirs already watched keep their current watch.
+    pub fn watch_dirs(&mut self) -> Result<()> {
+        let mask = WatchMask::MODIFY
+            | WatchMask::CLOSE_WRITE
+            | WatchMask::ATTRIB
+            | WatchMask::CREATE
+            | WatchMask::DELETE
+            | WatchMask::MOVED_FROM
+            | WatchMask::MOVED_TO
+            | WatchMask::DELETE_SELF
+            | WatchMask::MOVE_SELF;
+
+        if self.file_name.is_some() {
+            return self.add_watch(&self.root.clone(), mask);
+        }
+
+        let max_depth = if self.recursive { usize::MAX } else { 0 };
+        let dirs: Vec<PathBuf> = WalkDir::new(&self.root)
+            .max_depth(max_depth)
+            .follow_links(self.follow_links)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_dir())
+            .map(|entry| entry.path().to_path_buf())
+            .collect();
+        for dir in dirs.iter() {
+            self.add_watch(dir, mask)?;
+        }
+        debug!(
+            "Watching {} dir(s) of \"{}\"",
+            dirs.len(),
+            self.root.display()
+        );
+
+        Ok(())
+    }
+
+    // Waits until the location changes and is then left unchanged for
+    // DEBOUNCE_DELAY. Returns false if Ctrl-C is pressed before that.
+    pub async fn wait_for_changes(&mut self) -> Result<bool> {
+        let mut deadline: Option<Instant> = None;
+        loop {
+            let debounce = async move {
+                match deadline {
+                    Some(deadline) => sleep_until(deadline).await,
+                    None => future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                _ = self.interrupt.recv() => return Ok(false),
+                _ = debounce => return Ok(true),
+                event = self.events.next() => {
+                    let event = event
+                        .ok_or_else(|| anyhow!("Stopped receiving inotify events"))?
+                        .context("Failed to read inotify event")?;
+                    if self.is_relevant(&event) {
+                        trace!("Change detected in \"{}\": {:?}", self.root.display(), event);
+                        deadline = Some(Instant::now() + DEBOUNCE_DELAY);
+                    }
+                }
+            }
+        }
+    }
+
+    fn add_watch(&mut self, dir: &Path, mask: WatchMask) -> Result<()> {
+        self.inotify
+            .add_watch(dir, mask)
+            .with_context(|| format!("Failed to watch \"{}\"", dir.display()))?;
+        Ok(())
+    }
+
+    fn is_relevant(&self, event: &EventOwned) -> bool {
+        if event.mask.contains(EventMask::IGNORED) {
+            // the watch was removed, e.g. because its dir was removed.
+            return false;
+        }
+        match &self.file_name {
+            Some(file_name) => event.name.as_ref() == Some(file_name),
+            None => true,
+        }
+    }
+}
@@ -7,7 +7,7 @@
 // specific language governing permissions and limitations relating to use of the SAFE Network
 // Software.
 
-use super::OutputFmt;
+use super::{files_filter::SKIPPED_MARKER, OutputFmt};
 use ansi_term::Style;
 use anyhow::{anyhow, bail, Context, Result};
 use log::debug;
@@ -142,7 +142,8 @@ pub fn gen_processed_files_table(
     table.set_format(format);
     let mut success_count = 0;
     for (file_name, (change, link)) in processed_files.iter() {
-        if change != "E" {
+        // errors and files filtered out are not changes.
+        if change != "E" && change != SKIPPED_MARKER {
             success_count += 1;
         }
         if show_change_sign {
@@ -163,6 +164,38 @@ pub fn parse_stdin_arg(src: &str) -> String {
     }
 }
 
+// Parses a size in bytes, optionally followed by a unit, e.g. "10MB" or "1.5GiB".
+// K, M, G and T are 1024 based, the same as KiB, MiB, GiB and TiB, while KB,
+// MB, GB and TB are 1000 based.
+pub fn parse_size(src: &str) -> Result<u64, String> {
+    let src = src.trim();
+    let split = src
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or_else(|| src.len());
+    let (number, unit) = src.split_at(split);
+    let multiplier: u64 = match unit.trim().to_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KIB" => 1 << 10,
+        "M" | "MIB" => 1 << 20,
+        "G" | "GIB" => 1 << 30,
+        "T" | "TIB" => 1 << 40,
+        "KB" => 1_000,
+        "MB" => 1_000_000,
+        "GB" => 1_000_000_000,
+        "TB" => 1_000_000_000_000,
+        _ => {
+            return Err(format!(
+                "'{}' is not a supported unit. Supported units are B, K, KB, KiB, M, MB, MiB, G, GB, GiB, T, TB and TiB",
+                unit.trim()
+            ))
+        }
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid size", src))?;
+    Ok((number * multiplier as f64) as u64)
+}
+
 // serialize structured value using any format from OutputFmt
 // except OutputFmt::Pretty, which must be handled by caller.
 pub fn serialise_output<T: ?Sized>(value: &T, fmt: OutputFmt) -> String
@@ -8,11 +8,23 @@
 // Software.
 
 pub mod auth;
+pub mod cache;
 pub mod cat;
 pub mod config;
 pub mod dog;
 pub mod files;
+mod files_archive;
+mod files_du;
+mod files_filter;
+mod files_find;
 mod files_get;
+mod files_limits;
+mod files_media_type;
+#[cfg(target_os = "linux")]
+mod files_mount;
+mod files_symlinks;
+#[cfg(target_os = "linux")]
+mod files_watch;
 mod helpers;
 pub mod keys;
 pub mod networks;
@@ -94,6 +106,17 @@ pub enum SubCommands {
     )]
     /// Read data on the SAFE Network
     Cat(cat::CatCommands),
+    #[structopt(
+        name = "cache",
+        no_version,
+        global_settings(&[AppSettings::DisableVersion]),
+    )]
+    /// Manage the local cache of files fetched from the SAFE Network
+    Cache {
+        /// subcommands
+        #[structopt(subcommand)]
+        cmd: Option<cache::CacheSubCommands>,
+    },
     #[structopt(
         name = "dog",
         no_version,
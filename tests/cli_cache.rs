@@ -0,0 +1,84 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use anyhow::{anyhow, Result};
+use sn_cmd_test_utilities::util::{
+    get_random_nrs_string, mk_emptyfolder, parse_files_put_or_sync_output, safe_cmd_stdout,
+    safeurl_from,
+};
+use std::{fs, path::Path};
+
+// Uploads a file with random content, so its blob is not
+// shared with other tests, returning its URL and content.
+fn upload_random_file() -> Result<(String, String)> {
+    let (tmp_dir, folder) = mk_emptyfolder("cache")?;
+    let content = get_random_nrs_string();
+    let filepath = Path::new(&folder).join("random.txt");
+    fs::write(&filepath, &content).map_err(|e| anyhow!(e.to_string()))?;
+
+    let path = filepath.display().to_string();
+    let stdout = safe_cmd_stdout(&["files", "put", &path, "--json"], Some(0))?;
+    let (_container_xorurl, processed_files) = parse_files_put_or_sync_output(&stdout);
+    fs::remove_dir_all(&tmp_dir).map_err(|e| anyhow!(e.to_string()))?;
+
+    Ok((processed_files[&path].1.clone(), content))
+}
+
+#[test]
+fn calling_safe_cat_stores_blob_in_cache() -> Result<()> {
+    let (blob_url, content) = upload_random_file()?;
+
+    let cat = safe_cmd_stdout(&["cat", &blob_url], Some(0))?;
+    assert_eq!(cat, content);
+
+    let stdout = safe_cmd_stdout(&["cache", "stats", "--json"], Some(0))?;
+    let stats: serde_json::Value =
+        serde_json::from_str(&stdout).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(stats["blobs"].as_u64().unwrap_or(0) >= 1);
+    assert!(stats["size"].as_u64().unwrap_or(0) >= content.len() as u64);
+
+    Ok(())
+}
+
+#[test]
+fn calling_safe_cache_verify_removes_corrupted_blob() -> Result<()> {
+    let (blob_url, content) = upload_random_file()?;
+    let _ = safe_cmd_stdout(&["cat", &blob_url], Some(0))?;
+
+    // corrupt the cached blob, keeping its size
+    let stdout = safe_cmd_stdout(&["cache", "stats", "--json"], Some(0))?;
+    let stats: serde_json::Value =
+        serde_json::from_str(&stdout).map_err(|e| anyhow!(e.to_string()))?;
+    let location = stats["location"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Missing cache location"))?;
+    let key: String = safeurl_from(&blob_url)?
+        .xorname()
+        .0
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let blob_path = Path::new(location).join(&key);
+    fs::write(&blob_path, "x".repeat(content.len())).map_err(|e| anyhow!(e.to_string()))?;
+
+    // the cache is bypassed with --no-cache
+    let cat = safe_cmd_stdout(&["cat", &blob_url, "--no-cache"], Some(0))?;
+    assert_eq!(cat, content);
+
+    let stdout = safe_cmd_stdout(&["cache", "verify", "--json"], Some(0))?;
+    let corrupted: Vec<String> =
+        serde_json::from_str(&stdout).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(corrupted.contains(&key));
+    assert!(!blob_path.exists());
+
+    let cat = safe_cmd_stdout(&["cat", &blob_url], Some(0))?;
+    assert_eq!(cat, content);
+
+    Ok(())
+}
@@ -15,7 +15,7 @@ use assert_cmd::prelude::*;
 use predicates::prelude::*;
 use sn_api::{
     fetch::{SafeContentType, SafeDataType},
-    sk_to_hex, Keypair,
+    sk_to_hex, Keypair, SafeUrl, XorUrlBase,
 };
 use sn_cmd_test_utilities::util::{
     create_preload_and_get_keys, get_random_nrs_string, parse_cat_wallet_output,
@@ -23,6 +23,7 @@ use sn_cmd_test_utilities::util::{
     safeurl_from, test_symlinks_are_valid, upload_test_symlinks_folder, CLI,
 };
 use std::process::Command;
+use xor_name::XorName;
 
 const TEST_DATA: &str = "./testdata/";
 const TEST_FILE: &str = "./testdata/test.md";
@@ -441,3 +442,32 @@ fn calling_cat_symlinks_resolve_dir_outside() -> Result<()> {
 
     Ok(())
 }
+
+// Test:  safe cat <src>
+//    src is the XOR-URL of a private blob.
+//
+//    expected result: error, private files are not supported.
+#[test]
+fn calling_safe_cat_private_blob() -> Result<()> {
+    let xorname = XorName(*b"12345678901234567890123456789012");
+    let url = SafeUrl::encode(
+        xorname,
+        None,
+        0,
+        SafeDataType::PrivateBlob,
+        SafeContentType::Raw,
+        None,
+        None,
+        None,
+        None,
+        None,
+        XorUrlBase::Base32,
+    )
+    .map_err(|e| anyhow!("Failed to encode URL: {}", e))?;
+
+    let args = ["cat", &url];
+    let output = safe_cmd_stderr(&args, Some(1))?;
+    assert!(output.contains("Rendering private files is not supported yet"));
+
+    Ok(())
+}
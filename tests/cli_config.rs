@@ -0,0 +1,86 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+// The config settings are shared by all the commands run, thus these tests are
+// kept apart from those of the commands they affect, so they don't run in parallel.
+
+use anyhow::{anyhow, Result};
+use sn_cmd_test_utilities::util::{
+    mk_emptyfolder, parse_files_put_or_sync_output, safe_cmd_stderr, safe_cmd_stdout,
+};
+use std::fs;
+
+const TEST_FOLDER: &str = "./testdata/";
+
+#[test]
+fn calling_files_put_with_max_file_size_setting() -> Result<()> {
+    // test.md is 12 bytes long, while another.md is 6 bytes long
+    safe_cmd_stdout(&["config", "add", "max-file-size", "10B"], Some(0))?;
+
+    let args = ["files", "put", TEST_FOLDER, "--recursive", "--json"];
+    let result = safe_cmd_stderr(&args, Some(1));
+    // the option passed takes precedence over the setting
+    let args = [
+        "files",
+        "put",
+        TEST_FOLDER,
+        "--recursive",
+        "--max-file-size",
+        "1KiB",
+        "--json",
+    ];
+    let overridden = safe_cmd_stdout(&args, Some(0));
+
+    // cleanup before checking the results, not to affect other tests
+    safe_cmd_stdout(&["config", "remove", "max-file-size"], Some(0))?;
+
+    let stderr = result?;
+    assert!(stderr.contains("exceeds the max file size of 10 bytes"));
+    assert!(stderr.contains("./testdata/test.md"));
+    overridden?;
+    Ok(())
+}
+
+#[test]
+fn calling_files_put_with_media_type_setting() -> Result<()> {
+    let (tmp_dir, folder) = mk_emptyfolder("typed").map_err(|e| anyhow!(e.to_string()))?;
+    let page = format!("{}page.xhtm", folder);
+    fs::write(&page, "<html><body>Hello</body></html>")?;
+    fs::write(format!("{}notes.txt", folder), "Some notes")?;
+
+    safe_cmd_stdout(
+        &["config", "add", "media-type", ".XHTM", "text/html"],
+        Some(0),
+    )?;
+
+    let args = ["files", "put", &folder, "--json"];
+    let put = safe_cmd_stdout(&args, Some(0));
+
+    // cleanup before checking the results, not to affect other tests
+    safe_cmd_stdout(&["config", "remove", "media-type", "xhtm"], Some(0))?;
+    fs::remove_dir_all(&tmp_dir).map_err(|e| anyhow!(e.to_string()))?;
+
+    let (container_xorurl, processed_files) = parse_files_put_or_sync_output(&put?);
+    assert_eq!(processed_files.len(), 2);
+    assert_eq!(processed_files[&page].0, "+");
+
+    let args = ["files", "ls", &container_xorurl, "--json"];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (_xorurl, entries, _next_page): (String, Vec<serde_json::Value>, serde_json::Value) =
+        serde_json::from_str(&stdout).map_err(|e| anyhow!(e.to_string()))?;
+    let type_of = |name: &str| {
+        entries
+            .iter()
+            .find(|entry| entry["name"] == name)
+            .map(|entry| entry["details"]["type"].clone())
+    };
+    assert_eq!(type_of("page.xhtm"), Some("text/html".into()));
+    assert_eq!(type_of("notes.txt"), Some("text/plain".into()));
+    Ok(())
+}
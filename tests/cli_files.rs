@@ -16,13 +16,15 @@ use predicates::prelude::*;
 use sn_cmd_test_utilities::util::{
     create_nrs_link, get_random_nrs_string, mk_emptyfolder, parse_files_container_output,
     parse_files_put_or_sync_output, parse_files_tree_output, safe_cmd_stderr, safe_cmd_stdout,
-    safeurl_from, test_symlinks_are_valid, upload_test_symlinks_folder,
+    safeurl_from, test_symlinks_are_valid, upload_path, upload_test_symlinks_folder,
     upload_testfolder_no_trailing_slash, upload_testfolder_trailing_slash, CLI, SAFE_PROTOCOL,
 };
 use std::{
+    collections::BTreeMap,
     env,
     fs::{self, OpenOptions},
     io::{prelude::*, Seek, SeekFrom},
+    path::Path,
     process::Command,
 };
 
@@ -95,6 +97,278 @@ fn calling_safe_files_put_recursive() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn calling_safe_files_put_recursive_with_exclude() -> Result<()> {
+    let mut cmd = Command::cargo_bin(CLI).map_err(|e| anyhow!(e.to_string()))?;
+    cmd.args(&vec![
+        "files",
+        "put",
+        TEST_FOLDER,
+        "--recursive",
+        "--exclude",
+        "subfolder",
+        "--json",
+    ])
+    .assert()
+    .stdout(predicate::str::contains(r#""S""#).count(1))
+    .stdout(predicate::str::contains("./testdata/test.md").count(1))
+    .stdout(predicate::str::contains("./testdata/subfolder/subexists.md").count(0))
+    .success();
+    Ok(())
+}
+
+#[test]
+fn calling_safe_files_put_and_sync_skip_get_journal() -> Result<()> {
+    let (tmp_dir, folder) = mk_emptyfolder("with_journal").map_err(|e| anyhow!(e.to_string()))?;
+    fs::write(format!("{}file.txt", folder), "some content")?;
+    fs::write(format!("{}.safe-get-state", folder), "{}")?;
+    fs::create_dir(format!("{}subfolder", folder))?;
+    fs::write(format!("{}subfolder/.safe-get-state", folder), "{}")?;
+
+    let args = ["files", "put", &folder, "--recursive", "--json"];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (container_xorurl, processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(processed_files[&format!("{}file.txt", folder)].0, "+");
+    assert_eq!(
+        processed_files[&format!("{}.safe-get-state", folder)].0,
+        "S"
+    );
+    let journal = format!("{}subfolder/.safe-get-state", folder);
+    assert_eq!(processed_files[&journal].0, "S");
+
+    let mut safeurl = safeurl_from(&container_xorurl)?;
+    safeurl.set_content_version(None);
+    fs::write(
+        format!("{}subfolder/.safe-get-state", folder),
+        "{\"changed\": true}",
+    )?;
+    let args = [
+        "files",
+        "sync",
+        &folder,
+        &safeurl.to_string(),
+        "--recursive",
+        "--delete",
+        "--json",
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (_target, processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(processed_files[&journal].0, "S");
+
+    safeurl.set_path("/subfolder/.safe-get-state");
+    let args = ["cat", &safeurl.to_string()];
+    let stderr = safe_cmd_stderr(&args, Some(1))?;
+    assert!(!stderr.is_empty());
+
+    // cleanup
+    fs::remove_dir_all(&tmp_dir).map_err(|e| anyhow!(e.to_string()))?;
+    Ok(())
+}
+
+#[test]
+fn calling_safe_files_put_skips_leftover_staging_dirs() -> Result<()> {
+    let (tmp_dir, folder) = mk_emptyfolder("with_leftover").map_err(|e| anyhow!(e.to_string()))?;
+    fs::write(format!("{}file.txt", folder), "some content")?;
+    let leftover = format!("{}.safe-staging-0123456789abcde", folder);
+    fs::create_dir_all(format!("{}/with_leftover", leftover))?;
+    fs::write(
+        format!("{}/with_leftover/file.txt", leftover),
+        "some content",
+    )?;
+
+    let args = [
+        "files",
+        "put",
+        &folder,
+        "--recursive",
+        "--exclude",
+        "*.md",
+        "--json",
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0));
+    // nothing is left next to the location once uploaded
+    let entries_next_to_it: Vec<_> = fs::read_dir(&tmp_dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name())
+        .collect();
+
+    // cleanup
+    fs::remove_dir_all(&tmp_dir).map_err(|e| anyhow!(e.to_string()))?;
+
+    let (_container_xorurl, processed_files) = parse_files_put_or_sync_output(&stdout?);
+    assert_eq!(processed_files[&format!("{}file.txt", folder)].0, "+");
+    assert_eq!(processed_files[&leftover].0, "S");
+    assert_eq!(entries_next_to_it, vec!["with_leftover"]);
+    Ok(())
+}
+
+#[test]
+fn calling_safe_files_put_recursive_with_max_file_size() -> Result<()> {
+    // test.md is 12 bytes long, while another.md is 6 bytes long
+    let args = [
+        "files",
+        "put",
+        TEST_FOLDER,
+        "--recursive",
+        "--max-file-size",
+        "10B",
+        "--json",
+    ];
+    let stderr = safe_cmd_stderr(&args, Some(1))?;
+    assert!(stderr.contains("exceeds the max file size of 10 bytes"));
+    assert!(stderr.contains("./testdata/test.md"));
+
+    // a dry run reports the files which broke the limit
+    let args = [
+        "files",
+        "put",
+        TEST_FOLDER,
+        "--recursive",
+        "--max-file-size",
+        "10B",
+        "--json",
+        "--dry-run",
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (_container_xorurl, processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(processed_files["./testdata/test.md"].0, "E");
+    assert_eq!(processed_files["./testdata/another.md"].0, "+");
+
+    let args = [
+        "files",
+        "put",
+        TEST_FOLDER,
+        "--recursive",
+        "--max-total-size",
+        "1KiB",
+        "--json",
+    ];
+    safe_cmd_stdout(&args, Some(0))?;
+    Ok(())
+}
+
+#[test]
+fn calling_safe_files_sync_with_max_file_size() -> Result<()> {
+    let (files_container_xor, _processed_files) = upload_testfolder_trailing_slash()?;
+    let mut safeurl = safeurl_from(&files_container_xor)?;
+    safeurl.set_content_version(None);
+    let target = safeurl.to_string();
+
+    // test.md is synced unchanged, thus it's not checked against the limits
+    let (tmp_dir, folder) = mk_emptyfolder("to_sync").map_err(|e| anyhow!(e.to_string()))?;
+    fs::copy(TEST_FILE, format!("{}test.md", folder))?;
+    fs::write(format!("{}small.txt", folder), "small")?;
+    fs::write(format!("{}large.txt", folder), "larger than 10 bytes")?;
+
+    // the files are reported by their local paths, even if filtered
+    let args = [
+        "files",
+        "sync",
+        &folder,
+        &target,
+        "--recursive",
+        "--exclude",
+        "small.txt",
+        "--max-file-size",
+        "10B",
+        "--json",
+    ];
+    let stderr = safe_cmd_stderr(&args, Some(1))?;
+    assert!(stderr.contains("1 file exceeds the max file size of 10 bytes"));
+    assert!(stderr.contains(&format!("{}large.txt (20 bytes)", folder)));
+
+    let args = [
+        "files",
+        "sync",
+        &folder,
+        &target,
+        "--recursive",
+        "--max-file-size",
+        "10B",
+        "--json",
+        "--dry-run",
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (_target, processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(processed_files[&format!("{}large.txt", folder)].0, "E");
+    assert_eq!(processed_files[&format!("{}small.txt", folder)].0, "+");
+
+    let args = [
+        "files",
+        "sync",
+        &folder,
+        &target,
+        "--recursive",
+        "--exclude",
+        "large.txt",
+        "--max-file-size",
+        "10B",
+        "--json",
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (_target, processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(processed_files[&format!("{}small.txt", folder)].0, "+");
+
+    // cleanup
+    fs::remove_dir_all(&tmp_dir).map_err(|e| anyhow!(e.to_string()))?;
+    Ok(())
+}
+
+#[test]
+fn calling_safe_files_add_with_max_file_size() -> Result<()> {
+    let (files_container_xor, _processed_files) = upload_testfolder_trailing_slash()?;
+    let mut safeurl = safeurl_from(&files_container_xor)?;
+    safeurl.set_content_version(None);
+    safeurl.set_path("/large.txt");
+    let target = safeurl.to_string();
+
+    let (tmp_dir, folder) = mk_emptyfolder("to_add").map_err(|e| anyhow!(e.to_string()))?;
+    let large_file = format!("{}large.txt", folder);
+    fs::write(&large_file, "larger than 10 bytes")?;
+
+    let args = [
+        "files",
+        "add",
+        &large_file,
+        &target,
+        "--max-file-size",
+        "10B",
+    ];
+    let stderr = safe_cmd_stderr(&args, Some(1))?;
+    assert!(stderr.contains("exceeds the max file size of 10 bytes"));
+    assert!(stderr.contains(&large_file));
+
+    let args = [
+        "files",
+        "add",
+        &large_file,
+        &target,
+        "--max-file-size",
+        "10B",
+        "--json",
+        "--dry-run",
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (_target, processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(processed_files[&large_file].0, "E");
+
+    let args = [
+        "files",
+        "add",
+        &large_file,
+        &target,
+        "--max-file-size",
+        "1KiB",
+    ];
+    safe_cmd_stdout(&args, Some(0))?;
+    let cat = safe_cmd_stdout(&["cat", &target], Some(0))?;
+    assert_eq!(cat, "larger than 10 bytes");
+
+    // cleanup
+    fs::remove_dir_all(&tmp_dir).map_err(|e| anyhow!(e.to_string()))?;
+    Ok(())
+}
+
 #[test]
 fn calling_safe_files_put_recursive_and_set_dest_path() -> Result<()> {
     let files_container = cmd!(
@@ -542,6 +816,58 @@ fn calling_files_sync_and_fetch_with_version() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn calling_files_sync_with_delete_keeps_excluded_files() -> Result<()> {
+    let files_container_output = cmd!(
+        env!("CARGO_BIN_EXE_safe"),
+        "files",
+        "put",
+        TEST_FOLDER,
+        "--recursive",
+        "--json"
+    )
+    .read()?;
+    let (files_container_xor, _processed_files) =
+        parse_files_put_or_sync_output(&files_container_output);
+
+    let emptyfolder_paths = mk_emptyfolder("emptyfolder").map_err(|e| anyhow!(e.to_string()))?;
+
+    let mut safeurl = safeurl_from(&files_container_xor)?;
+    safeurl.set_content_version(None);
+    let files_container_no_version = safeurl.to_string();
+    let sync_cmd_output = cmd!(
+        env!("CARGO_BIN_EXE_safe"),
+        "files",
+        "sync",
+        &emptyfolder_paths.1,
+        &files_container_no_version,
+        "--recursive",
+        "--delete",
+        "--exclude",
+        "*.md",
+        "--json",
+    )
+    .read()?;
+
+    // cleanup
+    fs::remove_dir_all(&emptyfolder_paths.0).map_err(|e| anyhow!(e.to_string()))?;
+
+    let (target, _processed_files) = parse_files_put_or_sync_output(&sync_cmd_output);
+    let cat_container = cmd!(env!("CARGO_BIN_EXE_safe"), "cat", &target, "--json").read()?;
+    let (_xorurl, files_map) = parse_files_container_output(&cat_container);
+
+    // the markdown files excluded are kept, along with the dirs containing them
+    assert!(files_map.contains_key("/test.md"));
+    assert!(files_map.contains_key("/subfolder/subexists.md"));
+    assert!(files_map.contains_key("/subfolder"));
+    assert!(files_map.contains_key("/.subhidden/test.md"));
+    assert!(!files_map.contains_key("/noextension"));
+    assert!(!files_map.contains_key("/.hidden.txt"));
+    assert!(!files_map.contains_key("/emptyfolder"));
+    assert_eq!(files_map.len(), 7);
+    Ok(())
+}
+
 #[test]
 fn calling_files_sync_and_fetch_with_nrsurl_and_nrs_update() -> Result<()> {
     let files_container_output = cmd!(
@@ -881,6 +1207,98 @@ fn calling_files_ls() -> Result<()> {
     Ok(())
 }
 
+// Test:  safe files ls <container> --sort=size --reverse --limit=3 --json
+//
+//    expected result: the 3 largest entries, listed from the largest,
+//                     and the URL of the version listed and the offset
+//                     to list the next ones from.
+#[test]
+fn calling_files_ls_sorted_with_limit() -> Result<()> {
+    let (files_container_xor, _processed_files) = upload_testfolder_trailing_slash()?;
+    let mut safeurl = safeurl_from(&files_container_xor)?;
+    safeurl.set_content_version(None);
+    let container_xorurl_no_version = safeurl.to_string();
+
+    // the files listed, and the URL and offset to list the next ones from
+    type LsPage = (
+        String,
+        BTreeMap<String, BTreeMap<String, String>>,
+        Option<BTreeMap<String, serde_json::Value>>,
+    );
+    let parse_page = |output: &str| -> Result<(Vec<String>, Option<(String, u64)>)> {
+        let (_xorurl, files_map, next_page): LsPage =
+            serde_json::from_str(output).map_err(|e| anyhow!(e.to_string()))?;
+        // the map keeps the order the entries are listed in
+        let mut names: Vec<String> = files_map.keys().cloned().collect();
+        names.sort_by_key(|name| output.find(&format!("\"{}\":", name)));
+        let next_page = next_page.map(|next_page| {
+            (
+                next_page["url"].as_str().unwrap_or_default().to_string(),
+                next_page["offset"].as_u64().unwrap_or_default(),
+            )
+        });
+        Ok((names, next_page))
+    };
+
+    let args = [
+        "files",
+        "ls",
+        &container_xorurl_no_version,
+        "--sort=size",
+        "--reverse",
+        "--limit=3",
+        "--json",
+    ];
+    let files_ls_output = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+    let (names, next_page) = parse_page(&files_ls_output)?;
+
+    // entries are listed from the largest: subfolder/ (27), .hidden.txt (23), test.md (12)
+    assert_eq!(names, vec!["subfolder/", ".hidden.txt", "test.md"]);
+
+    // the next entries are listed from the same version, even once updated
+    let (next_url, next_offset) = next_page.ok_or_else(|| anyhow!("No next page"))?;
+    assert_eq!(next_offset, 3);
+    assert_eq!(safeurl_from(&next_url)?.content_version(), Some(0));
+    let args = [
+        "files",
+        "add",
+        TEST_FILE,
+        &format!("{}/added.md", container_xorurl_no_version),
+    ];
+    safe_cmd_stdout(&args, Some(0))?;
+
+    let args = [
+        "files",
+        "ls",
+        &next_url,
+        "--sort=size",
+        "--reverse",
+        "--offset=3",
+        "--limit=3",
+        "--json",
+    ];
+    let files_ls_output = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+    let (names, next_page) = parse_page(&files_ls_output)?;
+    assert_eq!(names, vec![".subhidden/", "another.md", "noextension"]);
+    assert_eq!(next_page.map(|(_, offset)| offset), Some(6));
+
+    // the last page has no URL and offset to list the next entries from
+    let args = [
+        "files",
+        "ls",
+        &next_url,
+        "--offset=6",
+        "--limit=3",
+        "--json",
+    ];
+    let files_ls_output = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+    let (names, next_page) = parse_page(&files_ls_output)?;
+    assert_eq!(names, vec!["test.md"]);
+    assert_eq!(next_page, None);
+
+    Ok(())
+}
+
 // Test:  safe ls safe://<xorurl>/subfold
 //
 //    note: URL path is invalid.
@@ -1111,6 +1529,363 @@ fn calling_files_tree() -> Result<()> {
     Ok(())
 }
 
+// Test:  safe files find <src> --name '*.md' --min-size 10
+//         safe files find <src> --name subexists.md --exec safe cat {} ;
+//
+//    expected result: the URLs of the 3 markdown files of at least 10 bytes,
+//                     and then the content of the only file named subexists.md.
+#[test]
+fn calling_files_find() -> Result<()> {
+    let (files_container_xor, _processed_files) =
+        upload_testfolder_trailing_slash().map_err(|e| anyhow!(e.to_string()))?;
+    let mut safeurl = safeurl_from(&files_container_xor)?;
+    safeurl.set_content_version(None);
+    let container_xorurl_no_version = safeurl.to_string();
+
+    let args = [
+        "files",
+        "find",
+        &container_xorurl_no_version,
+        "--name",
+        "*.md",
+        "--min-size",
+        "10",
+    ];
+    let files_find_output = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+
+    let urls: Vec<&str> = files_find_output.lines().collect();
+    assert_eq!(urls.len(), 3);
+    assert!(urls.iter().all(|url| url.starts_with(SAFE_PROTOCOL)));
+    // the URLs found are pinned to the version searched
+    for url in urls.iter() {
+        assert_eq!(safeurl_from(url)?.content_version(), Some(0));
+    }
+    assert!(urls.iter().any(|url| url.contains("/.subhidden/test.md")));
+    assert!(urls
+        .iter()
+        .any(|url| url.contains("/subfolder/subexists.md")));
+    assert!(urls.iter().any(|url| url.contains("/test.md")));
+
+    let args = [
+        "files",
+        "find",
+        &files_container_xor,
+        "--name",
+        "subexists.md",
+        "--exec",
+        env!("CARGO_BIN_EXE_safe"),
+        "cat",
+        "{}",
+        ";",
+    ];
+    let files_find_output = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(files_find_output.trim_end(), "hello from a subfolder!");
+
+    // .hidden.txt is the only text file which is not markdown
+    let args = ["files", "find", &files_container_xor, "--type", "text/*"];
+    let files_find_output = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(files_find_output.lines().count(), 6);
+
+    let args = [
+        "files",
+        "find",
+        &files_container_xor,
+        "--type",
+        "text/plain",
+        "--json",
+    ];
+    let files_find_output = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+    let (_target, found): (String, BTreeMap<String, BTreeMap<String, String>>) =
+        serde_json::from_str(&files_find_output).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(found.len(), 1);
+    let (url, details) = found
+        .iter()
+        .next()
+        .ok_or_else(|| anyhow!("No file found"))?;
+    assert_eq!(safeurl_from(url)?.path_decoded()?, "/.hidden.txt");
+
+    // the file must be modified strictly after --newer, and before --older
+    let modified = details["modified"].as_str();
+    let find_text_file = |args: &[&str]| -> Result<usize> {
+        let mut find_args = vec![
+            "files",
+            "find",
+            files_container_xor.as_str(),
+            "--type",
+            "text/plain",
+        ];
+        find_args.extend_from_slice(args);
+        let output = safe_cmd_stdout(&find_args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+        Ok(output.lines().count())
+    };
+    assert_eq!(find_text_file(&["--newer", "2000-01-01"])?, 1);
+    assert_eq!(find_text_file(&["--older", "2000-01-01"])?, 0);
+    assert_eq!(find_text_file(&["--newer", modified])?, 0);
+    assert_eq!(find_text_file(&["--older", modified])?, 0);
+    assert_eq!(
+        find_text_file(&["--newer", "2000-01-01", "--older", "2999-01-01"])?,
+        1
+    );
+    Ok(())
+}
+
+// Test:  safe files du <src> --json
+//         safe files du ./testdata/ --json --dry-run
+//
+//    expected result: the same disk usage is reported for the FilesContainer
+//                     and for the local location it was uploaded from.
+#[test]
+fn calling_files_du() -> Result<()> {
+    let (files_container_xor, _processed_files) =
+        upload_testfolder_trailing_slash().map_err(|e| anyhow!(e.to_string()))?;
+
+    let args = ["files", "du", &files_container_xor, "--json"];
+    let files_du_output = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+    let report: serde_json::Value =
+        serde_json::from_str(&files_du_output).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(report["total"]["files"], 8);
+    assert_eq!(report["total"]["apparent_size"], 80);
+    assert_eq!(report["total"]["unique_size"], 80);
+    assert_eq!(report["dirs"]["/subfolder"]["files"], 2);
+    assert_eq!(report["dirs"]["/subfolder"]["apparent_size"], 27);
+
+    let args = ["files", "du", TEST_FOLDER, "--json", "--dry-run"];
+    let files_du_output = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+    let local_report: serde_json::Value =
+        serde_json::from_str(&files_du_output).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(local_report["total"], report["total"]);
+    assert_eq!(local_report["dirs"], report["dirs"]);
+    Ok(())
+}
+
+// Test:  safe files du <src> --json
+//    src has two files with the same content, but different media types.
+//
+//    expected result: the files share a blob, which is counted once.
+#[test]
+fn calling_files_du_with_duplicated_files() -> Result<()> {
+    let (tmp_dir, folder) = mk_emptyfolder("du").map_err(|e| anyhow!(e.to_string()))?;
+    fs::write(format!("{}copy.md", folder), "duplicated content")?;
+    fs::write(format!("{}copy.txt", folder), "duplicated content")?;
+    fs::write(format!("{}unique.md", folder), "unique")?;
+    let (files_container_xor, _processed_files, _path) =
+        upload_path(folder.trim_end_matches('/'), true).map_err(|e| anyhow!(e.to_string()))?;
+
+    let args = ["files", "du", &files_container_xor, "--json"];
+    let files_du_output = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+    let report: serde_json::Value =
+        serde_json::from_str(&files_du_output).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(report["total"]["files"], 3);
+    assert_eq!(report["total"]["apparent_size"], 42);
+    assert_eq!(report["total"]["unique_size"], 24);
+
+    let duplicated_blobs = report["duplicated_blobs"]
+        .as_array()
+        .ok_or_else(|| anyhow!("No duplicated blobs reported"))?;
+    assert_eq!(duplicated_blobs.len(), 1);
+    assert_eq!(duplicated_blobs[0]["size"], 18);
+    assert_eq!(
+        duplicated_blobs[0]["paths"],
+        serde_json::json!(["/copy.md", "/copy.txt"])
+    );
+
+    // cleanup
+    fs::remove_dir_all(&tmp_dir).map_err(|e| anyhow!(e.to_string()))?;
+    Ok(())
+}
+
+// Test:  safe files put ./testdata/test.md --media-type text/plain
+//         safe files put ./testdata/ --media-type text/plain
+//
+//    expected result: the file uploaded is assigned the media type passed,
+//                     which is not allowed when uploading a folder.
+#[test]
+fn calling_files_put_with_media_type() -> Result<()> {
+    let args = [
+        "files",
+        "put",
+        TEST_FILE,
+        "--media-type",
+        "text/plain",
+        "--json",
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+    let (container_xorurl, processed_files) = parse_files_put_or_sync_output(&stdout);
+    // the file is stored once, with the media type, and the FilesContainer
+    // created with it
+    assert_eq!(processed_files.len(), 1);
+    assert_eq!(processed_files[TEST_FILE].0, "+");
+    assert_eq!(safeurl_from(&container_xorurl)?.content_version(), Some(0));
+
+    let args = ["files", "ls", &container_xorurl, "--json"];
+    let files_ls_output = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+    let (_xorurl, files_map) = parse_files_container_output(&files_ls_output);
+    assert_eq!(files_map.len(), 1);
+    assert_eq!(files_map["test.md"]["type"], "text/plain");
+    assert_eq!(files_map["test.md"]["link"], processed_files[TEST_FILE].1);
+
+    let args = [
+        "files",
+        "put",
+        TEST_FOLDER,
+        "--recursive",
+        "--media-type",
+        "text/plain",
+    ];
+    let stderr = safe_cmd_stderr(&args, Some(1)).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(stderr.contains("--media-type can only be used when uploading a single file"));
+    Ok(())
+}
+
+// Test:  safe files put <folder> --recursive --no-sniff-media-type --json
+//         safe files put <folder> --recursive --json
+//         safe files sync <folder> <target> --recursive --json
+//         safe files add <file> <target> --json
+//
+//    expected result: the files without an extension are assigned the media
+//                     type of their content, unless sniffing is turned off,
+//                     and are all added with a single update.
+#[test]
+fn calling_files_put_sync_and_add_sniff_media_type() -> Result<()> {
+    let (tmp_dir, folder) = mk_emptyfolder("to_sniff").map_err(|e| anyhow!(e.to_string()))?;
+    let html = "<!DOCTYPE html>\n<html><body>Hello</body></html>\n";
+    fs::write(format!("{}page", folder), html)?;
+    fs::write(format!("{}empty", folder), "")?;
+    fs::write(format!("{}page.md", folder), "# Hello")?;
+
+    let args = [
+        "files",
+        "put",
+        &folder,
+        "--recursive",
+        "--no-sniff-media-type",
+        "--json",
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+    let (unsniffed_xorurl, _processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(safeurl_from(&unsniffed_xorurl)?.content_version(), Some(0));
+
+    // the files sniffed are added once the rest are uploaded
+    let args = ["files", "put", &folder, "--recursive", "--json"];
+    let stdout = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+    let (container_xorurl, processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(processed_files.len(), 3);
+    assert_eq!(processed_files[&format!("{}page", folder)].0, "+");
+    assert_eq!(safeurl_from(&container_xorurl)?.content_version(), Some(1));
+
+    let mut target = safeurl_from(&container_xorurl)?;
+    target.set_content_version(None);
+    fs::write(format!("{}readme", folder), "Read me")?;
+    fs::write(format!("{}licence", folder), "Some licence")?;
+    let args = [
+        "files",
+        "sync",
+        &folder,
+        &target.to_string(),
+        "--recursive",
+        "--json",
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+    let (synced_xorurl, processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(processed_files[&format!("{}readme", folder)].0, "+");
+    assert_eq!(processed_files[&format!("{}licence", folder)].0, "+");
+    assert_eq!(safeurl_from(&synced_xorurl)?.content_version(), Some(2));
+
+    let notes = format!("{}notes", folder);
+    fs::write(&notes, "Some notes")?;
+    let args = ["files", "add", &notes, &target.to_string(), "--json"];
+    let stdout = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+    let (added_xorurl, processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(processed_files[&notes].0, "+");
+    assert_eq!(safeurl_from(&added_xorurl)?.content_version(), Some(3));
+
+    let list_types = |url: &str| -> Result<BTreeMap<String, String>> {
+        let args = ["files", "ls", url, "--json"];
+        let stdout = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+        let (_xorurl, files_map) = parse_files_container_output(&stdout);
+        Ok(files_map
+            .into_iter()
+            .map(|(name, details)| (name, details["type"].clone()))
+            .collect())
+    };
+    let unsniffed_types = list_types(&unsniffed_xorurl)?;
+    let types = list_types(&target.to_string())?;
+    target.set_path("/page");
+    let page_cat = safe_cmd_stdout(&["cat", &target.to_string()], Some(0));
+
+    // cleanup
+    fs::remove_dir_all(&tmp_dir).map_err(|e| anyhow!(e.to_string()))?;
+
+    assert_eq!(unsniffed_types["page"], "Raw");
+    assert_eq!(types["page"], "text/html");
+    assert_eq!(types["notes"], "text/plain");
+    assert_eq!(types["readme"], "text/plain");
+    assert_eq!(types["licence"], "text/plain");
+    assert_eq!(types["empty"], "Raw");
+    assert_eq!(types["page.md"], "text/markdown");
+    assert_eq!(page_cat.map_err(|e| anyhow!(e.to_string()))?, html);
+    Ok(())
+}
+
+// Test:  safe files tree <src> --pattern '*.md' --du --json
+//         safe files tree <src> -L 1 --dirs-only
+//
+//    expected result: only the markdown files, with the aggregated size of
+//                     each directory, and then only the top level directories.
+#[test]
+fn calling_files_tree_with_pattern_and_level() -> Result<()> {
+    let (files_container_xor, _processed_files) =
+        upload_testfolder_trailing_slash().map_err(|e| anyhow!(e.to_string()))?;
+
+    let args = [
+        "files",
+        "tree",
+        &files_container_xor,
+        "--pattern",
+        "*.md",
+        "--du",
+        "--json",
+    ];
+    let files_tree_output = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+
+    let root = parse_files_tree_output(&files_tree_output);
+    assert_eq!(root["sub"].as_array().unwrap().len(), 5);
+    assert_eq!(root["du"], 57);
+    assert_eq!(root["sub"][0]["name"], ".subhidden");
+    assert_eq!(root["sub"][0]["fs_type"], "directory");
+    assert_eq!(root["sub"][1]["name"], "another.md");
+    assert_eq!(root["sub"][1]["fs_type"], "file");
+    assert_eq!(root["sub"][2]["name"], "emptyfolder");
+    assert!(root["sub"][2]["sub"].is_null());
+    assert_eq!(root["sub"][3]["name"], "subfolder");
+    assert_eq!(root["sub"][3]["du"], 27);
+    assert_eq!(root["sub"][4]["name"], "test.md");
+
+    let args = [
+        "files",
+        "tree",
+        &files_container_xor,
+        "-L",
+        "1",
+        "--dirs-only",
+    ];
+    let files_tree_output = safe_cmd_stdout(&args, Some(0)).map_err(|e| anyhow!(e.to_string()))?;
+
+    let should_match = format!(
+        "{}\n{}",
+        files_container_xor,
+        "\
+├── .subhidden
+├── emptyfolder
+└── subfolder
+
+3 directories, 0 files
+"
+    );
+    assert_eq!(files_tree_output, should_match);
+    Ok(())
+}
+
 // Test:  safe files tree <src>
 //    src is symlinks_test dir, put with trailing slash.
 //
@@ -1164,3 +1939,602 @@ fn calling_files_tree_with_symlinks() -> Result<()> {
 
     Ok(())
 }
+
+// Test:  safe files diff <local> <src>
+//    src is testdata dir, put with trailing slash.
+//    local is testdata dir, with and without trailing slash.
+//
+//    expected result: no differences found
+#[test]
+fn calling_files_diff_local_and_container() -> Result<()> {
+    let (files_container_xor, _processed_files) = upload_testfolder_trailing_slash()?;
+
+    let args = ["files", "diff", TEST_FOLDER, &files_container_xor];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    assert!(stdout.contains("No differences found"));
+
+    // the files of the local dir are at the root even without a trailing slash
+    let args = [
+        "files",
+        "diff",
+        TEST_FOLDER_NO_TRAILING_SLASH,
+        &files_container_xor,
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    assert!(stdout.contains("No differences found"));
+
+    Ok(())
+}
+
+// Test:  safe files diff <src1> <src2> --json
+//    src1 is testdata dir, put with trailing slash.
+//    src2 is testdata/subfolder dir, put with trailing slash.
+//
+//    expected result: entries only in src1 are reported as removed,
+//    and those only in src2 as added.
+#[test]
+fn calling_files_diff_containers() -> Result<()> {
+    let (from_xorurl, _processed_files) = upload_testfolder_trailing_slash()?;
+    let (to_xorurl, _processed_files, _path) = upload_path(TEST_FOLDER_SUBFOLDER, true)?;
+
+    let args = ["files", "diff", &from_xorurl, &to_xorurl, "--json"];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+
+    let (_from, _to, changes): (String, String, BTreeMap<String, (String, String)>) =
+        serde_json::from_str(&stdout).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(changes["/test.md"].0, "-");
+    assert_eq!(changes["/another.md"].0, "-");
+    assert_eq!(changes["/subexists.md"].0, "+");
+
+    Ok(())
+}
+
+#[test]
+fn calling_files_log() -> Result<()> {
+    let (container_xorurl, processed_files) = upload_testfolder_trailing_slash()?;
+    let _ = safe_cmd_stdout(
+        &[
+            "files",
+            "sync",
+            TEST_FOLDER_SUBFOLDER,
+            &container_xorurl,
+            "--recursive",
+        ],
+        Some(0),
+    )?;
+
+    let args = ["files", "log", &container_xorurl, "--json"];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+
+    let (_url, history): (String, Vec<serde_json::Value>) =
+        serde_json::from_str(&stdout).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0]["version"], 0);
+    assert_eq!(history[1]["version"], 1);
+
+    let first_changes: BTreeMap<String, (String, String)> =
+        serde_json::from_value(history[0]["changes"].clone())
+            .map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(first_changes.len(), processed_files.len());
+    assert!(first_changes.values().all(|(change, _)| change == "+"));
+
+    let second_changes: BTreeMap<String, (String, String)> =
+        serde_json::from_value(history[1]["changes"].clone())
+            .map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(second_changes.len(), 2);
+    assert_eq!(second_changes["/sub2.md"].0, "+");
+    assert_eq!(second_changes["/subexists.md"].0, "+");
+
+    Ok(())
+}
+
+#[test]
+fn calling_files_revert() -> Result<()> {
+    let (container_xorurl, _processed_files) = upload_testfolder_trailing_slash()?;
+    let _ = safe_cmd_stdout(
+        &[
+            "files",
+            "sync",
+            TEST_FOLDER_SUBFOLDER,
+            &container_xorurl,
+            "--recursive",
+        ],
+        Some(0),
+    )?;
+
+    let args = [
+        "files",
+        "revert",
+        &container_xorurl,
+        "--to-version",
+        "0",
+        "--json",
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (reverted_url, processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(processed_files.len(), 2);
+    assert_eq!(processed_files["/sub2.md"].0, "-");
+    assert_eq!(processed_files["/subexists.md"].0, "-");
+
+    // the latest version must now have the same content as the first one
+    let mut safeurl = safeurl_from(&reverted_url)?;
+    safeurl.set_content_version(Some(0));
+    let args = [
+        "files",
+        "diff",
+        &safeurl.to_string(),
+        &reverted_url,
+        "--json",
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (_from, _to, changes): (String, String, BTreeMap<String, (String, String)>) =
+        serde_json::from_str(&stdout).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(changes.is_empty());
+
+    // a single version was created by the revert
+    safeurl.set_content_version(Some(2));
+    assert_eq!(reverted_url, safeurl.to_string());
+    let args = ["files", "log", &container_xorurl, "--json"];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (_url, history): (String, Vec<serde_json::Value>) =
+        serde_json::from_str(&stdout).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(history.len(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn calling_files_revert_dry_run() -> Result<()> {
+    let (container_xorurl, _processed_files) = upload_testfolder_trailing_slash()?;
+    let _ = safe_cmd_stdout(
+        &[
+            "files",
+            "sync",
+            TEST_FOLDER_SUBFOLDER,
+            &container_xorurl,
+            "--recursive",
+        ],
+        Some(0),
+    )?;
+
+    let args = [
+        "files",
+        "revert",
+        &container_xorurl,
+        "--to-version",
+        "0",
+        "--dry-run",
+        "--json",
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (url, processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(processed_files["/sub2.md"].0, "-");
+
+    // the version reported is the one the revert would result in
+    let mut safeurl = safeurl_from(&container_xorurl)?;
+    safeurl.set_content_version(Some(2));
+    assert_eq!(url, safeurl.to_string());
+
+    // no new version must have been created
+    let args = ["files", "log", &container_xorurl, "--json"];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (_url, history): (String, Vec<serde_json::Value>) =
+        serde_json::from_str(&stdout).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(history.len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn calling_files_revert_directories() -> Result<()> {
+    let (container_xorurl, _processed_files) = upload_testfolder_trailing_slash()?;
+    let (tmp_dir, folder) = mk_emptyfolder("revert")?;
+
+    // remove all the content, including the directories, from the container
+    let mut safeurl = safeurl_from(&container_xorurl)?;
+    safeurl.set_content_version(None);
+    let _ = safe_cmd_stdout(
+        &[
+            "files",
+            "sync",
+            &folder,
+            &safeurl.to_string(),
+            "--recursive",
+            "--delete",
+        ],
+        Some(0),
+    )?;
+
+    let args = [
+        "files",
+        "revert",
+        &container_xorurl,
+        "--to-version",
+        "0",
+        "--json",
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (reverted_url, processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(processed_files.len(), EXPECT_TESTDATA_PUT_CNT);
+    assert_eq!(processed_files["/subfolder"].0, "+");
+    assert_eq!(processed_files["/emptyfolder"].0, "+");
+    assert_eq!(processed_files["/test.md"].0, "+");
+
+    // all the entries were restored by a single new version
+    let mut safeurl = safeurl_from(&reverted_url)?;
+    assert_eq!(safeurl.content_version(), Some(2));
+    safeurl.set_content_version(Some(0));
+    let args = [
+        "files",
+        "diff",
+        &safeurl.to_string(),
+        &reverted_url,
+        "--json",
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (_from, _to, changes): (String, String, BTreeMap<String, (String, String)>) =
+        serde_json::from_str(&stdout).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(changes.is_empty());
+
+    fs::remove_dir_all(&tmp_dir).map_err(|e| anyhow!(e.to_string()))?;
+    Ok(())
+}
+
+#[test]
+fn calling_files_cp() -> Result<()> {
+    let (container_xorurl, _processed_files) = upload_testfolder_trailing_slash()?;
+
+    let mut safeurl = safeurl_from(&container_xorurl)?;
+    safeurl.set_path("/test.md");
+    let source = safeurl.to_string();
+    safeurl.set_path("/copies/");
+    let dest = safeurl.to_string();
+
+    let args = ["files", "cp", &source, &dest, "--json"];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (_url, processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(processed_files["/copies/test.md"].0, "+");
+
+    // the copy links to the same content as the original file
+    safeurl.set_path("/copies/test.md");
+    let copy_cat = safe_cmd_stdout(&["cat", &safeurl.to_string()], Some(0))?;
+    let original_cat = safe_cmd_stdout(&["cat", &source], Some(0))?;
+    assert_eq!(copy_cat, original_cat);
+
+    Ok(())
+}
+
+#[test]
+fn calling_files_mv_recursive_across_containers() -> Result<()> {
+    let (from_xorurl, _processed_files) = upload_testfolder_trailing_slash()?;
+    let (to_xorurl, _processed_files) = upload_testfolder_trailing_slash()?;
+
+    let mut safeurl = safeurl_from(&from_xorurl)?;
+    safeurl.set_path("/subfolder");
+    let source = safeurl.to_string();
+    let mut safeurl = safeurl_from(&to_xorurl)?;
+    safeurl.set_path("/moved");
+    let dest = safeurl.to_string();
+
+    // a directory can only be moved recursively
+    let args = ["files", "mv", &source, &dest, "--json"];
+    let _ = safe_cmd_stderr(&args, Some(1))?;
+
+    let args = ["files", "mv", &source, &dest, "--recursive", "--json"];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (_url, processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(processed_files["/moved/sub2.md"].0, "+");
+    assert_eq!(processed_files["/moved/subexists.md"].0, "+");
+    assert_eq!(processed_files["/subfolder/sub2.md"].0, "-");
+    assert_eq!(processed_files["/subfolder/subexists.md"].0, "-");
+
+    let _ = safe_cmd_stderr(&["cat", &format!("{}/sub2.md", source)], Some(1))?;
+
+    Ok(())
+}
+
+#[test]
+fn calling_files_mv_recursive_within_container() -> Result<()> {
+    let (container_xorurl, _processed_files) = upload_testfolder_trailing_slash()?;
+
+    let mut safeurl = safeurl_from(&container_xorurl)?;
+    safeurl.set_content_version(None);
+    safeurl.set_path("/subfolder");
+    let source = safeurl.to_string();
+    safeurl.set_path("/moved");
+    let dest = safeurl.to_string();
+
+    let args = ["files", "mv", &source, &dest, "--recursive", "--json"];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (moved_url, processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(processed_files["/moved/sub2.md"].0, "+");
+    assert_eq!(processed_files["/moved/subexists.md"].0, "+");
+    assert_eq!(processed_files["/subfolder/sub2.md"].0, "-");
+    assert_eq!(processed_files["/subfolder/subexists.md"].0, "-");
+
+    // the entries were copied, and the source removed, by a single new version
+    assert_eq!(safeurl_from(&moved_url)?.content_version(), Some(1));
+    let args = ["files", "log", &container_xorurl, "--json"];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (_url, history): (String, Vec<serde_json::Value>) =
+        serde_json::from_str(&stdout).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(history.len(), 2);
+
+    let moved_cat = safe_cmd_stdout(&["cat", &format!("{}/sub2.md", dest)], Some(0))?;
+    safeurl.set_content_version(Some(0));
+    safeurl.set_path("/subfolder/sub2.md");
+    let original_cat = safe_cmd_stdout(&["cat", &safeurl.to_string()], Some(0))?;
+    assert_eq!(moved_cat, original_cat);
+    let _ = safe_cmd_stderr(&["cat", &format!("{}/sub2.md", source)], Some(1))?;
+
+    Ok(())
+}
+
+#[test]
+fn calling_files_mv_into_itself() -> Result<()> {
+    let (container_xorurl, _processed_files) = upload_testfolder_trailing_slash()?;
+
+    let mut safeurl = safeurl_from(&container_xorurl)?;
+    safeurl.set_content_version(None);
+    safeurl.set_path("/subfolder");
+    let source = safeurl.to_string();
+    safeurl.set_path("/subfolder/nested/");
+    let dest = safeurl.to_string();
+
+    let args = ["files", "mv", &source, &dest, "--recursive", "--json"];
+    let stderr = safe_cmd_stderr(&args, Some(1))?;
+    assert!(stderr.contains("into itself"));
+
+    // the source was left untouched
+    let _ = safe_cmd_stdout(&["cat", &format!("{}/sub2.md", source)], Some(0))?;
+    let args = ["files", "log", &container_xorurl, "--json"];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (_url, history): (String, Vec<serde_json::Value>) =
+        serde_json::from_str(&stdout).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(history.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn calling_files_cp_recursive_with_empty_dirs() -> Result<()> {
+    let (container_xorurl, _processed_files) = upload_testfolder_trailing_slash()?;
+
+    let mut safeurl = safeurl_from(&container_xorurl)?;
+    safeurl.set_content_version(None);
+    safeurl.set_path("/newfolder/empty");
+    let _ = safe_cmd_stdout(&["files", "mkdir", &safeurl.to_string()], Some(0))?;
+    safeurl.set_path("/newfolder");
+    let source = safeurl.to_string();
+    safeurl.set_path("/copied");
+    let dest = safeurl.to_string();
+
+    let args = ["files", "cp", &source, &dest, "--recursive", "--json"];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (_url, processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(processed_files["/copied/empty"].0, "+");
+
+    Ok(())
+}
+
+#[test]
+fn calling_files_mkdir() -> Result<()> {
+    let (container_xorurl, _processed_files) = upload_testfolder_trailing_slash()?;
+
+    let mut safeurl = safeurl_from(&container_xorurl)?;
+    safeurl.set_path("/newfolder/empty");
+    let args = ["files", "mkdir", &safeurl.to_string(), "--json"];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (new_url, processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(processed_files["/newfolder"].0, "+");
+    assert_eq!(processed_files["/newfolder/empty"].0, "+");
+
+    // the empty folder is listed even though it has no files
+    let args = ["files", "ls", &new_url, "--json"];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (_xorurl, files_map) = parse_files_container_output(&stdout);
+    assert!(files_map.contains_key("newfolder/"));
+
+    // creating it again fails as it already exists
+    let args = ["files", "mkdir", &safeurl.to_string()];
+    let _ = safe_cmd_stderr(&args, Some(1))?;
+
+    // nor can it be created within a file
+    safeurl.set_path("/test.md/sub");
+    let args = ["files", "mkdir", &safeurl.to_string()];
+    let stderr = safe_cmd_stderr(&args, Some(1))?;
+    assert!(stderr.contains("\"/test.md\", which is not a directory"));
+    safeurl.set_path("/test.md/sub/nested");
+    let args = ["files", "mkdir", &safeurl.to_string()];
+    let _ = safe_cmd_stderr(&args, Some(1))?;
+
+    Ok(())
+}
+
+#[test]
+fn calling_files_verify() -> Result<()> {
+    let (tmp_dir, folder) = mk_emptyfolder("verify")?;
+    let folder_path = Path::new(&folder);
+    fs::write(folder_path.join("same.txt"), "same").map_err(|e| anyhow!(e.to_string()))?;
+    fs::write(folder_path.join("changed.txt"), "original").map_err(|e| anyhow!(e.to_string()))?;
+    fs::write(folder_path.join("removed.txt"), "removed").map_err(|e| anyhow!(e.to_string()))?;
+
+    let (container_xorurl, _processed_files, _path) = upload_path(&folder, false)?;
+
+    let args = [
+        "files",
+        "verify",
+        &folder,
+        &container_xorurl,
+        "-r",
+        "--json",
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(report["missing"], serde_json::json!([]));
+    assert_eq!(report["extra"], serde_json::json!([]));
+    assert_eq!(report["different"], serde_json::json!([]));
+
+    fs::write(folder_path.join("changed.txt"), "modified").map_err(|e| anyhow!(e.to_string()))?;
+    fs::remove_file(folder_path.join("removed.txt")).map_err(|e| anyhow!(e.to_string()))?;
+    fs::write(folder_path.join("added.txt"), "added").map_err(|e| anyhow!(e.to_string()))?;
+
+    let stdout = safe_cmd_stdout(&args, Some(1))?;
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(report["missing"], serde_json::json!(["/removed.txt"]));
+    assert_eq!(report["extra"], serde_json::json!(["/added.txt"]));
+    assert_eq!(report["different"], serde_json::json!(["/changed.txt"]));
+
+    fs::remove_dir_all(&tmp_dir).map_err(|e| anyhow!(e.to_string()))?;
+
+    Ok(())
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn calling_files_sync_watch() -> Result<()> {
+    let (tmp_dir, folder) = mk_emptyfolder("watch")?;
+    let file_path = Path::new(&folder).join("watched.txt");
+    fs::write(&file_path, "initial content").map_err(|e| anyhow!(e.to_string()))?;
+    let (container_xorurl, _processed_files, _) = upload_path(folder.trim_end_matches('/'), true)?;
+    let mut safeurl = safeurl_from(&container_xorurl)?;
+    safeurl.set_content_version(None);
+    let target = safeurl.to_string();
+
+    let mut watch = Command::new(env!("CARGO_BIN_EXE_safe"))
+        .args(&["files", "sync", &folder, &target, "--recursive", "--watch"])
+        .spawn()
+        .map_err(|e| anyhow!(e.to_string()))?;
+
+    // give the watcher time to start before changing the file
+    std::thread::sleep(std::time::Duration::from_secs(5));
+    let new_content = get_random_nrs_string();
+    fs::write(&file_path, &new_content).map_err(|e| anyhow!(e.to_string()))?;
+
+    safeurl.set_path("/watched.txt");
+    let file_url = safeurl.to_string();
+    let mut synced = false;
+    for _ in 0..60 {
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        if safe_cmd_stdout(&["cat", &file_url, "--no-cache"], Some(0))? == new_content {
+            synced = true;
+            break;
+        }
+    }
+
+    let _ = watch.kill();
+    let _ = watch.wait();
+    fs::remove_dir_all(&tmp_dir).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(synced);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn calling_files_mount_with_invalid_mountpoint() -> Result<()> {
+    let (container_xorurl, _processed_files) = upload_testfolder_trailing_slash()?;
+
+    let args = ["files", "mount", &container_xorurl, "/no/such/mountpoint"];
+    let stderr = safe_cmd_stderr(&args, Some(1))?;
+    assert!(stderr.contains("Mount point is not an existing directory"));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn calling_files_mount_and_read_files() -> Result<()> {
+    // FUSE may not be available, e.g. within a container
+    let has_fusermount = Command::new("fusermount")
+        .arg("-V")
+        .output()
+        .map_or(false, |output| output.status.success());
+    if !Path::new("/dev/fuse").exists() || !has_fusermount {
+        return Ok(());
+    }
+
+    let (container_xorurl, _processed_files) = upload_testfolder_trailing_slash()?;
+    let (tmp_dir, mountpoint) = mk_emptyfolder("mount")?;
+    let mut mount = Command::new(env!("CARGO_BIN_EXE_safe"))
+        .args(&["files", "mount", &container_xorurl, &mountpoint])
+        .spawn()
+        .map_err(|e| anyhow!(e.to_string()))?;
+
+    let mounted_file = Path::new(&mountpoint).join("subfolder").join("sub2.md");
+    let mut attempts = 0;
+    while !mounted_file.exists() && attempts < 60 {
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        attempts += 1;
+    }
+    let content = fs::read(&mounted_file).map_err(|e| anyhow!(e.to_string()));
+    let original = fs::read("./testdata/subfolder/sub2.md").map_err(|e| anyhow!(e.to_string()));
+
+    let _ = Command::new("fusermount")
+        .args(&["-u", &mountpoint])
+        .status();
+    let status = mount.wait().map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(content?, original?);
+    assert!(status.success());
+
+    fs::remove_dir_all(&tmp_dir).map_err(|e| anyhow!(e.to_string()))?;
+    Ok(())
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn calling_files_mount_and_read_added_file() -> Result<()> {
+    // FUSE may not be available, e.g. within a container
+    let has_fusermount = Command::new("fusermount")
+        .arg("-V")
+        .output()
+        .map_or(false, |output| output.status.success());
+    if !Path::new("/dev/fuse").exists() || !has_fusermount {
+        return Ok(());
+    }
+
+    let (container_xorurl, _processed_files) = upload_testfolder_trailing_slash()?;
+    let mut safeurl = safeurl_from(&container_xorurl)?;
+    safeurl.set_content_version(None);
+    let latest_url = safeurl.to_string();
+
+    let (tmp_dir, mountpoint) = mk_emptyfolder("mount_latest")?;
+    let mut mount = Command::new(env!("CARGO_BIN_EXE_safe"))
+        .args(&["files", "mount", &latest_url, &mountpoint])
+        .spawn()
+        .map_err(|e| anyhow!(e.to_string()))?;
+
+    let mut attempts = 0;
+    while !Path::new(&mountpoint).join("test.md").exists() && attempts < 60 {
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        attempts += 1;
+    }
+
+    // the mount follows the latest version, thus the file added is found
+    // once the FilesMap is refreshed
+    safeurl.set_path("/added.md");
+    let args = ["files", "add", "./testdata/test.md", &safeurl.to_string()];
+    let added = safe_cmd_stdout(&args, Some(0));
+
+    let mounted_file = Path::new(&mountpoint).join("added.md");
+    let mut attempts = 0;
+    while !mounted_file.exists() && attempts < 60 {
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        attempts += 1;
+    }
+    let content = fs::read(&mounted_file).map_err(|e| anyhow!(e.to_string()));
+    let original = fs::read("./testdata/test.md").map_err(|e| anyhow!(e.to_string()));
+
+    let _ = Command::new("fusermount")
+        .args(&["-u", &mountpoint])
+        .status();
+    let status = mount.wait().map_err(|e| anyhow!(e.to_string()))?;
+    added?;
+    assert_eq!(content?, original?);
+    assert!(status.success());
+
+    fs::remove_dir_all(&tmp_dir).map_err(|e| anyhow!(e.to_string()))?;
+    Ok(())
+}
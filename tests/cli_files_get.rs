@@ -18,11 +18,14 @@ const EXISTS_OVERWRITE: &str = "overwrite";
 const EXISTS_PRESERVE: &str = "preserve";
 const PROGRESS_NONE: &str = "none";
 
+const JOURNAL_FILE_NAME: &str = ".safe-get-state";
+
 use anyhow::{anyhow, bail, Result};
 use sn_cmd_test_utilities::util::{
     can_write_symlinks, create_and_upload_test_absolute_symlinks_folder, create_nrs_link,
-    create_symlink, digest_file, get_random_nrs_string, parse_files_put_or_sync_output,
-    safe_cmd_stdout, safeurl_from, str_to_sha3_256, sum_tree, test_symlinks_are_valid,
+    create_symlink, digest_file, get_random_nrs_string, mk_emptyfolder,
+    parse_files_container_output, parse_files_put_or_sync_output, safe_cmd, safe_cmd_stderr,
+    safe_cmd_stdout, safeurl_from, str_to_sha3_256, sum_tree, test_symlinks_are_valid, upload_path,
     upload_test_symlinks_folder, upload_testfolder_no_trailing_slash,
     upload_testfolder_trailing_slash, TEST_FOLDER,
 };
@@ -1168,6 +1171,879 @@ fn files_get_symlinks_after_sync() -> Result<()> {
     Ok(())
 }
 
+// Test:  safe files get <src> /tmp/symlinks_follow --symlinks=follow --external-symlinks=skip
+//    src is xor-url generated from `safe files put ./test_symlinks`
+//    dest does not exist
+//
+//    expected result: symlinks to files and dirs within the tree are
+//    retrieved as files and dirs, and those escaping it are skipped.
+//    With --external-symlinks=error the command fails.
+#[test]
+fn files_get_symlinks_follow() -> Result<()> {
+    // Bail if test_symlinks not valid.
+    if !test_symlinks_are_valid()? {
+        return Ok(());
+    }
+
+    let (files_container_xor, _processed_files, path) = upload_test_symlinks_folder(true)?;
+
+    let src = source_path(&files_container_xor, &[])?;
+    let dest = dest_dir(&["symlinks_follow"]);
+    remove_dest(&dest)?;
+
+    let args = [
+        "files",
+        "get",
+        &src,
+        &dest,
+        "--exists=overwrite",
+        "--progress=none",
+        "--external-symlinks=error",
+    ];
+    let stderr = safe_cmd_stderr(&args, Some(1))?;
+    assert!(stderr.contains("external symlink"));
+
+    let args = [
+        "files",
+        "get",
+        &src,
+        &dest,
+        "--exists=overwrite",
+        "--progress=none",
+        "--symlinks=follow",
+        "--external-symlinks=skip",
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    assert!(stdout.contains("skipped symlinks:"));
+
+    let is_symlink = |p: &Path| {
+        fs::symlink_metadata(p)
+            .map(|m| m.file_type().is_symlink())
+            .unwrap_or(false)
+    };
+    let dest_path = Path::new(&dest);
+    let file_link = dest_path.join("file_link");
+    assert!(file_link.is_file() && !is_symlink(&file_link));
+    assert_eq!(
+        digest_file(&file_link.display().to_string())?,
+        digest_file(&Path::new(&path).join("realfile.txt").display().to_string())?
+    );
+    let dir_link = dest_path.join("dir_link");
+    assert!(dir_link.is_dir() && !is_symlink(&dir_link));
+    assert!(dir_link.join("readme.md").is_file());
+    assert!(!dest_path.join("dir_outside").exists());
+    assert!(!dest_path.join("file_outside").exists());
+
+    remove_dest(&dest)?;
+
+    // absolute symlinks are external, wherever their target is
+    if !can_write_symlinks() {
+        return Ok(());
+    }
+    let (files_container_xor, _processed_files, tmp_dir, _path) =
+        create_and_upload_test_absolute_symlinks_folder(true)?;
+    let src = source_path(&files_container_xor, &[])?;
+
+    let args = [
+        "files",
+        "get",
+        &src,
+        &dest,
+        "--exists=overwrite",
+        "--progress=none",
+        "--external-symlinks=error",
+    ];
+    let stderr = safe_cmd_stderr(&args, Some(1))?;
+    assert!(stderr.contains("absolute symlink"));
+
+    let args = [
+        "files",
+        "get",
+        &src,
+        &dest,
+        "--exists=overwrite",
+        "--progress=none",
+        "--symlinks=follow",
+        "--external-symlinks=skip",
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    assert!(stdout.contains("skipped symlinks: 2"));
+    assert!(dest_path.join("file.txt").is_file());
+    assert!(dest_path.join("subdir").is_dir());
+    assert!(fs::symlink_metadata(dest_path.join("absolute_link_to_file.txt")).is_err());
+    assert!(fs::symlink_metadata(dest_path.join("absolute_link_to_dir")).is_err());
+
+    remove_dest(&dest)?;
+    remove_dest(&tmp_dir)?;
+
+    Ok(())
+}
+
+// ----------------------------------------
+// Concurrency Tests
+// ----------------------------------------
+
+// Test:  safe files get <url>/<file> /tmp/newname --jobs=N
+//    src is a file bigger than a single download range
+//    dest does not exist
+//
+//    expected result: file matches /tmp/newname, regardless of
+//    the number of ranges downloaded concurrently.
+#[test]
+fn files_get_multi_range_file_concurrently() -> Result<()> {
+    let (tmp_dir, folder) = mk_emptyfolder("multi_range")?;
+    let filepath = Path::new(&folder).join("big.txt");
+    let content: String = (0..200_000).map(|i| format!("line {:08}\n", i)).collect();
+    fs::write(&filepath, content).map_err(|e| anyhow!(e.to_string()))?;
+
+    let (files_container_xor, _processed_files, _path) = upload_path(&folder, false)?;
+
+    for jobs in &["1", "8"] {
+        let src = source_path(&files_container_xor, &["big.txt"])?;
+        let dest = dest_dir(&[NEWNAME]);
+
+        remove_dest(&dest)?;
+
+        let args = [
+            "files",
+            "get",
+            &src,
+            &dest,
+            "--exists=overwrite",
+            "--progress=none",
+            "--jobs",
+            jobs,
+        ];
+        safe_cmd_stdout(&args, Some(0))?;
+
+        assert_eq!(
+            digest_file(&filepath.display().to_string())?,
+            digest_file(&dest)?
+        );
+    }
+
+    remove_dest(&tmp_dir)?;
+
+    Ok(())
+}
+
+// ----------------------------------------
+// Filter Tests
+// ----------------------------------------
+
+// Test:  safe files get ./testdata /tmp/testdata --exclude=*.md
+//    src is a dir
+//    dest exists, and is a dir
+//
+//    expected result: only files not matching *.md are retrieved
+//    into /tmp/testdata/testdata
+#[test]
+fn files_get_exclude_glob() -> Result<()> {
+    let (files_container_xor, _processed_files) = upload_testfolder_no_trailing_slash()?;
+
+    let src = source_path(&files_container_xor, &[TESTDATA])?;
+    let dest = dest_dir(&[TESTDATA]);
+    let final_dest = dest_dir(&[TESTDATA, TESTDATA]);
+
+    remove_dest(&dest)?;
+    fs::create_dir_all(&dest).map_err(|e| anyhow!(e.to_string()))?;
+
+    let args = [
+        "files",
+        "get",
+        &src,
+        &dest,
+        "--exists=overwrite",
+        "--progress=none",
+        "--exclude=*.md",
+    ];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    assert!(stdout.contains("filtered out:"));
+
+    let final_path = Path::new(&final_dest);
+    assert!(final_path.join(NOEXTENSION).is_file());
+    assert!(!final_path.join("test.md").exists());
+    assert!(!final_path.join(SUBFOLDER).join("subexists.md").exists());
+
+    Ok(())
+}
+
+// ----------------------------------------
+// Preserve Tests
+// ----------------------------------------
+
+// Test:  safe files get <url>/run.sh /tmp/newname --preserve
+//    src is an executable file
+//    dest does not exist
+//
+//    expected result: /tmp/newname has the same mode and
+//    modification time as the original file.
+#[cfg(unix)]
+#[test]
+fn files_get_preserve_mode_and_mtime() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let (tmp_dir, folder) = mk_emptyfolder("preserve")?;
+    let filepath = Path::new(&folder).join("run.sh");
+    fs::write(&filepath, "#!/bin/sh\necho hello\n").map_err(|e| anyhow!(e.to_string()))?;
+    fs::set_permissions(&filepath, fs::Permissions::from_mode(0o755))
+        .map_err(|e| anyhow!(e.to_string()))?;
+    let src_meta = fs::metadata(&filepath).map_err(|e| anyhow!(e.to_string()))?;
+
+    let (files_container_xor, _processed_files, _path) = upload_path(&folder, false)?;
+
+    let src = source_path(&files_container_xor, &["run.sh"])?;
+    let dest = dest_dir(&[NEWNAME]);
+
+    remove_dest(&dest)?;
+
+    let args = [
+        "files",
+        "get",
+        &src,
+        &dest,
+        "--exists=overwrite",
+        "--progress=none",
+        "--preserve",
+    ];
+    safe_cmd_stdout(&args, Some(0))?;
+
+    let dest_meta = fs::metadata(&dest).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(
+        src_meta.permissions().mode(),
+        dest_meta.permissions().mode()
+    );
+    assert_eq!(
+        src_meta
+            .modified()
+            .map_err(|e| anyhow!(e.to_string()))?
+            .duration_since(UNIX_EPOCH)?
+            .as_secs(),
+        dest_meta
+            .modified()
+            .map_err(|e| anyhow!(e.to_string()))?
+            .duration_since(UNIX_EPOCH)?
+            .as_secs()
+    );
+
+    remove_dest(&dest)?;
+    remove_dest(&tmp_dir)?;
+
+    Ok(())
+}
+
+// Test:  safe files get <url> /tmp/empty_dirs
+//    src is a container url with empty directories, one of
+//    them uploaded with files put and another one created
+//    with files mkdir.
+//
+//    expected result: both empty directories are created in dest.
+#[test]
+fn files_get_empty_dirs() -> Result<()> {
+    let (tmp_dir, folder) = mk_emptyfolder("empty_dirs_src")?;
+    fs::create_dir_all(Path::new(&folder).join("uploaded").join("empty"))
+        .map_err(|e| anyhow!(e.to_string()))?;
+    fs::write(Path::new(&folder).join("file.txt"), "hello empty dirs")
+        .map_err(|e| anyhow!(e.to_string()))?;
+
+    let (files_container_xor, _processed_files, _path) = upload_path(&folder, true)?;
+
+    let mut safeurl = safeurl_from(&files_container_xor)?;
+    safeurl.set_path("/created/empty");
+    safeurl.set_content_version(None);
+    safe_cmd_stdout(&["files", "mkdir", &safeurl.to_string()], Some(0))?;
+    safeurl.set_path("");
+
+    let dest = dest_dir(&["empty_dirs"]);
+    remove_dest(&dest)?;
+
+    files_get(
+        &safeurl.to_string(),
+        Some(&dest),
+        Some(EXISTS_OVERWRITE),
+        Some(PROGRESS_NONE),
+        Some(0),
+    )?;
+
+    assert!(Path::new(&dest).join("uploaded").join("empty").is_dir());
+    assert!(Path::new(&dest).join("created").join("empty").is_dir());
+    assert!(Path::new(&dest).join("file.txt").is_file());
+
+    remove_dest(&dest)?;
+    remove_dest(&tmp_dir)?;
+
+    Ok(())
+}
+
+// Test:  safe files get <url> /tmp/verify --verify
+//    src is a dir
+//
+//    expected result:
+//        exit code = 0 and
+//        the retrieved files match the uploaded ones
+#[test]
+fn files_get_verify() -> Result<()> {
+    let (files_container_xor, _processed_files) = upload_testfolder_no_trailing_slash()?;
+
+    let src = source_path(&files_container_xor, &[TESTDATA])?;
+    let dest = dest_dir(&["verify"]);
+    remove_dest(&dest)?;
+
+    let output = safe_cmd_stdout(
+        &[
+            "files",
+            "get",
+            &src,
+            &dest,
+            "--exists=overwrite",
+            "--progress=none",
+            "--verify",
+        ],
+        Some(0),
+    )?;
+
+    assert!(!output.contains("failed"));
+    assert_eq!(sum_tree(TEST_FOLDER)?, sum_tree(&dest)?);
+
+    remove_dest(&dest)?;
+
+    Ok(())
+}
+
+// Test:  safe files get <url> /tmp/verify_corrupted --verify
+//    src is a dir whose file's content was corrupted in the local cache
+//
+//    expected result:
+//        exit code = 1 and
+//        the file is listed as failed, and not counted as retrieved, and
+//        a new retrieval gets the content from the network again
+#[test]
+fn files_get_verify_corrupted_content() -> Result<()> {
+    // a file with random content, so its blob is not shared with other tests
+    let (tmp_dir, folder) = mk_emptyfolder("verify_src")?;
+    let content = get_random_nrs_string();
+    let filepath = Path::new(&folder).join("random.txt");
+    fs::write(&filepath, &content).map_err(|e| anyhow!(e.to_string()))?;
+    let (files_container_xor, processed_files, _) =
+        upload_path(folder.trim_end_matches('/'), true)?;
+    let link = &processed_files[&filepath.display().to_string()].1;
+
+    // a first retrieval stores the content in the local cache
+    let dest = dest_dir(&["verify_corrupted"]);
+    remove_dest(&dest)?;
+    let args = [
+        "files",
+        "get",
+        &files_container_xor,
+        &dest,
+        "--exists=overwrite",
+        "--progress=none",
+    ];
+    let _ = safe_cmd_stdout(&args, Some(0))?;
+
+    // corrupt the cached blob, keeping its size
+    let stdout = safe_cmd_stdout(&["cache", "stats", "--json"], Some(0))?;
+    let stats: serde_json::Value =
+        serde_json::from_str(&stdout).map_err(|e| anyhow!(e.to_string()))?;
+    let location = stats["location"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Missing cache location"))?;
+    let key: String = safeurl_from(link)?
+        .xorname()
+        .0
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let blob_path = Path::new(location).join(&key);
+    fs::write(&blob_path, "x".repeat(content.len())).map_err(|e| anyhow!(e.to_string()))?;
+
+    let args = [
+        "files",
+        "get",
+        &files_container_xor,
+        &dest,
+        "--exists=overwrite",
+        "--progress=none",
+        "--verify",
+    ];
+    let output = safe_cmd_stdout(&args, Some(1))?;
+    assert!(output.contains("Retrieved 0 files"));
+    assert!(output.contains("failed: 1"));
+    assert!(output.contains("random.txt"));
+
+    // the corrupted blob is evicted from the cache once the verification
+    // fails, thus a new retrieval fetches the content from the network again
+    assert!(!blob_path.exists());
+    let output = safe_cmd_stdout(&args, Some(0))?;
+    assert!(output.contains("Retrieved 1 file"));
+    assert!(!output.contains("failed"));
+    // and only the verified content is stored in the cache
+    assert!(blob_path.exists());
+    let cat = safe_cmd_stdout(&["cat", link], Some(0))?;
+    assert_eq!(cat, content);
+
+    remove_dest(&dest)?;
+    remove_dest(&tmp_dir)?;
+
+    Ok(())
+}
+
+// Test:  safe files get <url> --archive /tmp/archive.<ext>
+//        safe files put /tmp/archive.<ext> --archive
+//    src is a dir, retrieved into an archive which is then uploaded
+//
+//    expected result:
+//        the files retrieved from the new container match the uploaded ones
+#[test]
+fn files_get_archive_and_put_archive() -> Result<()> {
+    let (files_container_xor, _processed_files) = upload_testfolder_trailing_slash()?;
+
+    for ext in &["tar", "tar.gz", "zip"] {
+        let archive = dest_dir(&[&format!("archive.{}", ext)]);
+        remove_dest(&archive)?;
+
+        let args = [
+            "files",
+            "get",
+            &files_container_xor,
+            "--archive",
+            &archive,
+            "--progress=none",
+        ];
+        safe_cmd_stdout(&args, Some(0))?;
+        assert!(Path::new(&archive).is_file());
+
+        let args = ["files", "put", &archive, "--archive", "--json"];
+        let stdout = safe_cmd_stdout(&args, Some(0))?;
+        let (new_container_xor, _processed_files) = parse_files_put_or_sync_output(&stdout);
+
+        let dest = dest_dir(&[&format!("from_archive_{}", ext.replace('.', "_"))]);
+        remove_dest(&dest)?;
+        files_get(
+            &new_container_xor,
+            Some(&dest),
+            Some(EXISTS_OVERWRITE),
+            Some(PROGRESS_NONE),
+            Some(0),
+        )?;
+        assert_eq!(sum_tree(TEST_FOLDER)?, sum_tree(&dest)?);
+
+        remove_dest(&archive)?;
+        remove_dest(&dest)?;
+    }
+
+    Ok(())
+}
+
+// Test:  safe files get <url> --archive /tmp/symlinks_archive.<ext> --jobs 2
+//        safe files put /tmp/symlinks_archive.<ext> --archive
+//    src is xor-url generated from `safe files put ./test_symlinks`
+//
+//    expected result:
+//        the symlinks of the new container have the same targets and
+//        target types, and the files retrieved from it match ./test_symlinks
+#[test]
+fn files_get_archive_symlinks() -> Result<()> {
+    if !test_symlinks_are_valid()? || !can_write_symlinks() {
+        return Ok(());
+    }
+
+    let (files_container_xor, _processed_files, path) = upload_test_symlinks_folder(true)?;
+    let symlinks_of = |url: &str| -> Result<Vec<(String, String, String)>> {
+        let stdout = safe_cmd_stdout(&["cat", url, "--json"], Some(0))?;
+        let (_url, files_map) = parse_files_container_output(&stdout);
+        Ok(files_map
+            .into_iter()
+            .filter(|(_, details)| details["type"] == "inode/symlink")
+            .map(|(path, details)| {
+                let attr = |name: &str| details.get(name).cloned().unwrap_or_default();
+                (path, attr("symlink_target"), attr("symlink_target_type"))
+            })
+            .collect())
+    };
+    let symlinks = symlinks_of(&files_container_xor)?;
+    assert!(symlinks
+        .iter()
+        .any(|(_, _, target_type)| target_type == "dir"));
+
+    for ext in &["tar", "zip"] {
+        let archive = dest_dir(&[&format!("symlinks_archive.{}", ext)]);
+        remove_dest(&archive)?;
+
+        let args = [
+            "files",
+            "get",
+            &files_container_xor,
+            "--archive",
+            &archive,
+            "--jobs",
+            "2",
+            "--progress=none",
+        ];
+        safe_cmd_stdout(&args, Some(0))?;
+
+        let args = ["files", "put", &archive, "--archive", "--json"];
+        let stdout = safe_cmd_stdout(&args, Some(0))?;
+        let (new_container_xor, _processed_files) = parse_files_put_or_sync_output(&stdout);
+        assert_eq!(symlinks_of(&new_container_xor)?, symlinks);
+
+        let dest = dest_dir(&[&format!("from_symlinks_archive_{}", ext)]);
+        remove_dest(&dest)?;
+        files_get(
+            &new_container_xor,
+            Some(&dest),
+            Some(EXISTS_OVERWRITE),
+            Some(PROGRESS_NONE),
+            Some(0),
+        )?;
+        assert_eq!(sum_tree(&path)?, sum_tree(&dest)?);
+
+        remove_dest(&archive)?;
+        remove_dest(&dest)?;
+    }
+
+    Ok(())
+}
+
+// Test:  safe files get <url> --archive /tmp/long_symlink.tar
+//        safe files put /tmp/long_symlink.tar --archive
+//    src is a dir with a symlink whose target is longer than 100 bytes
+//
+//    expected result:
+//        the symlink of the new container keeps its whole target
+#[test]
+fn files_get_archive_long_symlink_target() -> Result<()> {
+    if !can_write_symlinks() {
+        return Ok(());
+    }
+
+    let (tmp_dir, folder) = mk_emptyfolder("long_symlink")?;
+    let subdir = format!("{}_{}", "a".repeat(60), "b".repeat(60));
+    fs::create_dir(Path::new(&folder).join(&subdir)).map_err(|e| anyhow!(e.to_string()))?;
+    fs::write(Path::new(&folder).join(&subdir).join("file.txt"), "long")
+        .map_err(|e| anyhow!(e.to_string()))?;
+    let target = Path::new(&subdir).join("file.txt");
+    create_symlink(&target, &Path::new(&folder).join("link"), false)
+        .map_err(|e| anyhow!(e.to_string()))?;
+
+    let (files_container_xor, _processed_files, _path) = upload_path(&folder, false)?;
+
+    let archive = dest_dir(&["long_symlink.tar"]);
+    remove_dest(&archive)?;
+    let args = [
+        "files",
+        "get",
+        &files_container_xor,
+        "--archive",
+        &archive,
+        "--progress=none",
+    ];
+    safe_cmd_stdout(&args, Some(0))?;
+
+    let args = ["files", "put", &archive, "--archive", "--json"];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (new_container_xor, _processed_files) = parse_files_put_or_sync_output(&stdout);
+
+    let stdout = safe_cmd_stdout(&["cat", &new_container_xor, "--json"], Some(0))?;
+    let (_url, files_map) = parse_files_container_output(&stdout);
+    let symlink_target = files_map
+        .values()
+        .find(|details| details["type"] == "inode/symlink")
+        .and_then(|details| details.get("symlink_target").cloned());
+    assert_eq!(symlink_target, Some(target.display().to_string()));
+
+    remove_dest(&archive)?;
+    fs::remove_dir_all(&tmp_dir).map_err(|e| anyhow!(e.to_string()))?;
+    Ok(())
+}
+
+// Test:  safe files get <url> /tmp/json_report --json --progress=text
+//    src is a dir, retrieved with a JSON output format
+//
+//    expected result:
+//        --exists=ask is rejected, otherwise the report is printed to stdout
+//        and each line of stderr is a JSON progress event
+#[test]
+fn files_get_json_report() -> Result<()> {
+    let (files_container_xor, _processed_files) = upload_testfolder_no_trailing_slash()?;
+
+    let src = source_path(&files_container_xor, &[TESTDATA])?;
+    let dest = dest_dir(&["json_report"]);
+    remove_dest(&dest)?;
+
+    let stderr = safe_cmd_stderr(&["files", "get", &src, &dest, "--json"], Some(1))?;
+    assert!(stderr.contains("--exists=ask"));
+
+    let args = [
+        "files",
+        "get",
+        &src,
+        &dest,
+        "--exists=overwrite",
+        "--progress=text",
+        "--json",
+    ];
+    let output = safe_cmd(&args, Some(0))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(report["url"].as_str().unwrap_or("").contains("?v="));
+    assert_eq!(report["dest"], dest.as_str());
+    assert!(report["processed_files"].is_object());
+    assert_eq!(report["overwrites"], 0);
+    assert_eq!(report["preserves"], 0);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    for line in stderr.lines() {
+        let event: serde_json::Value =
+            serde_json::from_str(line).map_err(|e| anyhow!(e.to_string()))?;
+        assert!(event["path"].is_string());
+    }
+    assert_eq!(sum_tree(TEST_FOLDER)?, sum_tree(&dest)?);
+
+    remove_dest(&dest)?;
+
+    Ok(())
+}
+
+// Test:  safe files sync --pull <url> /tmp/pull_checkout --delete
+//    a local checkout is synced down with a container, which is then
+//    updated with a new file and a removed file, and synced down again.
+//
+//    expected result: the checkout matches the container's source folder
+//    after each pull, and the removed file is deleted from the checkout.
+#[test]
+fn files_sync_pull() -> Result<()> {
+    let (tmp_dir, folder) = mk_emptyfolder("pull_source")?;
+    let folder_path = Path::new(&folder);
+    fs::write(folder_path.join("kept.txt"), "kept").map_err(|e| anyhow!(e.to_string()))?;
+    fs::write(folder_path.join("removed.txt"), "removed").map_err(|e| anyhow!(e.to_string()))?;
+
+    let args = ["files", "put", &folder, "-r", "--json"];
+    let stdout = safe_cmd_stdout(&args, Some(0))?;
+    let (files_container_xor, _processed_files) = parse_files_put_or_sync_output(&stdout);
+
+    let dest = dest_dir(&["pull_checkout"]);
+    remove_dest(&dest)?;
+    let pull_args = [
+        "files",
+        "sync",
+        "--pull",
+        &files_container_xor,
+        &dest,
+        "--delete",
+        "--exists=overwrite",
+        "--json",
+    ];
+    safe_cmd_stdout(&pull_args, Some(0))?;
+    assert_eq!(sum_tree(&folder)?, sum_tree(&dest)?);
+
+    fs::write(folder_path.join("added.txt"), "added").map_err(|e| anyhow!(e.to_string()))?;
+    fs::remove_file(folder_path.join("removed.txt")).map_err(|e| anyhow!(e.to_string()))?;
+    let args = [
+        "files",
+        "sync",
+        &folder,
+        &files_container_xor,
+        "-r",
+        "--delete",
+        "--json",
+    ];
+    safe_cmd_stdout(&args, Some(0))?;
+
+    let stdout = safe_cmd_stdout(&pull_args, Some(0))?;
+    let (_url, processed_files) = parse_files_put_or_sync_output(&stdout);
+    assert_eq!(processed_files["/added.txt"].0, "+");
+    assert_eq!(processed_files["/removed.txt"].0, "-");
+    assert!(!processed_files.contains_key("/kept.txt"));
+    assert_eq!(sum_tree(&folder)?, sum_tree(&dest)?);
+
+    remove_dest(&dest)?;
+    fs::remove_dir_all(&tmp_dir).map_err(|e| anyhow!(e.to_string()))?;
+
+    Ok(())
+}
+
+// Test:  safe files sync --pull <url> /tmp/pull_default_exists --jobs 1
+//    a local checkout whose file was changed is synced down again,
+//    with a pretty output and no --exists argument.
+//
+//    expected result: the local file is overwritten without prompting
+#[test]
+fn files_sync_pull_overwrites_by_default() -> Result<()> {
+    let (files_container_xor, _processed_files) = upload_testfolder_trailing_slash()?;
+
+    let dest = dest_dir(&["pull_default_exists"]);
+    remove_dest(&dest)?;
+    let pull_args = ["files", "sync", "--pull", &files_container_xor, &dest];
+    safe_cmd_stdout(&pull_args, Some(0))?;
+    assert_eq!(sum_tree(TEST_FOLDER)?, sum_tree(&dest)?);
+
+    let changed = Path::new(&dest).join("test.md");
+    fs::write(&changed, "changed locally").map_err(|e| anyhow!(e.to_string()))?;
+    let args = [
+        "files",
+        "sync",
+        "--pull",
+        &files_container_xor,
+        &dest,
+        "--jobs",
+        "1",
+    ];
+    safe_cmd_stdout(&args, Some(0))?;
+    assert_eq!(sum_tree(TEST_FOLDER)?, sum_tree(&dest)?);
+
+    remove_dest(&dest)?;
+
+    Ok(())
+}
+
+// ----------------------------------------
+// Resume Tests
+// ----------------------------------------
+
+// Test:  safe files get ./testdata /tmp/testdata --resume
+//    src is a dir
+//    dest exists, and is a dir. no transfer journal exists.
+//
+//    expected result: ./testdata matches /tmp/testdata/testdata
+//    and no transfer journal is left behind.
+#[test]
+fn files_get_resume_without_journal() -> Result<()> {
+    let (files_container_xor, _processed_files) = upload_testfolder_no_trailing_slash()?;
+
+    let src = source_path(&files_container_xor, &[TESTDATA])?;
+    let dest = dest_dir(&[TESTDATA]);
+    let final_dest = dest_dir(&[TESTDATA, TESTDATA]);
+
+    remove_dest(&dest)?;
+    fs::create_dir_all(&dest).map_err(|e| anyhow!(e.to_string()))?;
+
+    let args = [
+        "files",
+        "get",
+        &src,
+        &dest,
+        "--exists=overwrite",
+        "--progress=none",
+        "--resume",
+    ];
+    safe_cmd_stdout(&args, Some(0))?;
+
+    assert!(!Path::new(&final_dest).join(JOURNAL_FILE_NAME).exists());
+    assert_eq!(sum_tree(TEST_FOLDER)?, sum_tree(&final_dest)?);
+
+    Ok(())
+}
+
+// Test:  safe files get ./testdata /tmp/testdata --resume
+//    src is a dir
+//    dest exists, and is a dir. a transfer journal for another
+//    version of the FilesContainer exists in /tmp/testdata/testdata
+//
+//    expected result:
+//        exit code = 1 and
+//        stderr contains: "has changed since the interrupted transfer"
+#[test]
+fn files_get_resume_container_version_changed() -> Result<()> {
+    let (files_container_xor, _processed_files) = upload_testfolder_no_trailing_slash()?;
+
+    let src = source_path(&files_container_xor, &[TESTDATA])?;
+    let dest = dest_dir(&[TESTDATA]);
+    let final_dest = dest_dir(&[TESTDATA, TESTDATA]);
+
+    remove_dest(&dest)?;
+    fs::create_dir_all(&final_dest).map_err(|e| anyhow!(e.to_string()))?;
+
+    let journal = format!(r#"{{"source":"{}","version":999,"files":{{}}}}"#, src);
+    fs::write(Path::new(&final_dest).join(JOURNAL_FILE_NAME), journal)
+        .map_err(|e| anyhow!(e.to_string()))?;
+
+    let args = [
+        "files",
+        "get",
+        &src,
+        &dest,
+        "--exists=overwrite",
+        "--progress=none",
+        "--resume",
+    ];
+    let stderr = safe_cmd_stderr(&args, Some(1))?;
+    assert!(stderr.contains("has changed since the interrupted transfer"));
+
+    remove_dest(&dest)?;
+
+    Ok(())
+}
+
+// Test:  safe files get ./testdata /tmp/testdata_resume --resume
+//    src is a dir
+//    dest exists, and is a dir. a transfer journal left by an interrupted
+//    transfer reports test.md as complete, and another.md as started
+//    but with no range written yet, the file being empty.
+//
+//    expected result: test.md is not retrieved again, another.md is
+//    completed, and the transfer journal is removed.
+#[test]
+fn files_get_resume_interrupted_transfer() -> Result<()> {
+    let (files_container_xor, processed_files) = upload_testfolder_no_trailing_slash()?;
+
+    let src = source_path(&files_container_xor, &[TESTDATA])?;
+    let dest = dest_dir(&["testdata_resume"]);
+    let final_dest = dest_dir(&["testdata_resume", TESTDATA]);
+
+    remove_dest(&dest)?;
+    fs::create_dir_all(&final_dest).map_err(|e| anyhow!(e.to_string()))?;
+
+    // test.md was completely written, with content we can tell apart
+    // from the original one, and another.md was only created.
+    let test_md_size = fs::metadata(join_paths(&[TESTDATA, "test.md"]))
+        .map_err(|e| anyhow!(e.to_string()))?
+        .len();
+    let interrupted_content = "x".repeat(test_md_size as usize);
+    fs::write(Path::new(&final_dest).join("test.md"), &interrupted_content)
+        .map_err(|e| anyhow!(e.to_string()))?;
+    fs::write(Path::new(&final_dest).join("another.md"), "").map_err(|e| anyhow!(e.to_string()))?;
+    let another_md_size = fs::metadata(join_paths(&[TESTDATA, "another.md"]))
+        .map_err(|e| anyhow!(e.to_string()))?
+        .len();
+
+    let journal = format!(
+        r#"{{"source":"{}","version":0,"files":{{"/test.md":{{"link":"{}","size":{},"complete":true,"ranges":[]}},"/another.md":{{"link":"{}","size":{},"complete":false,"ranges":[]}}}}}}"#,
+        src,
+        processed_files["./testdata/test.md"].1,
+        test_md_size,
+        processed_files["./testdata/another.md"].1,
+        another_md_size
+    );
+    fs::write(Path::new(&final_dest).join(JOURNAL_FILE_NAME), journal)
+        .map_err(|e| anyhow!(e.to_string()))?;
+
+    let args = [
+        "files",
+        "get",
+        &src,
+        &dest,
+        "--exists=overwrite",
+        "--progress=none",
+        "--resume",
+    ];
+    safe_cmd_stdout(&args, Some(0))?;
+
+    assert!(!Path::new(&final_dest).join(JOURNAL_FILE_NAME).exists());
+    let test_md = fs::read_to_string(Path::new(&final_dest).join("test.md"))
+        .map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(test_md, interrupted_content);
+    assert_eq!(
+        digest_file(&join_paths(&[&final_dest, "another.md"]))?,
+        digest_file(&join_paths(&[TESTDATA, "another.md"]))?
+    );
+
+    remove_dest(&dest)?;
+
+    Ok(())
+}
+
 // recursively removes a directory, or a file.
 // intended for removal of dir/files downloaded
 // by 'safe files get' test cases.